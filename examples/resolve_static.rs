@@ -1,5 +1,5 @@
 use std::fs;
-use typst::foundations::{Bytes, Smart};
+use typst::foundations::Bytes;
 use typst::text::Font;
 use typst_as_lib::TypstTemplate;
 
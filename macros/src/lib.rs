@@ -0,0 +1,85 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use proc_macro::TokenStream;
+
+/// Embeds a template directory (relative to the crate root) at compile time and expands to
+/// a `typst_as_lib::file_resolver::EmbeddedResolver`. `.typ` files are embedded as sources,
+/// everything else as binaries. The virtual paths are relative to the directory.
+///
+/// ```rust,ignore
+/// let template = TypstTemplate::new(fonts, ("/main.typ", MAIN))
+///     .add_file_resolver(embed_templates!("./templates"));
+/// ```
+#[proc_macro]
+pub fn embed_templates(input: TokenStream) -> TokenStream {
+    match expand(input) {
+        Ok(expanded) => expanded,
+        Err(message) => format!("::core::compile_error!({message:?})")
+            .parse()
+            .unwrap(),
+    }
+}
+
+fn expand(input: TokenStream) -> Result<TokenStream, String> {
+    let dir = parse_str_literal(input)?;
+    let manifest_dir =
+        std::env::var("CARGO_MANIFEST_DIR").map_err(|_| "CARGO_MANIFEST_DIR is not set")?;
+    let root = Path::new(&manifest_dir).join(dir);
+    let mut files = Vec::new();
+    collect_files(&root, &mut files)
+        .map_err(|err| format!("Could not read {}: {err}", root.display()))?;
+    files.sort();
+
+    let mut sources = String::new();
+    let mut binaries = String::new();
+    for file in files {
+        let relative = file.strip_prefix(&root).map_err(|err| err.to_string())?;
+        let vpath = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let vpath = format!("/{vpath}");
+        let absolute = file.to_string_lossy();
+        if file.extension().is_some_and(|ext| ext == "typ") {
+            sources.push_str(&format!("({vpath:?}, ::core::include_str!({absolute:?})),"));
+        } else {
+            binaries.push_str(&format!(
+                "({vpath:?}, ::core::include_bytes!({absolute:?}) as &'static [u8]),"
+            ));
+        }
+    }
+    format!("::typst_as_lib::file_resolver::EmbeddedResolver::new(&[{sources}], &[{binaries}])")
+        .parse()
+        .map_err(|err| format!("{err:?}"))
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn parse_str_literal(input: TokenStream) -> Result<String, String> {
+    let literal = input.to_string();
+    let literal = literal.trim();
+    let unquoted = if let Some(raw) = literal.strip_prefix('r') {
+        raw.trim_matches('#')
+            .strip_prefix('"')
+            .and_then(|s| s.strip_suffix('"'))
+    } else {
+        literal.strip_prefix('"').and_then(|s| s.strip_suffix('"'))
+    };
+    unquoted
+        .map(|s| s.replace("\\\\", "\\").replace("\\\"", "\""))
+        .ok_or_else(|| "embed_templates! expects a string literal".to_owned())
+}
@@ -0,0 +1,174 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    io::{Cursor, Read},
+    path::{Path, PathBuf},
+};
+
+use ecow::eco_format;
+use typst::{
+    diag::{FileError, FileResult},
+    foundations::Bytes,
+    syntax::{FileId, Source, VirtualPath},
+};
+
+use crate::{
+    file_resolver::FileResolver,
+    util::{bytes_to_source, not_found},
+};
+
+/// Resolves local files (when `package` is not set in `FileId`) from a zip (feature `zip`)
+/// or tar archive (feature `tar`, optionally gzip compressed), e.g. a template bundle.
+/// All files of the archive are read into memory, when the resolver is created.
+///
+/// ```rust,ignore
+/// let resolver = ArchiveResolver::from_zip_file("bundles/invoice.zip")?.with_root("invoice");
+/// let collection = TypstTemplateCollection::new(fonts).add_file_resolver(resolver);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ArchiveResolver {
+    files: HashMap<VirtualPath, Bytes>,
+}
+
+impl ArchiveResolver {
+    /// Read a zip archive from memory.
+    #[cfg(feature = "zip")]
+    pub fn from_zip<B>(data: B) -> FileResult<Self>
+    where
+        B: AsRef<[u8]>,
+    {
+        let mut archive = zip::ZipArchive::new(Cursor::new(data.as_ref())).map_err(zip_error)?;
+        let mut files = HashMap::new();
+        for index in 0..archive.len() {
+            let mut file = archive.by_index(index).map_err(zip_error)?;
+            let Some(path) = file.enclosed_name().filter(|_| file.is_file()) else {
+                continue;
+            };
+            let mut buf = Vec::new();
+            file.read_to_end(&mut buf)
+                .map_err(|error| archive_error(eco_format!("{error}")))?;
+            files.insert(VirtualPath::new(path), Bytes::from(buf));
+        }
+        Ok(Self { files })
+    }
+
+    /// Read a zip archive from disk.
+    #[cfg(feature = "zip")]
+    pub fn from_zip_file<P>(path: P) -> FileResult<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Self::from_zip(read(path.as_ref())?)
+    }
+
+    /// Read a tar archive from memory. Gzip compressed archives (`.tar.gz`) are detected
+    /// automatically.
+    #[cfg(feature = "tar")]
+    pub fn from_tar<B>(data: B) -> FileResult<Self>
+    where
+        B: AsRef<[u8]>,
+    {
+        let data = data.as_ref();
+        let data: Cow<[u8]> = if data.starts_with(&[0x1f, 0x8b]) {
+            let mut decoded = Vec::new();
+            flate2::read::GzDecoder::new(data)
+                .read_to_end(&mut decoded)
+                .map_err(|error| archive_error(eco_format!("{error}")))?;
+            Cow::Owned(decoded)
+        } else {
+            Cow::Borrowed(data)
+        };
+        let mut archive = binstall_tar::Archive::new(Cursor::new(data.as_ref()));
+        let entries = archive
+            .entries()
+            .map_err(|error| archive_error(eco_format!("{error}")))?;
+        let mut files = HashMap::new();
+        for entry in entries {
+            let mut entry = entry.map_err(|error| archive_error(eco_format!("{error}")))?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let path = entry
+                .path()
+                .map_err(|error| archive_error(eco_format!("{error}")))?
+                .into_owned();
+            let mut buf = Vec::new();
+            entry
+                .read_to_end(&mut buf)
+                .map_err(|error| archive_error(eco_format!("{error}")))?;
+            files.insert(VirtualPath::new(path), Bytes::from(buf));
+        }
+        Ok(Self { files })
+    }
+
+    /// Read a tar archive from disk. See `from_tar`.
+    #[cfg(feature = "tar")]
+    pub fn from_tar_file<P>(path: P) -> FileResult<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Self::from_tar(read(path.as_ref())?)
+    }
+
+    /// Only serve the files below `root` of the archive, relative to `root`. Useful for
+    /// archives, that contain a single top level directory.
+    pub fn with_root<P>(self, root: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        let root = VirtualPath::new(root.as_ref());
+        let root = root.as_rootless_path();
+        let files = self
+            .files
+            .into_iter()
+            .filter_map(|(path, bytes)| {
+                let relative = path.as_rootless_path().strip_prefix(root).ok()?;
+                Some((VirtualPath::new(relative), bytes))
+            })
+            .collect();
+        Self { files }
+    }
+
+    /// Paths of all files in the archive.
+    pub fn paths(&self) -> impl Iterator<Item = PathBuf> + '_ {
+        self.files
+            .keys()
+            .map(|path| path.as_rooted_path().to_path_buf())
+    }
+
+    fn get(&self, id: FileId) -> FileResult<&Bytes> {
+        if id.package().is_some() {
+            return Err(not_found(id));
+        }
+        self.files.get(id.vpath()).ok_or_else(|| not_found(id))
+    }
+}
+
+impl FileResolver for ArchiveResolver {
+    fn resolve_binary(&self, id: FileId) -> FileResult<Cow<'_, Bytes>> {
+        self.get(id).map(Cow::Borrowed)
+    }
+
+    fn resolve_source(&self, id: FileId) -> FileResult<Cow<'_, Source>> {
+        let bytes = self.get(id)?;
+        let source = bytes_to_source(id, bytes)?;
+        Ok(Cow::Owned(source))
+    }
+
+    fn is_in_memory(&self) -> bool {
+        true
+    }
+}
+
+fn read(path: &Path) -> FileResult<Vec<u8>> {
+    std::fs::read(path).map_err(|error| FileError::from_io(error, path))
+}
+
+fn archive_error(message: ecow::EcoString) -> FileError {
+    FileError::Other(Some(eco_format!("Could not read archive: {message}")))
+}
+
+#[cfg(feature = "zip")]
+fn zip_error(error: zip::result::ZipError) -> FileError {
+    archive_error(eco_format!("{error}"))
+}
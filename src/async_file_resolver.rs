@@ -0,0 +1,138 @@
+//! Resolve files from async sources (e.g. S3 or a database) without blocking
+//! inside of the compile (feature `async`).
+//!
+//! Compiling is synchronous, so the files of an `AsyncFileResolver` are fetched before the
+//! compile and buffered by a `PrefetchResolver`. Files, that were requested during a compile,
+//! but were not fetched yet, are recorded and can be fetched with `prefetch_missing`:
+//!
+//! ```rust,ignore
+//! let resolver = PrefetchResolver::new(S3Resolver::new(bucket));
+//! resolver.prefetch([template_id]).await;
+//! let collection = Arc::new(TypstTemplateCollection::new(fonts).add_file_resolver(resolver.clone()));
+//! let doc = loop {
+//!     let doc = collection.compile_async(template_id).await;
+//!     // Fetch the files, that are imported by the template, and compile again.
+//!     if !resolver.prefetch_missing().await {
+//!         break doc;
+//!     }
+//! };
+//! ```
+
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    future::Future,
+    pin::Pin,
+    sync::{Arc, RwLock},
+};
+
+use ecow::eco_format;
+use typst::{
+    diag::{FileError, FileResult},
+    foundations::Bytes,
+    syntax::{FileId, Source},
+};
+
+use crate::{
+    file_resolver::FileResolver,
+    util::{bytes_to_source, not_found},
+};
+
+/// Future, that is returned by `AsyncFileResolver::resolve`.
+pub type ResolveFuture<'a> = Pin<Box<dyn Future<Output = FileResult<Bytes>> + Send + 'a>>;
+
+/// Async counterpart of `FileResolver`. Sources are resolved as binaries and decoded as utf-8.
+/// Return `FileError::NotFound` for files, that the resolver does not know.
+pub trait AsyncFileResolver: Send + Sync {
+    fn resolve(&self, id: FileId) -> ResolveFuture<'_>;
+}
+
+/// Bridges an `AsyncFileResolver` into the resolvers of a collection by buffering the
+/// fetched files. Cloning is cheap, all clones share the same buffer.
+#[derive(Clone)]
+pub struct PrefetchResolver {
+    resolver: Arc<dyn AsyncFileResolver>,
+    files: Arc<RwLock<HashMap<FileId, FileResult<Bytes>>>>,
+    missing: Arc<RwLock<HashSet<FileId>>>,
+}
+
+impl PrefetchResolver {
+    pub fn new<R>(resolver: R) -> Self
+    where
+        R: AsyncFileResolver + 'static,
+    {
+        Self {
+            resolver: Arc::new(resolver),
+            files: Default::default(),
+            missing: Default::default(),
+        }
+    }
+
+    /// Fetch the files with `ids`, that were not fetched yet. Errors are buffered as well and
+    /// returned, when the file is resolved during the compile.
+    pub async fn prefetch<I>(&self, ids: I)
+    where
+        I: IntoIterator<Item = FileId>,
+    {
+        for id in ids {
+            let fetched = self.files.read().is_ok_and(|files| files.contains_key(&id));
+            if fetched {
+                continue;
+            }
+            let file = self.resolver.resolve(id).await;
+            if let Ok(mut files) = self.files.write() {
+                files.insert(id, file);
+            }
+        }
+    }
+
+    /// Fetch the files, that were requested during a compile, but were not fetched yet.
+    /// Returns `false`, if there were no such files, so compiling again would not change
+    /// the result.
+    pub async fn prefetch_missing(&self) -> bool {
+        let missing: Vec<FileId> = match self.missing.write() {
+            Ok(mut missing) => missing.drain().collect(),
+            Err(_) => return false,
+        };
+        if missing.is_empty() {
+            return false;
+        }
+        self.prefetch(missing).await;
+        true
+    }
+
+    /// Drop all buffered files, e.g. to fetch changed files again.
+    pub fn clear(&self) {
+        if let Ok(mut files) = self.files.write() {
+            files.clear();
+        }
+    }
+
+    fn get(&self, id: FileId) -> FileResult<Bytes> {
+        let files = self.files.read().map_err(|_| lock_error())?;
+        match files.get(&id) {
+            Some(file) => file.clone(),
+            None => {
+                if let Ok(mut missing) = self.missing.write() {
+                    missing.insert(id);
+                }
+                Err(not_found(id))
+            }
+        }
+    }
+}
+
+impl FileResolver for PrefetchResolver {
+    fn resolve_binary(&self, id: FileId) -> FileResult<Cow<'_, Bytes>> {
+        self.get(id).map(Cow::Owned)
+    }
+
+    fn resolve_source(&self, id: FileId) -> FileResult<Cow<'_, Source>> {
+        let bytes = self.get(id)?;
+        bytes_to_source(id, &bytes).map(Cow::Owned)
+    }
+}
+
+fn lock_error() -> FileError {
+    FileError::Other(Some(eco_format!("Could not lock prefetch buffer")))
+}
@@ -1,7 +1,12 @@
 use std::{
     borrow::Cow,
     collections::HashMap,
-    sync::{Arc, Mutex},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
 };
 
 use typst::{
@@ -10,85 +15,246 @@ use typst::{
     syntax::{FileId, Source},
 };
 
-use crate::file_resolver::FileResolver;
+use crate::{
+    file_resolver::{FileResolver, PackageFailure},
+    metrics::ResolverStats,
+};
+
+/// Storage for the files of a `CachedFileResolver`. The built-in `FileCache` keeps the files
+/// in memory of the process, other backends (e.g. Redis or a disk cache) can share them
+/// between processes.
+pub trait CacheBackend<V> {
+    fn get(&self, id: FileId) -> Option<V>;
+
+    fn put(&self, id: FileId, value: &V);
+
+    fn invalidate(&self, id: FileId);
+
+    fn clear(&self);
+
+    /// Number of cached files, if known.
+    fn cached_files(&self) -> usize {
+        0
+    }
+
+    /// Size of the cached files in bytes, if known.
+    fn cached_bytes(&self) -> usize {
+        0
+    }
+}
 
-pub struct CachedFileResolver<T> {
+/// File resolver, that caches the files of `file_resolver` in `source_cache` and
+/// `binary_cache`. By default, both are in memory `FileCache`s.
+pub struct CachedFileResolver<T, S = FileCache<Source>, B = FileCache<Bytes>> {
     pub file_resolver: T,
-    pub in_memory_source_cache: Option<Arc<Mutex<HashMap<FileId, Source>>>>,
-    pub in_memory_binary_cache: Option<Arc<Mutex<HashMap<FileId, Bytes>>>>,
+    pub source_cache: Option<S>,
+    pub binary_cache: Option<B>,
+    hits: AtomicU64,
+    misses: AtomicU64,
 }
 
 impl<T> CachedFileResolver<T> {
     pub fn new(file_resolver: T) -> Self {
         CachedFileResolver {
             file_resolver,
-            in_memory_source_cache: None,
-            in_memory_binary_cache: None,
+            source_cache: None,
+            binary_cache: None,
+            hits: Default::default(),
+            misses: Default::default(),
         }
     }
 
     pub fn with_in_memory_source_cache(self) -> Self {
         Self {
-            in_memory_source_cache: Some(Default::default()),
+            source_cache: Some(Default::default()),
             ..self
         }
     }
 
     pub fn with_in_memory_binary_cache(self) -> Self {
         Self {
-            in_memory_binary_cache: Some(Default::default()),
+            binary_cache: Some(Default::default()),
             ..self
         }
     }
+
+    /// Limit the number of cached files (of each of the enabled caches).
+    /// The least recently used files are evicted first.
+    pub fn with_max_entries(self, max_entries: usize) -> Self {
+        self.update_limits(|limits| limits.max_entries = Some(max_entries))
+    }
+
+    /// Limit the size of the cached files in bytes (of each of the enabled caches).
+    /// The least recently used files are evicted first.
+    pub fn with_max_bytes(self, max_bytes: usize) -> Self {
+        self.update_limits(|limits| limits.max_bytes = Some(max_bytes))
+    }
+
+    /// Resolve cached files again, after they have been cached for `ttl`.
+    pub fn with_ttl(self, ttl: Duration) -> Self {
+        self.update_limits(|limits| limits.ttl = Some(ttl))
+    }
+
+    fn update_limits<F>(self, f: F) -> Self
+    where
+        F: Fn(&mut CacheLimits),
+    {
+        if let Some(cache) = &self.source_cache {
+            cache.update_limits(&f);
+        }
+        if let Some(cache) = &self.binary_cache {
+            cache.update_limits(&f);
+        }
+        self
+    }
 }
 
-impl<T> FileResolver for CachedFileResolver<T>
-where
-    T: FileResolver,
-{
-    fn resolve_binary(&self, id: FileId) -> FileResult<Cow<Bytes>> {
+impl<T, S, B> CachedFileResolver<T, S, B> {
+    /// Cache sources in `cache` instead of the built-in in memory cache.
+    pub fn with_source_cache<S1>(self, cache: S1) -> CachedFileResolver<T, S1, B>
+    where
+        S1: CacheBackend<Source>,
+    {
         let Self {
-            in_memory_binary_cache,
+            file_resolver,
+            binary_cache,
+            hits,
+            misses,
             ..
         } = self;
+        CachedFileResolver {
+            file_resolver,
+            source_cache: Some(cache),
+            binary_cache,
+            hits,
+            misses,
+        }
+    }
+
+    /// Cache binary files in `cache` instead of the built-in in memory cache.
+    pub fn with_binary_cache<B1>(self, cache: B1) -> CachedFileResolver<T, S, B1>
+    where
+        B1: CacheBackend<Bytes>,
+    {
+        let Self {
+            file_resolver,
+            source_cache,
+            hits,
+            misses,
+            ..
+        } = self;
+        CachedFileResolver {
+            file_resolver,
+            source_cache,
+            binary_cache: Some(cache),
+            hits,
+            misses,
+        }
+    }
+
+    fn count(&self, hit: bool) {
+        let counter = if hit { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
 
-        if let Some(in_memory_binary_cache) = in_memory_binary_cache {
-            if let Ok(in_memory_binary_cache) = in_memory_binary_cache.lock() {
-                if let Some(cached) = in_memory_binary_cache.get(&id) {
-                    return Ok(Cow::Owned(cached.clone()));
-                }
+impl<T, S, B> FileResolver for CachedFileResolver<T, S, B>
+where
+    T: FileResolver,
+    S: CacheBackend<Source>,
+    B: CacheBackend<Bytes>,
+{
+    fn resolve_binary(&self, id: FileId) -> FileResult<Cow<'_, Bytes>> {
+        let Self { binary_cache, .. } = self;
+
+        if let Some(binary_cache) = binary_cache {
+            let cached = binary_cache.get(id);
+            self.count(cached.is_some());
+            if let Some(cached) = cached {
+                return Ok(Cow::Owned(cached));
             }
         }
         let resolved = self.file_resolver.resolve_binary(id)?;
-        if let Some(in_memory_binary_cache) = in_memory_binary_cache {
-            if let Ok(mut in_memory_binary_cache) = in_memory_binary_cache.lock() {
-                in_memory_binary_cache.insert(id, resolved.as_ref().clone());
-            }
+        if let Some(binary_cache) = binary_cache {
+            binary_cache.put(id, &resolved);
         }
         Ok(resolved)
     }
 
-    fn resolve_source(&self, id: FileId) -> FileResult<Cow<Source>> {
-        let Self {
-            in_memory_source_cache,
-            ..
-        } = self;
+    fn resolve_source(&self, id: FileId) -> FileResult<Cow<'_, Source>> {
+        let Self { source_cache, .. } = self;
 
-        if let Some(in_memory_source_cache) = in_memory_source_cache {
-            if let Ok(in_memory_source_cache) = in_memory_source_cache.lock() {
-                if let Some(cached) = in_memory_source_cache.get(&id) {
-                    return Ok(Cow::Owned(cached.clone()));
-                }
+        if let Some(source_cache) = source_cache {
+            let cached = source_cache.get(id);
+            self.count(cached.is_some());
+            if let Some(cached) = cached {
+                return Ok(Cow::Owned(cached));
             }
         }
         let resolved = self.file_resolver.resolve_source(id)?;
-        if let Some(in_memory_source_cache) = in_memory_source_cache {
-            if let Ok(mut in_memory_source_cache) = in_memory_source_cache.lock() {
-                in_memory_source_cache.insert(id, resolved.as_ref().clone());
-            }
+        if let Some(source_cache) = source_cache {
+            source_cache.put(id, &resolved);
         }
         Ok(resolved)
     }
+
+    fn resolve_path(&self, id: FileId) -> Option<PathBuf> {
+        self.file_resolver.resolve_path(id)
+    }
+
+    fn is_in_memory(&self) -> bool {
+        self.file_resolver.is_in_memory()
+    }
+
+    fn invalidate(&self, id: FileId) {
+        if let Some(cache) = &self.source_cache {
+            cache.invalidate(id);
+        }
+        if let Some(cache) = &self.binary_cache {
+            cache.invalidate(id);
+        }
+        self.file_resolver.invalidate(id);
+    }
+
+    fn clear_cache(&self) {
+        if let Some(cache) = &self.source_cache {
+            cache.clear();
+        }
+        if let Some(cache) = &self.binary_cache {
+            cache.clear();
+        }
+        self.file_resolver.clear_cache();
+    }
+
+    fn stats(&self) -> ResolverStats {
+        let Self {
+            file_resolver,
+            source_cache,
+            binary_cache,
+            hits,
+            misses,
+        } = self;
+        let inner = file_resolver.stats();
+        let (source_files, source_bytes) = source_cache
+            .as_ref()
+            .map(|cache| (cache.cached_files(), cache.cached_bytes()))
+            .unwrap_or_default();
+        let (binary_files, binary_bytes) = binary_cache
+            .as_ref()
+            .map(|cache| (cache.cached_files(), cache.cached_bytes()))
+            .unwrap_or_default();
+        ResolverStats {
+            cache_hits: inner.cache_hits + hits.load(Ordering::Relaxed),
+            cache_misses: inner.cache_misses + misses.load(Ordering::Relaxed),
+            cached_files: inner.cached_files + source_files + binary_files,
+            cached_bytes: inner.cached_bytes + source_bytes + binary_bytes,
+            package_downloads: inner.package_downloads,
+        }
+    }
+
+    fn package_failure(&self, id: FileId) -> Option<PackageFailure> {
+        self.file_resolver.package_failure(id)
+    }
 }
 
 pub trait IntoCachedFileResolver {
@@ -96,3 +262,240 @@ pub trait IntoCachedFileResolver {
     where
         Self: Sized;
 }
+
+/// Limits of a `FileCache`. No limits are set by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheLimits {
+    pub max_entries: Option<usize>,
+    pub max_bytes: Option<usize>,
+    pub ttl: Option<Duration>,
+}
+
+/// In memory cache of a `CachedFileResolver`, that evicts the least recently used files,
+/// when one of its `CacheLimits` is exceeded. Cloning is cheap, all clones share the same
+/// files.
+pub struct FileCache<V> {
+    state: Arc<Mutex<FileCacheState<V>>>,
+}
+
+struct FileCacheState<V> {
+    entries: HashMap<FileId, CacheEntry<V>>,
+    limits: CacheLimits,
+    bytes: usize,
+    /// Incremented on each access, to find the least recently used entry.
+    clock: u64,
+}
+
+struct CacheEntry<V> {
+    value: V,
+    size: usize,
+    inserted: Instant,
+    last_used: u64,
+}
+
+impl<V> Default for FileCache<V> {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(FileCacheState {
+                entries: HashMap::new(),
+                limits: CacheLimits::default(),
+                bytes: 0,
+                clock: 0,
+            })),
+        }
+    }
+}
+
+impl<V> Clone for FileCache<V> {
+    fn clone(&self) -> Self {
+        Self {
+            state: Arc::clone(&self.state),
+        }
+    }
+}
+
+impl<V> FileCache<V> {
+    pub fn new(limits: CacheLimits) -> Self {
+        let cache = Self::default();
+        cache.update_limits(|l| *l = limits);
+        cache
+    }
+
+    pub fn limits(&self) -> CacheLimits {
+        self.state
+            .lock()
+            .map(|state| state.limits)
+            .unwrap_or_default()
+    }
+
+    /// Changes the limits and evicts files, until the new limits are met.
+    pub fn update_limits<F>(&self, f: F)
+    where
+        F: FnOnce(&mut CacheLimits),
+    {
+        if let Ok(mut state) = self.state.lock() {
+            f(&mut state.limits);
+            state.evict();
+        }
+    }
+
+    /// Remove the file `id` from the cache, so it is resolved again.
+    pub fn invalidate(&self, id: FileId) {
+        if let Ok(mut state) = self.state.lock() {
+            state.remove(id);
+        }
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.entries.clear();
+            state.bytes = 0;
+        }
+    }
+
+    /// Number of cached files.
+    pub fn len(&self) -> usize {
+        self.state
+            .lock()
+            .map(|state| state.entries.len())
+            .unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Size of the cached files in bytes.
+    pub fn bytes(&self) -> usize {
+        self.state
+            .lock()
+            .map(|state| state.bytes)
+            .unwrap_or_default()
+    }
+
+    fn get(&self, id: FileId) -> Option<V>
+    where
+        V: Clone,
+    {
+        let mut state = self.state.lock().ok()?;
+        let expired = match (state.entries.get(&id), state.limits.ttl) {
+            (None, _) => return None,
+            (Some(entry), Some(ttl)) => entry.inserted.elapsed() > ttl,
+            (Some(_), None) => false,
+        };
+        if expired {
+            state.remove(id);
+            return None;
+        }
+        state.clock += 1;
+        let clock = state.clock;
+        let entry = state.entries.get_mut(&id)?;
+        entry.last_used = clock;
+        Some(entry.value.clone())
+    }
+
+    fn insert(&self, id: FileId, value: V, size: usize) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        state.remove(id);
+        if state
+            .limits
+            .max_bytes
+            .is_some_and(|max_bytes| size > max_bytes)
+        {
+            return;
+        }
+        state.clock += 1;
+        let entry = CacheEntry {
+            value,
+            size,
+            inserted: Instant::now(),
+            last_used: state.clock,
+        };
+        state.entries.insert(id, entry);
+        state.bytes += size;
+        state.evict();
+    }
+}
+
+impl CacheBackend<Source> for FileCache<Source> {
+    fn get(&self, id: FileId) -> Option<Source> {
+        FileCache::get(self, id)
+    }
+
+    fn put(&self, id: FileId, value: &Source) {
+        self.insert(id, value.clone(), value.len_bytes());
+    }
+
+    fn invalidate(&self, id: FileId) {
+        FileCache::invalidate(self, id)
+    }
+
+    fn clear(&self) {
+        FileCache::clear(self)
+    }
+
+    fn cached_files(&self) -> usize {
+        self.len()
+    }
+
+    fn cached_bytes(&self) -> usize {
+        self.bytes()
+    }
+}
+
+impl CacheBackend<Bytes> for FileCache<Bytes> {
+    fn get(&self, id: FileId) -> Option<Bytes> {
+        FileCache::get(self, id)
+    }
+
+    fn put(&self, id: FileId, value: &Bytes) {
+        self.insert(id, value.clone(), value.len());
+    }
+
+    fn invalidate(&self, id: FileId) {
+        FileCache::invalidate(self, id)
+    }
+
+    fn clear(&self) {
+        FileCache::clear(self)
+    }
+
+    fn cached_files(&self) -> usize {
+        self.len()
+    }
+
+    fn cached_bytes(&self) -> usize {
+        self.bytes()
+    }
+}
+
+impl<V> FileCacheState<V> {
+    fn remove(&mut self, id: FileId) {
+        if let Some(entry) = self.entries.remove(&id) {
+            self.bytes -= entry.size;
+        }
+    }
+
+    fn evict(&mut self) {
+        let CacheLimits {
+            max_entries,
+            max_bytes,
+            ..
+        } = self.limits;
+        while max_entries.is_some_and(|max| self.entries.len() > max)
+            || max_bytes.is_some_and(|max| self.bytes > max)
+        {
+            let Some(id) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(id, _)| *id)
+            else {
+                break;
+            };
+            self.remove(id);
+        }
+    }
+}
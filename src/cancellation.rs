@@ -0,0 +1,59 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+/// Cancels a running compile, e.g. when the client disconnected or a deadline passed.
+/// Cloning is cheap, all clones share the same state.
+///
+/// Typst cannot be interrupted at arbitrary points, the token is checked each time the
+/// compile accesses a file, a font or the current date. A compile, that does none of these
+/// (e.g. an endless loop in a script or a show rule, that never terminates), is not stopped,
+/// run such compiles in a separate process, if they have to be killed.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel at `deadline`.
+    pub fn with_deadline(self, deadline: Instant) -> Self {
+        Self {
+            deadline: Some(deadline),
+            ..self
+        }
+    }
+
+    /// Cancel, when `timeout` passed from now.
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.with_deadline(Instant::now() + timeout)
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// Cancel at `deadline`, if the token does not have an earlier deadline.
+    pub(crate) fn with_deadline_at_most(self, deadline: Instant) -> Self {
+        let deadline = match self.deadline {
+            Some(current) => current.min(deadline),
+            None => deadline,
+        };
+        self.with_deadline(deadline)
+    }
+}
@@ -0,0 +1,157 @@
+//! Large datasets, that are split into chunks, which are only created, when the template loads
+//! them. Passing 100k rows as one input `Dict` keeps all of them as typst values and hashes
+//! them on each compile, chunks are loaded one after another with typst's `json(..)` or
+//! `csv(..)` and can be dropped after each chunk is laid out.
+//!
+//! ```rust,ignore
+//! let orders = ChunkedDataResolver::json_rows("/data/orders", orders, 1000);
+//! let template = TypstTemplate::new(fonts, TEMPLATE).add_file_resolver(orders);
+//! ```
+//!
+//! ```typ
+//! #let chunks = json("/data/orders/index.json").chunks
+//! #for i in range(chunks) {
+//!   for order in json("/data/orders/" + str(i) + ".json") [
+//!     - #order.id: #order.customer
+//!   ]
+//! }
+//! ```
+
+use std::{borrow::Cow, path::Path};
+
+use ecow::{eco_format, EcoString};
+use typst::{
+    diag::FileResult,
+    foundations::Bytes,
+    syntax::{FileId, Source, VirtualPath},
+};
+
+use crate::{file_resolver::FileResolver, util::not_found};
+
+/// Name of the file, that contains the number of chunks (`{"chunks": 3}`).
+pub static CHUNK_INDEX_FILE: &str = "index.json";
+
+/// Creates the chunks of a `ChunkedDataResolver`.
+pub trait ChunkProvider {
+    fn chunk_count(&self) -> usize;
+
+    /// Content of the chunk `index` (starting at 0), e.g. a JSON array or CSV rows. Called
+    /// each time the template loads the chunk.
+    fn chunk(&self, index: usize) -> FileResult<Vec<u8>>;
+}
+
+struct FnChunkProvider<F> {
+    chunk_count: usize,
+    chunk: F,
+}
+
+impl<F> ChunkProvider for FnChunkProvider<F>
+where
+    F: Fn(usize) -> FileResult<Vec<u8>>,
+{
+    fn chunk_count(&self) -> usize {
+        self.chunk_count
+    }
+
+    fn chunk(&self, index: usize) -> FileResult<Vec<u8>> {
+        (self.chunk)(index)
+    }
+}
+
+/// Resolves the chunks of a dataset as `{dir}/{index}.{extension}` (e.g.
+/// `/data/orders/0.json`) and the number of chunks as `{dir}/index.json`.
+pub struct ChunkedDataResolver {
+    dir: VirtualPath,
+    extension: EcoString,
+    provider: Box<dyn ChunkProvider + Send + Sync + 'static>,
+}
+
+impl ChunkedDataResolver {
+    pub fn new<D, E, P>(dir: D, extension: E, provider: P) -> Self
+    where
+        D: AsRef<Path>,
+        E: Into<EcoString>,
+        P: ChunkProvider + Send + Sync + 'static,
+    {
+        Self {
+            dir: VirtualPath::new(dir),
+            extension: extension.into(),
+            provider: Box::new(provider),
+        }
+    }
+
+    /// `chunk_count` chunks, that are created by `chunk`, e.g. with a database query per chunk.
+    ///
+    /// ```rust,ignore
+    /// let orders = ChunkedDataResolver::from_fn("/data/orders", "csv", pages, move |page| {
+    ///     db.orders_csv(page * 1000, 1000).map_err(|err| FileError::Other(Some(err.to_string().into())))
+    /// });
+    /// ```
+    pub fn from_fn<D, E, F>(dir: D, extension: E, chunk_count: usize, chunk: F) -> Self
+    where
+        D: AsRef<Path>,
+        E: Into<EcoString>,
+        F: Fn(usize) -> FileResult<Vec<u8>> + Send + Sync + 'static,
+    {
+        Self::new(dir, extension, FnChunkProvider { chunk_count, chunk })
+    }
+
+    /// `rows` as JSON arrays with up to `chunk_size` rows each (feature `json`). The rows are
+    /// only serialized, when their chunk is loaded.
+    #[cfg(feature = "json")]
+    pub fn json_rows<D, T>(dir: D, rows: Vec<T>, chunk_size: usize) -> Self
+    where
+        D: AsRef<Path>,
+        T: serde::Serialize + Send + Sync + 'static,
+    {
+        let chunk_size = chunk_size.max(1);
+        let chunk_count = rows.len().div_ceil(chunk_size);
+        Self::from_fn(dir, "json", chunk_count, move |index| {
+            let start = index * chunk_size;
+            let rows = &rows[start..(start + chunk_size).min(rows.len())];
+            serde_json::to_vec(rows).map_err(|err| {
+                typst::diag::FileError::Other(Some(eco_format!("Could not serialize: {err}")))
+            })
+        })
+    }
+
+    fn resolve_bytes(&self, id: FileId) -> FileResult<Vec<u8>> {
+        let Self {
+            dir,
+            extension,
+            provider,
+        } = self;
+        let path = id.vpath().as_rootless_path();
+        let name = id
+            .package()
+            .is_none()
+            .then(|| path.strip_prefix(dir.as_rootless_path()).ok())
+            .flatten()
+            .filter(|name| name.components().count() == 1)
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| not_found(id))?;
+        if name == CHUNK_INDEX_FILE {
+            return Ok(eco_format!("{{\"chunks\": {}}}", provider.chunk_count())
+                .as_bytes()
+                .to_vec());
+        }
+        let index = name
+            .strip_suffix(extension.as_str())
+            .and_then(|name| name.strip_suffix('.'))
+            .and_then(|index| index.parse::<usize>().ok())
+            .filter(|index| *index < provider.chunk_count())
+            .ok_or_else(|| not_found(id))?;
+        provider.chunk(index)
+    }
+}
+
+impl FileResolver for ChunkedDataResolver {
+    fn resolve_binary(&self, id: FileId) -> FileResult<Cow<'_, Bytes>> {
+        let bytes = self.resolve_bytes(id)?;
+        Ok(Cow::Owned(bytes.into()))
+    }
+
+    fn resolve_source(&self, id: FileId) -> FileResult<Cow<'_, Source>> {
+        Err(not_found(id))
+    }
+}
@@ -0,0 +1,115 @@
+use chrono::{DateTime, Utc};
+use typst::{
+    foundations::{Bytes, Dict},
+    syntax::FileId,
+};
+
+use crate::{cancellation::CancellationToken, font_set::FontSet, FileIdNewType};
+
+/// Settings of a single compile, see `TypstTemplateCollection::compile_with_options`. Fields,
+/// that are `None`, keep the settings of the collection.
+///
+/// ```rust,ignore
+/// let options = CompileOptions::new()
+///     .with_now(Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap())
+///     .with_main("/letter.typ")
+///     .with_inputs(inputs)
+///     .with_comemo_evict_max_age(10);
+/// let doc = template.compile_with_options(options).output?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CompileOptions {
+    /// Current time (`datetime.today()`). Overrides `with_fixed_now`. Compiles with `now`
+    /// are not cached by `with_document_cache`.
+    pub now: Option<DateTime<Utc>>,
+    /// File, that is compiled instead of the main source.
+    pub main: Option<FileId>,
+    /// Available as `#import sys: inputs` (or the custom inject location).
+    pub inputs: Option<Dict>,
+    /// Files, that are only visible to this compile, see
+    /// `TypstTemplateCollection::compile_with_input_and_files`.
+    pub files: Vec<(FileId, Bytes)>,
+    /// Fonts, that are only available to this compile, see
+    /// `TypstTemplateCollection::compile_with_fonts`.
+    pub font_set: Option<FontSet>,
+    /// Overrides `comemo_evict_max_age` of the collection.
+    pub comemo_evict_max_age: Option<usize>,
+    /// Don't evict memoized results after the compile, e.g. when the caller evicts itself.
+    pub skip_evict: bool,
+    pub cancellation: Option<CancellationToken>,
+}
+
+impl CompileOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_now(mut self, now: DateTime<Utc>) -> Self {
+        self.now = Some(now);
+        self
+    }
+
+    pub fn with_main<F>(mut self, main: F) -> Self
+    where
+        F: Into<FileIdNewType>,
+    {
+        let FileIdNewType(main) = main.into();
+        self.main = Some(main);
+        self
+    }
+
+    pub fn with_inputs<D>(mut self, inputs: D) -> Self
+    where
+        D: Into<Dict>,
+    {
+        self.inputs = Some(inputs.into());
+        self
+    }
+
+    /// Add a file, that is only visible to this compile.
+    pub fn with_file<F, B>(mut self, id: F, bytes: B) -> Self
+    where
+        F: Into<FileIdNewType>,
+        B: Into<Bytes>,
+    {
+        let FileIdNewType(id) = id.into();
+        self.files.push((id, bytes.into()));
+        self
+    }
+
+    /// Add files, that are only visible to this compile (pairs of virtual path and content).
+    pub fn with_files<IB, F, B>(mut self, files: IB) -> Self
+    where
+        IB: IntoIterator<Item = (F, B)>,
+        F: Into<FileIdNewType>,
+        B: Into<Bytes>,
+    {
+        self.files.extend(files.into_iter().map(|(id, bytes)| {
+            let FileIdNewType(id) = id.into();
+            (id, bytes.into())
+        }));
+        self
+    }
+
+    /// Use the fonts of `font_set` in addition to the fonts of the collection. Cloning a
+    /// `FontSet` is cheap.
+    pub fn with_font_set(mut self, font_set: FontSet) -> Self {
+        self.font_set = Some(font_set);
+        self
+    }
+
+    pub fn with_comemo_evict_max_age(mut self, comemo_evict_max_age: usize) -> Self {
+        self.comemo_evict_max_age = Some(comemo_evict_max_age);
+        self
+    }
+
+    pub fn skip_evict(mut self, skip_evict: bool) -> Self {
+        self.skip_evict = skip_evict;
+        self
+    }
+
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = Some(cancellation);
+        self
+    }
+}
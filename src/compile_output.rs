@@ -0,0 +1,130 @@
+use std::time::Duration;
+
+use ecow::EcoVec;
+use typst::{
+    diag::{SourceDiagnostic, Warned},
+    model::Document,
+    syntax::{package::PackageSpec, FileId},
+};
+
+use crate::{FileIdNewType, TypstAsLibError};
+
+/// Result of a compile, bundled with the warnings and additional information
+/// about the compile. Returned by the `*_detailed` methods, other compile results can be
+/// converted with `From`.
+///
+/// ```rust,ignore
+/// let output = template.compile_with_input_detailed(inputs);
+/// for warning in &output.warnings {
+///     log::warn!("{}", warning.message);
+/// }
+/// let doc = output.into_result()?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct CompileOutput {
+    pub output: Result<Document, TypstAsLibError>,
+    pub warnings: EcoVec<SourceDiagnostic>,
+    /// All files, that were accessed during the compile, sorted. For documents served from
+    /// the `DocumentCache` the files of the compile, that filled the cache.
+    pub dependencies: Vec<FileId>,
+    pub stats: CompileStats,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CompileStats {
+    /// Time it took to compile the document.
+    pub duration: Duration,
+    /// The document was served from the `DocumentCache`.
+    pub from_cache: bool,
+}
+
+impl CompileOutput {
+    pub(crate) fn from_error(error: TypstAsLibError) -> Self {
+        Self {
+            output: Err(error),
+            warnings: Default::default(),
+            dependencies: Default::default(),
+            stats: Default::default(),
+        }
+    }
+
+    /// The compiled document, if the compile succeeded.
+    pub fn document(&self) -> Option<&Document> {
+        self.output.as_ref().ok()
+    }
+
+    /// `true`, if `file_id` was accessed during the compile, i.e. the document has to be
+    /// compiled again, when the file changes.
+    pub fn depends_on<F>(&self, file_id: F) -> bool
+    where
+        F: Into<FileIdNewType>,
+    {
+        let FileIdNewType(file_id) = file_id.into();
+        self.dependencies.binary_search(&file_id).is_ok()
+    }
+
+    /// The packages (`@namespace/name:version`), that files were accessed from during the
+    /// compile, sorted, e.g. to audit the third-party code, that a document was created with.
+    /// Packages, that are imported by other packages, are included.
+    ///
+    /// ```rust,ignore
+    /// let output = template.compile_with_input_detailed(inputs);
+    /// for package in output.packages() {
+    ///     audit_log.record(&document_id, &package.to_string());
+    /// }
+    /// ```
+    pub fn packages(&self) -> Vec<PackageSpec> {
+        let mut packages: Vec<PackageSpec> = Vec::new();
+        for package in self.dependencies.iter().filter_map(FileId::package) {
+            if !packages.contains(package) {
+                packages.push(package.clone());
+            }
+        }
+        packages.sort_by(|a, b| {
+            (&a.namespace, &a.name, a.version).cmp(&(&b.namespace, &b.name, b.version))
+        });
+        packages
+    }
+
+    /// The compiled document or the error, e.g. to use `?`. Drops the warnings.
+    pub fn into_result(self) -> Result<Document, TypstAsLibError> {
+        self.output
+    }
+
+    /// The compiled document with the warnings or the error.
+    pub fn into_result_with_warnings(
+        self,
+    ) -> Result<(Document, EcoVec<SourceDiagnostic>), TypstAsLibError> {
+        let Self {
+            output, warnings, ..
+        } = self;
+        output.map(|document| (document, warnings))
+    }
+}
+
+impl From<Warned<Result<Document, TypstAsLibError>>> for CompileOutput {
+    fn from(value: Warned<Result<Document, TypstAsLibError>>) -> Self {
+        let Warned { output, warnings } = value;
+        Self {
+            output,
+            warnings,
+            dependencies: Default::default(),
+            stats: Default::default(),
+        }
+    }
+}
+
+impl From<CompileOutput> for Result<Document, TypstAsLibError> {
+    fn from(value: CompileOutput) -> Self {
+        value.output
+    }
+}
+
+impl From<CompileOutput> for Warned<Result<Document, TypstAsLibError>> {
+    fn from(value: CompileOutput) -> Self {
+        let CompileOutput {
+            output, warnings, ..
+        } = value;
+        Warned { output, warnings }
+    }
+}
@@ -0,0 +1,87 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use typst::{diag::Warned, foundations::Dict, model::Document, syntax::FileId};
+
+use crate::{CompileOverrides, TypstAsLibError, TypstTemplateCollection};
+
+/// Repeated compiles of the same template, e.g. a live preview, where only the inputs change
+/// slightly. Other than `TypstTemplateCollection::compile`, the memoized results of typst
+/// (layout, evaluated modules, ...) are not evicted after each compile, so parts of the
+/// document, that did not change, are reused. Eviction happens only on `evict` and when
+/// the session is dropped (with `comemo_evict_max_age` of the collection).
+///
+/// The memoization of typst is global: compiles outside of the session, that evict, also
+/// evict the results of the session.
+///
+/// ```rust,ignore
+/// let session = template.session();
+/// for inputs in edits {
+///     let doc = session.compile_with_input(inputs).output?;
+///     // Keep the results of the last 10 compiles.
+///     session.evict(10);
+/// }
+/// ```
+pub struct CompileSession<'a> {
+    collection: &'a TypstTemplateCollection,
+    main_source_id: FileId,
+    compiles: AtomicUsize,
+}
+
+impl<'a> CompileSession<'a> {
+    pub(crate) fn new(collection: &'a TypstTemplateCollection, main_source_id: FileId) -> Self {
+        Self {
+            collection,
+            main_source_id,
+            compiles: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn main_source_id(&self) -> FileId {
+        self.main_source_id
+    }
+
+    /// Number of compiles in this session.
+    pub fn compiles(&self) -> usize {
+        self.compiles.load(Ordering::Relaxed)
+    }
+
+    pub fn compile(&self) -> Warned<Result<Document, TypstAsLibError>> {
+        self.compile_helper::<Dict>(None)
+    }
+
+    /// Compile with `inputs` available as `#import sys: inputs`.
+    pub fn compile_with_input<D>(&self, inputs: D) -> Warned<Result<Document, TypstAsLibError>>
+    where
+        D: Into<Dict>,
+    {
+        self.compile_helper(Some(inputs))
+    }
+
+    /// Evict memoized results, that were not used in the last `max_age` compiles
+    /// (see `comemo::evict`). `0` evicts everything.
+    pub fn evict(&self, max_age: usize) {
+        comemo::evict(max_age);
+    }
+
+    fn compile_helper<D>(&self, inputs: Option<D>) -> Warned<Result<Document, TypstAsLibError>>
+    where
+        D: Into<Dict>,
+    {
+        self.compiles.fetch_add(1, Ordering::Relaxed);
+        let overrides = CompileOverrides {
+            skip_evict: true,
+            ..Default::default()
+        };
+        self.collection
+            .compile_helper(self.main_source_id, inputs, overrides)
+            .into()
+    }
+}
+
+impl Drop for CompileSession<'_> {
+    fn drop(&mut self) {
+        if let Some(max_age) = self.collection.comemo_evict_max_age {
+            comemo::evict(max_age);
+        }
+    }
+}
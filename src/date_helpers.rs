@@ -0,0 +1,491 @@
+//! Helper functions for dates, that are injected into templates as a module
+//! (see `TypstTemplateCollection::with_date_helpers`).
+//!
+//! ```typ
+//! #import dates: add-months, format, month-name
+//! #let due = add-months(datetime.today(), 1)
+//! Due: #format(due, "%d.%m.%Y") (#context month-name(due))
+//! ```
+//!
+//! Functions:
+//! - `add-days(date, days)`, `add-months(date, months)`, `add-years(date, years)`:
+//!   Date arithmetic. Adding months clamps the day to the end of the month (Jan 31 + 1 month = Feb 28).
+//! - `days-between(start, end)`: Number of days from `start` to `end`.
+//! - `end-of-month(date)`: Last day of the month of `date`.
+//! - `format(date, pattern, tz: auto, lang: auto)`: Formats the date with a `strftime` pattern
+//!   (e.g. `"%d.%m.%Y"`). The date is local to `tz` (e.g. `"Europe/Berlin"`, feature
+//!   `timezone`), which defaults to the timezone of `with_timezone` or UTC, for the offset
+//!   specifiers `%z`, `%:z` and `%Z`. `%B` and `%A` are the names of `month-name` and
+//!   `weekday-name`.
+//! - `month-name(date, lang: auto)`, `weekday-name(date, lang: auto)`: Names in the given
+//!   language. Inside of `context`, `auto` uses the configured text language (`set text(lang: ..)`
+//!   or `StyleDefaults::lang`), otherwise English.
+
+use std::fmt::Write;
+
+use chrono::{
+    format::{Fixed, Item, StrftimeItems},
+    Datelike, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Timelike, Utc,
+};
+use comemo::Tracked;
+use ecow::{eco_vec, EcoString};
+use once_cell::sync::Lazy;
+use typst::{
+    diag::{bail, SourceResult},
+    engine::Engine,
+    foundations::{
+        Arg, Args, CastInfo, Context, Datetime, Func, Module, NativeFuncData, Scope, Smart, Str,
+        Value,
+    },
+    syntax::{Span, Spanned},
+    text::TextElem,
+};
+
+/// Default name of the injected module.
+pub const DEFAULT_MODULE_NAME: &str = "dates";
+
+/// Configuration of the collection, that is bound to the functions of the module.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DateHelperOptions {
+    /// Name of the timezone of `with_timezone`, the default of `tz`.
+    pub(crate) timezone: Option<EcoString>,
+}
+
+macro_rules! native_func {
+    ($name:literal, $title:literal, $function:ident) => {
+        native_func!($name, $title, $function, false)
+    };
+    ($name:literal, $title:literal, $function:ident, $contextual:literal) => {{
+        static DATA: NativeFuncData = NativeFuncData {
+            function: $function,
+            name: $name,
+            title: $title,
+            docs: "",
+            keywords: &[],
+            contextual: $contextual,
+            scope: Lazy::new(Scope::new),
+            params: Lazy::new(Vec::new),
+            returns: Lazy::new(|| CastInfo::Any),
+        };
+        Func::from(&DATA)
+    }};
+}
+
+pub(crate) fn module(name: &str, options: &DateHelperOptions) -> Module {
+    let mut scope = Scope::new();
+    scope.define("add-days", native_func!("add-days", "Add Days", add_days));
+    scope.define(
+        "add-months",
+        native_func!("add-months", "Add Months", add_months),
+    );
+    scope.define(
+        "add-years",
+        native_func!("add-years", "Add Years", add_years),
+    );
+    scope.define(
+        "days-between",
+        native_func!("days-between", "Days Between", days_between),
+    );
+    scope.define(
+        "end-of-month",
+        native_func!("end-of-month", "End Of Month", end_of_month),
+    );
+    let mut format = native_func!("format", "Format", format, true);
+    if let Some(timezone) = &options.timezone {
+        let span = Span::detached();
+        let mut args = Args {
+            span,
+            items: eco_vec![Arg {
+                span,
+                name: Some("tz".into()),
+                value: Spanned::new(Value::Str(timezone.as_str().into()), span),
+            }],
+        };
+        format = format.with(&mut args);
+    }
+    scope.define("format", format);
+    scope.define(
+        "month-name",
+        native_func!("month-name", "Month Name", month_name, true),
+    );
+    scope.define(
+        "weekday-name",
+        native_func!("weekday-name", "Weekday Name", weekday_name, true),
+    );
+    Module::new(name, scope)
+}
+
+fn add_days(_: &mut Engine, _: Tracked<Context>, args: &mut Args) -> SourceResult<Value> {
+    let date = expect_date(args)?;
+    let days: i64 = args.expect("days")?;
+    args.take().finish()?;
+    let added = date.value.checked_add_signed(chrono::Duration::days(days));
+    from_chrono(added, &date)
+}
+
+fn add_months(_: &mut Engine, _: Tracked<Context>, args: &mut Args) -> SourceResult<Value> {
+    let date = expect_date(args)?;
+    let months: i64 = args.expect("months")?;
+    args.take().finish()?;
+    from_chrono(checked_add_months(date.value, months), &date)
+}
+
+fn add_years(_: &mut Engine, _: Tracked<Context>, args: &mut Args) -> SourceResult<Value> {
+    let date = expect_date(args)?;
+    let years: i64 = args.expect("years")?;
+    args.take().finish()?;
+    let added = years
+        .checked_mul(12)
+        .and_then(|months| checked_add_months(date.value, months));
+    from_chrono(added, &date)
+}
+
+fn days_between(_: &mut Engine, _: Tracked<Context>, args: &mut Args) -> SourceResult<Value> {
+    let start = expect_date(args)?;
+    let end = expect_date(args)?;
+    args.take().finish()?;
+    let days = end.value.date() - start.value.date();
+    Ok(Value::Int(days.num_days()))
+}
+
+fn end_of_month(_: &mut Engine, _: Tracked<Context>, args: &mut Args) -> SourceResult<Value> {
+    let date = expect_date(args)?;
+    args.take().finish()?;
+    let first = date.value.with_day(1);
+    let end = first
+        .and_then(|first| first.checked_add_months(Months::new(1)))
+        .and_then(|next| next.checked_sub_signed(chrono::Duration::days(1)));
+    from_chrono(end, &date)
+}
+
+fn format(_: &mut Engine, context: Tracked<Context>, args: &mut Args) -> SourceResult<Value> {
+    let date = expect_date(args)?;
+    let Spanned {
+        v: pattern,
+        span: pattern_span,
+    } = args.expect::<Spanned<Str>>("pattern")?;
+    let tz: Option<Spanned<Str>> = args.named("tz")?;
+    let lang = lang(context, args)?;
+    args.take().finish()?;
+    let month = month_names(&lang)[date.value.month0() as usize];
+    let weekday = weekday_names(&lang)[date.value.weekday().num_days_from_monday() as usize];
+    let mut items = Vec::new();
+    for item in StrftimeItems::new(pattern.as_str()) {
+        items.push(match item {
+            Item::Error => bail!(pattern_span, "invalid format pattern: {}", pattern.as_str()),
+            Item::Fixed(Fixed::LongMonthName) => Item::Literal(month),
+            Item::Fixed(Fixed::LongWeekdayName) => Item::Literal(weekday),
+            item => item,
+        });
+    }
+    let mut formatted = String::new();
+    let written = match tz {
+        None => write!(
+            formatted,
+            "{}",
+            Utc.from_utc_datetime(&date.value)
+                .format_with_items(items.iter())
+        ),
+        Some(tz) => write_in_timezone(&mut formatted, &date, &items, tz)?,
+    };
+    if written.is_err() {
+        bail!(
+            pattern_span,
+            "could not format the date with {}",
+            pattern.as_str()
+        );
+    }
+    Ok(Value::Str(formatted.into()))
+}
+
+/// Write the date, as local time of `tz`, with `items`.
+#[cfg(feature = "timezone")]
+fn write_in_timezone(
+    formatted: &mut String,
+    date: &DateArg,
+    items: &[Item],
+    tz: Spanned<Str>,
+) -> SourceResult<std::fmt::Result> {
+    let Ok(timezone) = tz.v.as_str().parse::<chrono_tz::Tz>() else {
+        bail!(tz.span, "unknown timezone: {}", tz.v.as_str());
+    };
+    let Some(local) = timezone.from_local_datetime(&date.value).earliest() else {
+        bail!(date.span, "the time does not exist in {}", tz.v.as_str());
+    };
+    Ok(write!(
+        formatted,
+        "{}",
+        local.format_with_items(items.iter())
+    ))
+}
+
+/// Write the date, as local time of `tz`, with `items`. Only UTC is known without the feature
+/// `timezone`.
+#[cfg(not(feature = "timezone"))]
+fn write_in_timezone(
+    formatted: &mut String,
+    date: &DateArg,
+    items: &[Item],
+    tz: Spanned<Str>,
+) -> SourceResult<std::fmt::Result> {
+    if tz.v.as_str() != "UTC" {
+        bail!(
+            tz.span,
+            "unknown timezone: {} (timezones need the feature `timezone`)",
+            tz.v.as_str()
+        );
+    }
+    Ok(write!(
+        formatted,
+        "{}",
+        Utc.from_utc_datetime(&date.value)
+            .format_with_items(items.iter())
+    ))
+}
+
+fn month_name(_: &mut Engine, context: Tracked<Context>, args: &mut Args) -> SourceResult<Value> {
+    let date = expect_date(args)?;
+    let lang = lang(context, args)?;
+    args.take().finish()?;
+    let names = month_names(&lang);
+    Ok(Value::Str(names[date.value.month0() as usize].into()))
+}
+
+fn weekday_name(_: &mut Engine, context: Tracked<Context>, args: &mut Args) -> SourceResult<Value> {
+    let date = expect_date(args)?;
+    let lang = lang(context, args)?;
+    args.take().finish()?;
+    let weekday = date.value.weekday().num_days_from_monday() as usize;
+    Ok(Value::Str(weekday_names(&lang)[weekday].into()))
+}
+
+fn month_names(lang: &str) -> Names<12> {
+    match lang {
+        "de" => MONTHS_DE,
+        "fr" => MONTHS_FR,
+        "es" => MONTHS_ES,
+        "it" => MONTHS_IT,
+        "nl" => MONTHS_NL,
+        _ => MONTHS_EN,
+    }
+}
+
+fn weekday_names(lang: &str) -> Names<7> {
+    match lang {
+        "de" => WEEKDAYS_DE,
+        "fr" => WEEKDAYS_FR,
+        "es" => WEEKDAYS_ES,
+        "it" => WEEKDAYS_IT,
+        "nl" => WEEKDAYS_NL,
+        _ => WEEKDAYS_EN,
+    }
+}
+
+/// A date argument converted to chrono. `has_time` is set, if the datetime had a time.
+struct DateArg {
+    value: NaiveDateTime,
+    has_time: bool,
+    span: Span,
+}
+
+fn expect_date(args: &mut Args) -> SourceResult<DateArg> {
+    let Spanned { v: datetime, span } = args.expect::<Spanned<Datetime>>("date")?;
+    let (Some(year), Some(month), Some(day)) = (datetime.year(), datetime.month(), datetime.day())
+    else {
+        bail!(span, "expected a datetime with a date");
+    };
+    let Some(date) = NaiveDate::from_ymd_opt(year, month.into(), day.into()) else {
+        bail!(span, "invalid date");
+    };
+    let time = NaiveTime::from_hms_opt(
+        datetime.hour().unwrap_or(0).into(),
+        datetime.minute().unwrap_or(0).into(),
+        datetime.second().unwrap_or(0).into(),
+    )
+    .unwrap_or_default();
+    Ok(DateArg {
+        value: date.and_time(time),
+        has_time: datetime.hour().is_some(),
+        span,
+    })
+}
+
+fn from_chrono(value: Option<NaiveDateTime>, arg: &DateArg) -> SourceResult<Value> {
+    let Some(value) = value else {
+        bail!(arg.span, "date is out of range");
+    };
+    let (year, month, day) = (value.year(), value.month() as u8, value.day() as u8);
+    let datetime = if arg.has_time {
+        Datetime::from_ymd_hms(
+            year,
+            month,
+            day,
+            value.hour() as u8,
+            value.minute() as u8,
+            value.second() as u8,
+        )
+    } else {
+        Datetime::from_ymd(year, month, day)
+    };
+    match datetime {
+        Some(datetime) => Ok(Value::Datetime(datetime)),
+        None => bail!(arg.span, "date is out of range"),
+    }
+}
+
+fn checked_add_months(date: NaiveDateTime, months: i64) -> Option<NaiveDateTime> {
+    let abs = Months::new(u32::try_from(months.unsigned_abs()).ok()?);
+    if months >= 0 {
+        date.checked_add_months(abs)
+    } else {
+        date.checked_sub_months(abs)
+    }
+}
+
+/// The `lang` argument or, if it is `auto`, the text language of the context.
+fn lang(context: Tracked<Context>, args: &mut Args) -> SourceResult<String> {
+    let lang: Smart<Str> = args.named("lang")?.unwrap_or(Smart::Auto);
+    Ok(match lang {
+        Smart::Custom(lang) => lang.to_lowercase(),
+        Smart::Auto => context
+            .styles()
+            .map(|styles| TextElem::lang_in(styles).as_str().to_owned())
+            .unwrap_or_else(|_| "en".to_owned()),
+    })
+}
+
+type Names<const N: usize> = [&'static str; N];
+
+const MONTHS_EN: Names<12> = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+const MONTHS_DE: Names<12> = [
+    "Januar",
+    "Februar",
+    "März",
+    "April",
+    "Mai",
+    "Juni",
+    "Juli",
+    "August",
+    "September",
+    "Oktober",
+    "November",
+    "Dezember",
+];
+const MONTHS_FR: Names<12> = [
+    "janvier",
+    "février",
+    "mars",
+    "avril",
+    "mai",
+    "juin",
+    "juillet",
+    "août",
+    "septembre",
+    "octobre",
+    "novembre",
+    "décembre",
+];
+const MONTHS_ES: Names<12> = [
+    "enero",
+    "febrero",
+    "marzo",
+    "abril",
+    "mayo",
+    "junio",
+    "julio",
+    "agosto",
+    "septiembre",
+    "octubre",
+    "noviembre",
+    "diciembre",
+];
+const MONTHS_IT: Names<12> = [
+    "gennaio",
+    "febbraio",
+    "marzo",
+    "aprile",
+    "maggio",
+    "giugno",
+    "luglio",
+    "agosto",
+    "settembre",
+    "ottobre",
+    "novembre",
+    "dicembre",
+];
+const MONTHS_NL: Names<12> = [
+    "januari",
+    "februari",
+    "maart",
+    "april",
+    "mei",
+    "juni",
+    "juli",
+    "augustus",
+    "september",
+    "oktober",
+    "november",
+    "december",
+];
+
+const WEEKDAYS_EN: Names<7> = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+const WEEKDAYS_DE: Names<7> = [
+    "Montag",
+    "Dienstag",
+    "Mittwoch",
+    "Donnerstag",
+    "Freitag",
+    "Samstag",
+    "Sonntag",
+];
+const WEEKDAYS_FR: Names<7> = [
+    "lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi", "dimanche",
+];
+const WEEKDAYS_ES: Names<7> = [
+    "lunes",
+    "martes",
+    "miércoles",
+    "jueves",
+    "viernes",
+    "sábado",
+    "domingo",
+];
+const WEEKDAYS_IT: Names<7> = [
+    "lunedì",
+    "martedì",
+    "mercoledì",
+    "giovedì",
+    "venerdì",
+    "sabato",
+    "domenica",
+];
+const WEEKDAYS_NL: Names<7> = [
+    "maandag",
+    "dinsdag",
+    "woensdag",
+    "donderdag",
+    "vrijdag",
+    "zaterdag",
+    "zondag",
+];
@@ -0,0 +1,39 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Make/Ninja style depfile, that is written after each compile.
+#[derive(Debug, Clone)]
+pub(crate) struct Depfile {
+    pub(crate) path: PathBuf,
+    pub(crate) target: String,
+}
+
+impl Depfile {
+    /// Writes `<target>: <dependency> <dependency> ...` to the depfile.
+    pub(crate) fn write(&self, dependencies: &[PathBuf]) -> io::Result<()> {
+        let Self { path, target } = self;
+        let mut content = escape(target);
+        content.push(':');
+        for dependency in dependencies {
+            content.push(' ');
+            content.push_str(&escape(&dependency.to_string_lossy()));
+        }
+        content.push('\n');
+        write_file(path, &content)
+    }
+}
+
+fn write_file(path: &Path, content: &str) -> io::Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, content)
+}
+
+fn escape(path: &str) -> String {
+    path.replace('$', "$$")
+        .replace('#', "\\#")
+        .replace(' ', "\\ ")
+}
@@ -0,0 +1,134 @@
+use std::ops::Range;
+
+use codespan_reporting::diagnostic::{Diagnostic, Label};
+use codespan_reporting::files::{Error as CodespanError, Files};
+use codespan_reporting::term::termcolor::{Ansi, NoColor, WriteColor};
+use codespan_reporting::term::{self, Config};
+use ecow::eco_format;
+use typst::diag::{Severity, SourceDiagnostic};
+use typst::syntax::{FileId, Source, Span};
+
+use crate::util::display_path;
+use crate::TypstTemplateCollection;
+
+/// Renders diagnostics like the typst CLI does: with file name, line, column, the annotated
+/// source snippet, hints and the trace. Spans are resolved against the file resolvers of the
+/// collection.
+///
+/// ```rust,ignore
+/// if let Err(TypstAsLibError::TypstSource(diagnostics)) = result {
+///     eprintln!("{}", format_diagnostics(&collection, &diagnostics, true));
+/// }
+/// ```
+pub fn format_diagnostics(
+    collection: &TypstTemplateCollection,
+    diagnostics: &[SourceDiagnostic],
+    color: bool,
+) -> String {
+    if color {
+        let mut writer = Ansi::new(Vec::new());
+        write_diagnostics(collection, diagnostics, &mut writer);
+        String::from_utf8_lossy(&writer.into_inner()).into_owned()
+    } else {
+        let mut writer = NoColor::new(Vec::new());
+        write_diagnostics(collection, diagnostics, &mut writer);
+        String::from_utf8_lossy(&writer.into_inner()).into_owned()
+    }
+}
+
+fn write_diagnostics(
+    collection: &TypstTemplateCollection,
+    diagnostics: &[SourceDiagnostic],
+    writer: &mut dyn WriteColor,
+) {
+    let files = DiagnosticFiles(collection);
+    let config = Config::default();
+    for diagnostic in diagnostics {
+        let diag = match diagnostic.severity {
+            Severity::Error => Diagnostic::error(),
+            Severity::Warning => Diagnostic::warning(),
+        }
+        .with_message(diagnostic.message.clone())
+        .with_notes(
+            diagnostic
+                .hints
+                .iter()
+                .map(|hint| eco_format!("hint: {hint}").into())
+                .collect(),
+        )
+        .with_labels(files.label(diagnostic.span).into_iter().collect());
+        // Writing into a `Vec` only fails, if a file could not be resolved. In that case the
+        // diagnostic is skipped, as there is nothing sensible to print.
+        let _ = term::emit(writer, &config, &files, &diag);
+
+        for point in &diagnostic.trace {
+            let help = Diagnostic::help()
+                .with_message(point.v.to_string())
+                .with_labels(files.label(point.span).into_iter().collect());
+            let _ = term::emit(writer, &config, &files, &help);
+        }
+    }
+}
+
+struct DiagnosticFiles<'a>(&'a TypstTemplateCollection);
+
+impl DiagnosticFiles<'_> {
+    fn lookup(&self, id: FileId) -> Result<Source, CodespanError> {
+        self.0
+            .resolve_source(id)
+            .map(|source| source.into_owned())
+            .map_err(|_| CodespanError::FileMissing)
+    }
+
+    fn label(&self, span: Span) -> Option<Label<FileId>> {
+        let id = span.id()?;
+        let range = self.lookup(id).ok()?.range(span)?;
+        Some(Label::primary(id, range))
+    }
+}
+
+impl<'a> Files<'a> for DiagnosticFiles<'_> {
+    type FileId = FileId;
+    type Name = String;
+    type Source = Source;
+
+    fn name(&'a self, id: FileId) -> Result<String, CodespanError> {
+        Ok(display_path(id))
+    }
+
+    fn source(&'a self, id: FileId) -> Result<Source, CodespanError> {
+        self.lookup(id)
+    }
+
+    fn line_index(&'a self, id: FileId, given: usize) -> Result<usize, CodespanError> {
+        let source = self.lookup(id)?;
+        source
+            .byte_to_line(given)
+            .ok_or_else(|| CodespanError::IndexTooLarge {
+                given,
+                max: source.len_bytes(),
+            })
+    }
+
+    fn line_range(&'a self, id: FileId, given: usize) -> Result<Range<usize>, CodespanError> {
+        let source = self.lookup(id)?;
+        source
+            .line_to_range(given)
+            .ok_or_else(|| CodespanError::LineTooLarge {
+                given,
+                max: source.len_lines(),
+            })
+    }
+
+    fn column_number(&'a self, id: FileId, _: usize, given: usize) -> Result<usize, CodespanError> {
+        let source = self.lookup(id)?;
+        source.byte_to_column(given).ok_or_else(|| {
+            let max = source.len_bytes();
+            if given <= max {
+                CodespanError::InvalidCharBoundary { given }
+            } else {
+                CodespanError::IndexTooLarge { given, max }
+            }
+        })
+    }
+}
@@ -0,0 +1,178 @@
+use typst::diag::{Severity, SourceDiagnostic, Warned};
+use typst::syntax::Span;
+
+use crate::util::display_path;
+use crate::{TypstAsLibError, TypstTemplateCollection};
+
+/// A diagnostic with its spans resolved to a file path and line/column positions, so it can be
+/// handed to users, e.g. as JSON from a REST API (`Serialize` with feature `serde`).
+///
+/// ```rust,ignore
+/// let output = template.compile();
+/// let diagnostics = template.diagnostics(&output);
+/// let json = serde_json::to_string(&diagnostics)?;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Diagnostic {
+    pub severity: DiagnosticSeverity,
+    pub message: String,
+    /// Path of the file, prefixed with the package (e.g. `@preview/example:0.1.0/lib.typ`),
+    /// if the file is part of one. `None`, if the diagnostic does not point into a file.
+    pub path: Option<String>,
+    pub range: Option<LineColumnRange>,
+    pub hints: Vec<String>,
+    /// Where the error occurred while evaluating, e.g. function calls and imports,
+    /// innermost first.
+    pub trace: Vec<TracePoint>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum DiagnosticSeverity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TracePoint {
+    pub message: String,
+    pub path: Option<String>,
+    pub range: Option<LineColumnRange>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LineColumnRange {
+    pub start: LineColumn,
+    /// Exclusive.
+    pub end: LineColumn,
+}
+
+/// Position in a file. Both line and column start at 1, the column counts characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LineColumn {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl From<Severity> for DiagnosticSeverity {
+    fn from(value: Severity) -> Self {
+        match value {
+            Severity::Error => DiagnosticSeverity::Error,
+            Severity::Warning => DiagnosticSeverity::Warning,
+        }
+    }
+}
+
+impl Diagnostic {
+    /// Spans are resolved against the file resolvers of `collection`.
+    pub fn from_source_diagnostic(
+        collection: &TypstTemplateCollection,
+        diagnostic: &SourceDiagnostic,
+    ) -> Self {
+        let SourceDiagnostic {
+            severity,
+            span,
+            message,
+            trace,
+            hints,
+        } = diagnostic;
+        let (path, range) = resolve_span(collection, *span);
+        let trace = trace
+            .iter()
+            .map(|point| {
+                let (path, range) = resolve_span(collection, point.span);
+                TracePoint {
+                    message: point.v.to_string(),
+                    path,
+                    range,
+                }
+            })
+            .collect();
+        Self {
+            severity: (*severity).into(),
+            message: message.to_string(),
+            path,
+            range,
+            hints: hints.iter().map(ToString::to_string).collect(),
+            trace,
+        }
+    }
+
+    /// Errors, that are not caused by the typst source (e.g. invalid input), are converted
+    /// to diagnostics without location.
+    pub fn from_error(collection: &TypstTemplateCollection, error: &TypstAsLibError) -> Vec<Self> {
+        match error {
+            TypstAsLibError::TypstSource(diagnostics) => diagnostics
+                .iter()
+                .map(|diagnostic| Self::from_source_diagnostic(collection, diagnostic))
+                .collect(),
+            #[cfg(feature = "pdf")]
+            TypstAsLibError::PdfExport(diagnostics) => diagnostics
+                .iter()
+                .map(|diagnostic| Self::from_source_diagnostic(collection, diagnostic))
+                .collect(),
+            TypstAsLibError::HintedString(hinted) => vec![Self::without_location(
+                hinted.message().to_string(),
+                hinted.hints().iter().map(ToString::to_string).collect(),
+            )],
+            error => vec![Self::without_location(error.to_string(), Vec::new())],
+        }
+    }
+
+    fn without_location(message: String, hints: Vec<String>) -> Self {
+        Self {
+            severity: DiagnosticSeverity::Error,
+            message,
+            path: None,
+            range: None,
+            hints,
+            trace: Vec::new(),
+        }
+    }
+}
+
+/// All errors and warnings of a compile, errors first.
+pub fn from_warned<T>(
+    collection: &TypstTemplateCollection,
+    output: &Warned<Result<T, TypstAsLibError>>,
+) -> Vec<Diagnostic> {
+    let Warned { output, warnings } = output;
+    let mut diagnostics = match output {
+        Ok(_) => Vec::new(),
+        Err(error) => Diagnostic::from_error(collection, error),
+    };
+    diagnostics.extend(
+        warnings
+            .iter()
+            .map(|warning| Diagnostic::from_source_diagnostic(collection, warning)),
+    );
+    diagnostics
+}
+
+fn resolve_span(
+    collection: &TypstTemplateCollection,
+    span: Span,
+) -> (Option<String>, Option<LineColumnRange>) {
+    let Some(id) = span.id() else {
+        return (None, None);
+    };
+    let range = collection.resolve_source(id).ok().and_then(|source| {
+        let range = source.range(span)?;
+        let position = |byte| {
+            Some(LineColumn {
+                line: source.byte_to_line(byte)? + 1,
+                column: source.byte_to_column(byte)? + 1,
+            })
+        };
+        Some(LineColumnRange {
+            start: position(range.start)?,
+            end: position(range.end)?,
+        })
+    });
+    (Some(display_path(id)), range)
+}
@@ -0,0 +1,85 @@
+use std::fmt::{self, Display, Formatter};
+
+use typst::{
+    introspection::Tag,
+    layout::{Frame, FrameItem},
+    model::Document,
+};
+
+use crate::{document_ext::FrameExt, text_extraction::extract_text};
+
+/// A difference between two compiled documents. Pages start at 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Difference {
+    PageCount {
+        a: usize,
+        b: usize,
+    },
+    Text {
+        page: usize,
+        a: String,
+        b: String,
+    },
+    /// The elements (e.g. `heading`, `table`), that were laid out on the page, differ.
+    Elements {
+        page: usize,
+        a: Vec<&'static str>,
+        b: Vec<&'static str>,
+    },
+}
+
+impl Display for Difference {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Difference::PageCount { a, b } => write!(f, "page count: {a} != {b}"),
+            Difference::Text { page, a, b } => write!(f, "page {page} text: {a:?} != {b:?}"),
+            Difference::Elements { page, a, b } => {
+                write!(f, "page {page} elements: {a:?} != {b:?}")
+            }
+        }
+    }
+}
+
+/// Compares the page count, the text of each page and the elements on each page.
+/// An empty result means, that both documents are equivalent (e.g. after refactoring a template).
+pub fn diff(a: &Document, b: &Document) -> Vec<Difference> {
+    let mut differences = Vec::new();
+    if a.pages.len() != b.pages.len() {
+        differences.push(Difference::PageCount {
+            a: a.pages.len(),
+            b: b.pages.len(),
+        });
+    }
+    let pages = a.pages.iter().zip(&b.pages);
+    let texts = extract_text(a).into_iter().zip(extract_text(b));
+    for (i, ((page_a, page_b), (text_a, text_b))) in pages.zip(texts).enumerate() {
+        let page = i + 1;
+        if text_a != text_b {
+            differences.push(Difference::Text {
+                page,
+                a: text_a,
+                b: text_b,
+            });
+        }
+        let (elements_a, elements_b) = (elements(&page_a.frame), elements(&page_b.frame));
+        if elements_a != elements_b {
+            differences.push(Difference::Elements {
+                page,
+                a: elements_a,
+                b: elements_b,
+            });
+        }
+    }
+    differences
+}
+
+fn elements(frame: &Frame) -> Vec<&'static str> {
+    frame
+        .leaf_items()
+        .into_iter()
+        .filter_map(|(_, _, item)| match item {
+            FrameItem::Tag(Tag::Start(content)) => Some(content.elem().name()),
+            _ => None,
+        })
+        .collect()
+}
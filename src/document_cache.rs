@@ -0,0 +1,174 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use ecow::EcoVec;
+use typst::{
+    diag::SourceDiagnostic,
+    foundations::Dict,
+    model::Document,
+    syntax::{package::PackageSpec, FileId},
+    text::Font,
+};
+
+/// Maps the inputs of a compile to the `Dict`, that is used as cache key.
+/// Can be used to ignore volatile inputs (e.g. `generated_at`).
+pub type CacheKeyFn = dyn Fn(&Dict) -> Dict + Send + Sync + 'static;
+
+/// A compiled document together with the warnings of the compile, the files, that were
+/// accessed during the compile, and their paths on disk (for depfiles).
+pub(crate) type CachedDocument = (
+    Document,
+    EcoVec<SourceDiagnostic>,
+    Vec<FileId>,
+    Vec<PathBuf>,
+);
+
+/// In memory cache for compiled documents.
+/// Documents are cached by the main source `FileId` together with the (mapped) inputs, the
+/// collection, its library and fonts and the current date. Documents, that show the date of the
+/// system clock (`datetime.today()`), are only cached, if the time is fixed with
+/// `TypstTemplateCollection::with_fixed_now`. The cache is unbounded by default, see
+/// `with_max_documents`. Cloning is cheap, all clones share the same documents, but each
+/// collection only gets its own documents.
+#[derive(Clone, Default)]
+pub struct DocumentCache {
+    cache_key: Option<Arc<CacheKeyFn>>,
+    max_documents: Option<usize>,
+    documents: Arc<Mutex<Documents>>,
+}
+
+#[derive(Default)]
+struct Documents {
+    entries: HashMap<u128, (CachedDocument, u64)>,
+    /// Incremented on each access, to find the least recently used document.
+    clock: u64,
+}
+
+impl DocumentCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use `cache_key` to map the inputs before they are hashed.
+    pub fn with_cache_key<K>(self, cache_key: K) -> Self
+    where
+        K: Fn(&Dict) -> Dict + Send + Sync + 'static,
+    {
+        Self {
+            cache_key: Some(Arc::new(cache_key)),
+            ..self
+        }
+    }
+
+    /// Keep at most `max_documents` documents. The least recently used document is removed,
+    /// when another one is inserted.
+    pub fn with_max_documents(self, max_documents: usize) -> Self {
+        Self {
+            max_documents: Some(max_documents),
+            ..self
+        }
+    }
+
+    /// The part of the key of a compile of `main_source_id` with `inputs`, that doesn't
+    /// depend on the collection. Can be used to store artifacts of the document elsewhere
+    /// (e.g. `SqliteCache::put_artifact`).
+    pub fn key(&self, main_source_id: FileId, inputs: Option<&Dict>) -> u128 {
+        self.key_with_fonts(main_source_id, inputs, &[])
+    }
+
+    pub(crate) fn key_with_fonts(
+        &self,
+        main_source_id: FileId,
+        inputs: Option<&Dict>,
+        extra_fonts: &[Font],
+    ) -> u128 {
+        let Self { cache_key, .. } = self;
+        let inputs = match (cache_key, inputs) {
+            (Some(cache_key), Some(inputs)) => Some(cache_key(inputs)),
+            (_, inputs) => inputs.cloned(),
+        };
+        typst::utils::hash128(&(main_source_id, inputs, extra_fonts))
+    }
+
+    pub(crate) fn get(&self, key: u128) -> Option<CachedDocument> {
+        let mut documents = self.documents.lock().ok()?;
+        documents.clock += 1;
+        let clock = documents.clock;
+        let (cached, last_used) = documents.entries.get_mut(&key)?;
+        *last_used = clock;
+        Some(cached.clone())
+    }
+
+    pub(crate) fn insert(&self, key: u128, cached: CachedDocument) {
+        let Ok(mut documents) = self.documents.lock() else {
+            return;
+        };
+        if self.max_documents == Some(0) {
+            return;
+        }
+        documents.clock += 1;
+        let clock = documents.clock;
+        documents.entries.insert(key, (cached, clock));
+        let Some(max_documents) = self.max_documents else {
+            return;
+        };
+        while documents.entries.len() > max_documents {
+            let oldest = documents
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(key, _)| *key);
+            match oldest {
+                Some(oldest) => documents.entries.remove(&oldest),
+                None => break,
+            };
+        }
+    }
+
+    /// Number of cached documents.
+    pub fn len(&self) -> usize {
+        self.documents
+            .lock()
+            .map_or(0, |documents| documents.entries.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove the cached documents, that accessed `file_id` during their compile, e.g. after
+    /// the file changed. Returns the number of removed documents.
+    pub fn invalidate(&self, file_id: FileId) -> usize {
+        let Ok(mut documents) = self.documents.lock() else {
+            return 0;
+        };
+        let len = documents.entries.len();
+        documents
+            .entries
+            .retain(|_, ((_, _, dependencies, _), _)| !dependencies.contains(&file_id));
+        len - documents.entries.len()
+    }
+
+    /// Remove the cached documents, that accessed any file of the package `spec` during their
+    /// compile, e.g. after the package was replaced. Returns the number of removed documents.
+    pub fn invalidate_package(&self, spec: &PackageSpec) -> usize {
+        let Ok(mut documents) = self.documents.lock() else {
+            return 0;
+        };
+        let len = documents.entries.len();
+        documents.entries.retain(|_, ((_, _, dependencies, _), _)| {
+            !dependencies.iter().any(|id| id.package() == Some(spec))
+        });
+        len - documents.entries.len()
+    }
+
+    /// Remove all cached documents.
+    pub fn clear(&self) {
+        if let Ok(mut documents) = self.documents.lock() {
+            documents.entries.clear();
+        }
+    }
+}
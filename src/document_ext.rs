@@ -0,0 +1,178 @@
+//! Accessors for the pages and frames of a compiled document, e.g. for custom exporters.
+//! The typst types, that are needed to walk a document, are re-exported here.
+//!
+//! ```rust,ignore
+//! use typst_as_lib::document_ext::{DocumentExt, FrameExt, FrameItem};
+//!
+//! if document.page_count() > MAX_PAGES || !document.empty_pages().is_empty() {
+//!     return Err(ContractError::Layout);
+//! }
+//!
+//! for page in document.pages_iter() {
+//!     for (ts, pos, item) in page.frame.leaf_items() {
+//!         if let FrameItem::Text(text) = item {
+//!             export_text(pos.transform(ts), &text.text);
+//!         }
+//!     }
+//! }
+//! ```
+
+use std::num::NonZeroUsize;
+
+use ecow::{eco_format, EcoString};
+
+pub use typst::introspection::Tag;
+pub use typst::layout::{
+    Abs, Frame, FrameItem, GroupItem, Page, PageRange, PageRanges, Point, Position, Size, Transform,
+};
+pub use typst::model::Document;
+pub use typst::text::{Font, Glyph, TextItem};
+pub use typst::visualize::{Color, FixedStroke, Geometry, Image, Paint, Shape};
+
+pub trait DocumentExt {
+    fn page_count(&self) -> usize;
+
+    /// The page with `index`, starting at 0.
+    fn page(&self, index: usize) -> Option<&Page>;
+
+    fn pages_iter(&self) -> std::slice::Iter<'_, Page>;
+
+    /// Width and height of each page.
+    fn page_sizes(&self) -> Vec<Size>;
+
+    /// Indices (starting at 0) of the pages, that have no visible content (see
+    /// `FrameExt::is_blank`).
+    fn empty_pages(&self) -> Vec<usize>;
+
+    /// The pages in `ranges` with their indices (starting at 0).
+    fn pages_in<'a>(&'a self, ranges: &'a PageRanges) -> impl Iterator<Item = (usize, &'a Page)>;
+}
+
+impl DocumentExt for Document {
+    fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    fn page(&self, index: usize) -> Option<&Page> {
+        self.pages.get(index)
+    }
+
+    fn pages_iter(&self) -> std::slice::Iter<'_, Page> {
+        self.pages.iter()
+    }
+
+    fn page_sizes(&self) -> Vec<Size> {
+        self.pages.iter().map(|page| page.frame.size()).collect()
+    }
+
+    fn empty_pages(&self) -> Vec<usize> {
+        self.pages
+            .iter()
+            .enumerate()
+            .filter(|(_, page)| page.frame.is_blank())
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    fn pages_in<'a>(&'a self, ranges: &'a PageRanges) -> impl Iterator<Item = (usize, &'a Page)> {
+        self.pages
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| ranges.includes_page_index(*i))
+    }
+}
+
+/// Parse page ranges like the `--pages` argument of `typst compile`: comma separated pages
+/// and ranges, starting at 1, e.g. `1`, `2-5`, `-3` or `4-`.
+///
+/// ```rust,ignore
+/// let options = PdfExportOptions::new().with_page_ranges(parse_page_ranges("1,3-5")?);
+/// ```
+pub fn parse_page_ranges(value: &str) -> Result<PageRanges, EcoString> {
+    value
+        .split(',')
+        .map(parse_page_range)
+        .collect::<Result<Vec<_>, _>>()
+        .map(PageRanges::new)
+        .map_err(|err| eco_format!("invalid page range `{value}`: {err}"))
+}
+
+fn parse_page_range(value: &str) -> Result<PageRange, &'static str> {
+    let range = match value
+        .split('-')
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        [] | [""] => return Err("page range must not be empty"),
+        [page] => {
+            let page = parse_page_number(page)?;
+            Some(page)..=Some(page)
+        }
+        ["", ""] => return Err("page range must have a start or an end"),
+        [start, ""] => Some(parse_page_number(start)?)..=None,
+        ["", end] => None..=Some(parse_page_number(end)?),
+        [start, end] => {
+            let start = parse_page_number(start)?;
+            let end = parse_page_number(end)?;
+            if start > end {
+                return Err("page range must end at a page after the start");
+            }
+            Some(start)..=Some(end)
+        }
+        _ => return Err("page range must have a single hyphen"),
+    };
+    Ok(range)
+}
+
+fn parse_page_number(value: &str) -> Result<NonZeroUsize, &'static str> {
+    match value.parse::<usize>() {
+        Ok(0) => Err("page numbers start at one"),
+        Ok(page) => Ok(NonZeroUsize::new(page).unwrap()),
+        Err(_) => Err("not a valid page number"),
+    }
+}
+
+pub trait FrameExt {
+    /// All items except groups, flattened in paint order. Each item comes with the transform
+    /// of its group (relative to the frame) and its position inside of that group.
+    /// The position on the frame is `pos.transform(ts)`.
+    fn leaf_items(&self) -> Vec<(Transform, Point, &FrameItem)>;
+
+    /// `true`, if the frame has no text, shapes or images. Links and introspection tags are
+    /// invisible.
+    fn is_blank(&self) -> bool;
+}
+
+impl FrameExt for Frame {
+    fn leaf_items(&self) -> Vec<(Transform, Point, &FrameItem)> {
+        fn walk<'a>(
+            frame: &'a Frame,
+            ts: Transform,
+            items: &mut Vec<(Transform, Point, &'a FrameItem)>,
+        ) {
+            for (pos, item) in frame.items() {
+                match item {
+                    FrameItem::Group(group) => {
+                        let ts = ts
+                            .pre_concat(Transform::translate(pos.x, pos.y))
+                            .pre_concat(group.transform);
+                        walk(&group.frame, ts, items);
+                    }
+                    item => items.push((ts, *pos, item)),
+                }
+            }
+        }
+        let mut items = Vec::new();
+        walk(self, Transform::identity(), &mut items);
+        items
+    }
+
+    fn is_blank(&self) -> bool {
+        self.items().all(|(_, item)| match item {
+            FrameItem::Group(group) => group.frame.is_blank(),
+            FrameItem::Text(_) | FrameItem::Shape(..) | FrameItem::Image(..) => false,
+            FrameItem::Link(..) | FrameItem::Tag(_) => true,
+        })
+    }
+}
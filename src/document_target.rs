@@ -0,0 +1,36 @@
+//! Targets, that a template can be compiled to (`compile_as::<Document>(..)`).
+//!
+//! typst 0.12 only lays out paged documents, so `Document` is the only built-in target.
+//! Later typst releases split it into `PagedDocument` and `HtmlDocument`; they become targets,
+//! when the crate is updated, without changing the `compile_as` methods.
+//!
+//! Other targets can be built from the laid out document, e.g. a pdf:
+//!
+//! ```rust,ignore
+//! struct Pdf(Vec<u8>);
+//!
+//! impl DocumentTarget for Pdf {
+//!     fn from_document(document: Document) -> Result<Self, TypstAsLibError> {
+//!         let pdf = typst_pdf::pdf(&document, &Default::default())?;
+//!         Ok(Pdf(pdf))
+//!     }
+//! }
+//!
+//! let pdf = template.compile_as::<Pdf>().output?;
+//! ```
+
+use typst::model::Document;
+
+use crate::TypstAsLibError;
+
+/// Output of a compile. See the module documentation.
+pub trait DocumentTarget: Sized {
+    /// Create the target from the laid out `document` of a compile.
+    fn from_document(document: Document) -> Result<Self, TypstAsLibError>;
+}
+
+impl DocumentTarget for Document {
+    fn from_document(document: Document) -> Result<Self, TypstAsLibError> {
+        Ok(document)
+    }
+}
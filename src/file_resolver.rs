@@ -1,18 +1,22 @@
-use ecow::eco_format;
+use ecow::{eco_format, EcoString};
 use std::{
     borrow::Cow,
     collections::HashMap,
+    fmt,
+    ops::Range,
     path::{Path, PathBuf},
+    sync::{Arc, RwLock},
 };
 use typst::{
-    diag::{FileError, FileResult},
+    diag::{FileError, FileResult, PackageError},
     foundations::Bytes,
-    syntax::{FileId, Source},
+    syntax::{package::PackageSpec, FileId, Source},
 };
 
 use crate::{
     cached_file_resolver::{CachedFileResolver, IntoCachedFileResolver},
-    util::{bytes_to_source, not_found},
+    metrics::ResolverStats,
+    util::{bytes_to_source, not_found, short_type_name},
     FileIdNewType, SourceNewType,
 };
 
@@ -20,9 +24,346 @@ use crate::{
 /// The default packages sub directory within the package and package cache paths.
 pub const DEFAULT_PACKAGES_SUBDIR: &str = "typst/packages";
 
+/// Environment variable, that overrides the default package path (same as the typst cli).
+pub static PACKAGE_PATH_ENV: &str = "TYPST_PACKAGE_PATH";
+
+/// Environment variable, that overrides the default package cache path (same as the typst cli).
+pub static PACKAGE_CACHE_PATH_ENV: &str = "TYPST_PACKAGE_CACHE_PATH";
+
 pub trait FileResolver {
-    fn resolve_binary(&self, id: FileId) -> FileResult<Cow<Bytes>>;
-    fn resolve_source(&self, id: FileId) -> FileResult<Cow<Source>>;
+    fn resolve_binary(&self, id: FileId) -> FileResult<Cow<'_, Bytes>>;
+    fn resolve_source(&self, id: FileId) -> FileResult<Cow<'_, Source>>;
+
+    /// Name of the resolver, that is used in error messages.
+    fn name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
+    /// Path on disk, where the file with `id` is read from, if the resolver reads from disk
+    /// and the file exists. Used e.g. for depfiles.
+    fn resolve_path(&self, _id: FileId) -> Option<PathBuf> {
+        None
+    }
+
+    /// Whether the resolver only reads from memory (no file system or network access).
+    /// See `TypstTemplateCollection::with_in_memory_only`.
+    fn is_in_memory(&self) -> bool {
+        false
+    }
+
+    /// Remove the file `id` from the caches of the resolver, so it is resolved again.
+    /// See `TypstTemplateCollection::invalidate_file`.
+    fn invalidate(&self, _id: FileId) {}
+
+    /// Remove all files from the caches of the resolver.
+    fn clear_cache(&self) {}
+
+    /// Cache and download statistics. See `TypstTemplateCollection::metrics`.
+    fn stats(&self) -> ResolverStats {
+        ResolverStats::default()
+    }
+
+    /// Why the package of `id` could not be resolved by the last attempt, if the resolver
+    /// resolves packages and the reason is more specific than a `FileError` (see
+    /// `PackageFailure`). Only asked after the resolver returned an error for `id`.
+    fn package_failure(&self, _id: FileId) -> Option<PackageFailure> {
+        None
+    }
+}
+
+/// Why a package resolver could not resolve a package. Reported as the matching
+/// `TypstAsLibError` (`PackageNotCached`, `PackageNotAllowed`, `PackageIntegrity`, ...)
+/// instead of a generic file error. See `FileResolver::package_failure`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PackageFailure {
+    /// The package is not cached and the package resolver is offline.
+    NotCached,
+    /// The package doesn't match the patterns of `PackageResolverBuilder::allow`.
+    NotAllowed,
+    /// The package doesn't match its expected checksum.
+    Integrity {
+        expected: EcoString,
+        found: EcoString,
+    },
+    /// The package has no archive checksum, but
+    /// `PackageResolverBuilder::require_archive_checksums` is set.
+    ChecksumMissing,
+    /// The package is not in the lockfile and `PackageResolverBuilder::frozen_lock` is set.
+    Unpinned,
+    /// The package is cached, but can't be checked against its archive checksum, because the
+    /// package resolver is offline.
+    Unverifiable,
+}
+
+impl PackageFailure {
+    /// The `FileError`, that is returned to typst for the package.
+    pub fn file_error(&self, package: &PackageSpec) -> FileError {
+        let message = match self {
+            Self::NotCached => {
+                eco_format!("{package} is not cached and the package resolver is offline")
+            }
+            Self::NotAllowed => eco_format!("{package} is not allowed by the package resolver"),
+            Self::Integrity { expected, found } => eco_format!(
+                "{package} failed the integrity check (expected {expected}, found {found})"
+            ),
+            Self::ChecksumMissing => {
+                eco_format!("{package} has no archive checksum and is not downloaded")
+            }
+            Self::Unpinned => eco_format!("{package} is not in the lockfile"),
+            Self::Unverifiable => eco_format!(
+                "{package} is cached, but can't be checked against its archive checksum, \
+                because the package resolver is offline (pin it with a lockfile)"
+            ),
+        };
+        FileError::Package(PackageError::Other(Some(message)))
+    }
+}
+
+impl<T> FileResolver for &T
+where
+    T: FileResolver + ?Sized,
+{
+    fn resolve_binary(&self, id: FileId) -> FileResult<Cow<'_, Bytes>> {
+        (**self).resolve_binary(id)
+    }
+
+    fn resolve_source(&self, id: FileId) -> FileResult<Cow<'_, Source>> {
+        (**self).resolve_source(id)
+    }
+
+    fn name(&self) -> &'static str {
+        (**self).name()
+    }
+
+    fn resolve_path(&self, id: FileId) -> Option<PathBuf> {
+        (**self).resolve_path(id)
+    }
+
+    fn is_in_memory(&self) -> bool {
+        (**self).is_in_memory()
+    }
+
+    fn invalidate(&self, id: FileId) {
+        (**self).invalidate(id)
+    }
+
+    fn clear_cache(&self) {
+        (**self).clear_cache()
+    }
+
+    fn stats(&self) -> ResolverStats {
+        (**self).stats()
+    }
+
+    fn package_failure(&self, id: FileId) -> Option<PackageFailure> {
+        (**self).package_failure(id)
+    }
+}
+
+impl<T> FileResolver for Box<T>
+where
+    T: FileResolver + ?Sized,
+{
+    fn resolve_binary(&self, id: FileId) -> FileResult<Cow<'_, Bytes>> {
+        (**self).resolve_binary(id)
+    }
+
+    fn resolve_source(&self, id: FileId) -> FileResult<Cow<'_, Source>> {
+        (**self).resolve_source(id)
+    }
+
+    fn name(&self) -> &'static str {
+        (**self).name()
+    }
+
+    fn resolve_path(&self, id: FileId) -> Option<PathBuf> {
+        (**self).resolve_path(id)
+    }
+
+    fn is_in_memory(&self) -> bool {
+        (**self).is_in_memory()
+    }
+
+    fn invalidate(&self, id: FileId) {
+        (**self).invalidate(id)
+    }
+
+    fn clear_cache(&self) {
+        (**self).clear_cache()
+    }
+
+    fn stats(&self) -> ResolverStats {
+        (**self).stats()
+    }
+
+    fn package_failure(&self, id: FileId) -> Option<PackageFailure> {
+        (**self).package_failure(id)
+    }
+}
+
+impl<T> FileResolver for Arc<T>
+where
+    T: FileResolver + ?Sized,
+{
+    fn resolve_binary(&self, id: FileId) -> FileResult<Cow<'_, Bytes>> {
+        (**self).resolve_binary(id)
+    }
+
+    fn resolve_source(&self, id: FileId) -> FileResult<Cow<'_, Source>> {
+        (**self).resolve_source(id)
+    }
+
+    fn name(&self) -> &'static str {
+        (**self).name()
+    }
+
+    fn resolve_path(&self, id: FileId) -> Option<PathBuf> {
+        (**self).resolve_path(id)
+    }
+
+    fn is_in_memory(&self) -> bool {
+        (**self).is_in_memory()
+    }
+
+    fn invalidate(&self, id: FileId) {
+        (**self).invalidate(id)
+    }
+
+    fn clear_cache(&self) {
+        (**self).clear_cache()
+    }
+
+    fn stats(&self) -> ResolverStats {
+        (**self).stats()
+    }
+
+    fn package_failure(&self, id: FileId) -> Option<PackageFailure> {
+        (**self).package_failure(id)
+    }
+}
+
+/// The errors of all file resolvers, that were tried to resolve the file `id`, in the order
+/// they were tried. Shows which resolver searched where, e.g. to spot a typo in a path.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ResolverErrors {
+    pub id: FileId,
+    pub errors: Vec<ResolverError>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ResolverError {
+    /// Type name of the resolver, e.g. `CachedFileResolver<FileSystemResolver>`.
+    pub resolver: EcoString,
+    pub error: FileError,
+    /// See `FileResolver::package_failure`.
+    pub package_failure: Option<PackageFailure>,
+}
+
+impl ResolverErrors {
+    pub(crate) fn new(id: FileId) -> Self {
+        Self {
+            id,
+            errors: Vec::new(),
+        }
+    }
+
+    pub(crate) fn push(
+        &mut self,
+        resolver: &str,
+        error: FileError,
+        package_failure: Option<PackageFailure>,
+    ) {
+        self.errors.push(ResolverError {
+            resolver: short_type_name(resolver),
+            error,
+            package_failure,
+        });
+    }
+
+    /// The first `PackageFailure` of the resolvers, see `FileResolver::package_failure`.
+    pub fn package_failure(&self) -> Option<&PackageFailure> {
+        self.errors.iter().find_map(
+            |ResolverError {
+                 package_failure, ..
+             }| package_failure.as_ref(),
+        )
+    }
+}
+
+impl fmt::Display for ResolverErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { id, errors } = self;
+        write!(
+            f,
+            "file not found: {}",
+            id.vpath().as_rooted_path().display()
+        )?;
+        if !errors.is_empty() {
+            write!(f, "\ntried:")?;
+        }
+        for ResolverError {
+            resolver, error, ..
+        } in errors
+        {
+            write!(f, "\n  - {resolver}: {error}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ResolverErrors {}
+
+/// Typst only knows `FileError`s, so the errors are combined into one. The most specific
+/// kind is kept: if only `NotFound` errors occurred, the result is `NotFound` with all
+/// searched paths, if the other errors are of one kind (e.g. `AccessDenied`), that error is
+/// returned. Otherwise the result lists each resolver together with its error.
+impl From<ResolverErrors> for FileError {
+    fn from(value: ResolverErrors) -> Self {
+        let ResolverErrors { id, mut errors } = value;
+        // Missing packages in offline mode, packages, that are not allowed, and packages, that
+        // failed the integrity check, are reported as they are, so the reason is obvious.
+        if let Some(i) = errors.iter().position(
+            |ResolverError {
+                 package_failure, ..
+             }| package_failure.is_some(),
+        ) {
+            return errors.swap_remove(i).error;
+        }
+        // Resolvers, that don't have the file, return `NotFound`. The errors of the other
+        // resolvers (e.g. `AccessDenied`) tell, why the file could not be loaded.
+        let mut specific = errors
+            .iter()
+            .map(|ResolverError { error, .. }| error)
+            .filter(|error| !matches!(error, FileError::NotFound(_)));
+        match specific.next() {
+            Some(first) if specific.all(|error| error == first) => return first.clone(),
+            Some(_) => (),
+            None => {
+                let mut searched: Vec<&Path> = Vec::new();
+                for ResolverError { error, .. } in &errors {
+                    if let FileError::NotFound(path) = error {
+                        if !searched.contains(&path.as_path()) {
+                            searched.push(path);
+                        }
+                    }
+                }
+                // In memory resolvers report the virtual path, disk paths are more helpful.
+                if searched.iter().any(|path| path.is_absolute()) {
+                    searched.retain(|path| path.is_absolute());
+                }
+                if searched.is_empty() {
+                    return not_found(id);
+                }
+                // Shown by typst as `file not found (searched at a/x.typ, b/x.typ)`.
+                let searched: Vec<String> = searched
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect();
+                return FileError::NotFound(PathBuf::from(searched.join(", ")));
+            }
+        }
+        FileError::Other(Some(eco_format!("{}", ResolverErrors { id, errors })))
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -37,17 +378,21 @@ impl MainSourceFileResolver {
 }
 
 impl FileResolver for MainSourceFileResolver {
-    fn resolve_binary(&self, id: FileId) -> FileResult<Cow<Bytes>> {
+    fn resolve_binary(&self, id: FileId) -> FileResult<Cow<'_, Bytes>> {
         Err(not_found(id))
     }
 
-    fn resolve_source(&self, id: FileId) -> FileResult<Cow<Source>> {
+    fn resolve_source(&self, id: FileId) -> FileResult<Cow<'_, Source>> {
         let Self { main_source } = self;
         if id == main_source.id() {
             return Ok(Cow::Borrowed(main_source));
         }
         Err(not_found(id))
     }
+
+    fn is_in_memory(&self) -> bool {
+        true
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -73,16 +418,249 @@ impl StaticSourceFileResolver {
 }
 
 impl FileResolver for StaticSourceFileResolver {
-    fn resolve_binary(&self, id: FileId) -> FileResult<Cow<Bytes>> {
+    fn resolve_binary(&self, id: FileId) -> FileResult<Cow<'_, Bytes>> {
         Err(not_found(id))
     }
 
-    fn resolve_source(&self, id: FileId) -> FileResult<Cow<Source>> {
+    fn resolve_source(&self, id: FileId) -> FileResult<Cow<'_, Source>> {
         self.sources
             .get(&id)
-            .map(|s| Cow::Borrowed(s))
+            .map(Cow::Borrowed)
             .ok_or_else(|| not_found(id))
     }
+
+    fn is_in_memory(&self) -> bool {
+        true
+    }
+}
+
+/// Sources, that can be replaced or edited after the resolver was added to a collection, e.g.
+/// by a live preview on each keystroke. Edits go through `Source::replace` and `Source::edit`,
+/// so typst only reparses the changed parts. Cloning is cheap, all clones share the same
+/// sources.
+#[derive(Debug, Clone, Default)]
+pub struct MutableSourceFileResolver {
+    sources: Arc<RwLock<HashMap<FileId, Source>>>,
+}
+
+impl MutableSourceFileResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add or overwrite a source.
+    pub fn insert<S>(&self, source: S)
+    where
+        S: Into<SourceNewType>,
+    {
+        let SourceNewType(source) = source.into();
+        if let Ok(mut sources) = self.sources.write() {
+            sources.insert(source.id(), source);
+        }
+    }
+
+    /// Returns `true`, if the source existed.
+    pub fn remove<F>(&self, id: F) -> bool
+    where
+        F: Into<FileIdNewType>,
+    {
+        let FileIdNewType(id) = id.into();
+        self.sources
+            .write()
+            .is_ok_and(|mut sources| sources.remove(&id).is_some())
+    }
+
+    pub fn contains<F>(&self, id: F) -> bool
+    where
+        F: Into<FileIdNewType>,
+    {
+        let FileIdNewType(id) = id.into();
+        self.sources
+            .read()
+            .is_ok_and(|sources| sources.contains_key(&id))
+    }
+
+    /// Replace the text of the source `id`, which is created, if it does not exist.
+    /// Returns the range in the new text, that was changed (see `Source::replace`).
+    pub fn update<F>(&self, id: F, new_text: &str) -> Range<usize>
+    where
+        F: Into<FileIdNewType>,
+    {
+        let FileIdNewType(id) = id.into();
+        let Ok(mut sources) = self.sources.write() else {
+            return 0..0;
+        };
+        match sources.get_mut(&id) {
+            Some(source) => source.replace(new_text),
+            None => {
+                sources.insert(id, Source::new(id, new_text.to_owned()));
+                0..new_text.len()
+            }
+        }
+    }
+
+    /// Replace the byte range `replace` of the source `id` with `with`.
+    /// Returns the range in the new text, that was changed (see `Source::edit`).
+    pub fn edit<F>(&self, id: F, replace: Range<usize>, with: &str) -> FileResult<Range<usize>>
+    where
+        F: Into<FileIdNewType>,
+    {
+        let FileIdNewType(id) = id.into();
+        let mut sources = self
+            .sources
+            .write()
+            .map_err(|_| FileError::Other(Some(eco_format!("Could not lock sources"))))?;
+        let source = sources.get_mut(&id).ok_or_else(|| not_found(id))?;
+        let text = source.text();
+        if replace.start > replace.end
+            || !text.is_char_boundary(replace.start)
+            || !text.is_char_boundary(replace.end)
+        {
+            return Err(FileError::Other(Some(eco_format!(
+                "Invalid range {replace:?} for {:?} with {} bytes",
+                id.vpath(),
+                text.len()
+            ))));
+        }
+        Ok(source.edit(replace, with))
+    }
+}
+
+impl FileResolver for MutableSourceFileResolver {
+    fn resolve_binary(&self, id: FileId) -> FileResult<Cow<'_, Bytes>> {
+        let source = self.resolve_source(id)?;
+        Ok(Cow::Owned(Bytes::from(source.text().as_bytes().to_vec())))
+    }
+
+    fn resolve_source(&self, id: FileId) -> FileResult<Cow<'_, Source>> {
+        let sources = self
+            .sources
+            .read()
+            .map_err(|_| FileError::Other(Some(eco_format!("Could not lock sources"))))?;
+        sources
+            .get(&id)
+            .cloned()
+            .map(Cow::Owned)
+            .ok_or_else(|| not_found(id))
+    }
+
+    fn is_in_memory(&self) -> bool {
+        true
+    }
+}
+
+/// Files, that can be added and removed after the resolver was added to a collection, e.g.
+/// generated charts or uploaded CSVs, that are staged for a compile. Added with
+/// `TypstTemplateCollection::with_overlay_resolver`, it is resolved before all other file
+/// resolvers, so it can also shadow their files. Cloning is cheap, all clones share the
+/// same files.
+///
+/// ```rust,ignore
+/// let overlay = OverlayResolver::new();
+/// let collection = TypstTemplateCollection::new(fonts).with_overlay_resolver(overlay.clone());
+/// // ...
+/// overlay.insert("/data/sales.csv", csv);
+/// let doc = collection.compile("/report.typ").output?;
+/// overlay.remove("/data/sales.csv");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct OverlayResolver {
+    files: Arc<RwLock<HashMap<FileId, OverlayFile>>>,
+}
+
+#[derive(Debug, Clone)]
+enum OverlayFile {
+    Binary(Bytes),
+    Source(Source),
+}
+
+impl OverlayResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add or overwrite the file `id`. It can also be imported as source, if it is UTF-8.
+    pub fn insert<F, B>(&self, id: F, bytes: B)
+    where
+        F: Into<FileIdNewType>,
+        B: Into<Bytes>,
+    {
+        let FileIdNewType(id) = id.into();
+        if let Ok(mut files) = self.files.write() {
+            files.insert(id, OverlayFile::Binary(bytes.into()));
+        }
+    }
+
+    /// Add or overwrite a source.
+    pub fn insert_source<S>(&self, source: S)
+    where
+        S: Into<SourceNewType>,
+    {
+        let SourceNewType(source) = source.into();
+        if let Ok(mut files) = self.files.write() {
+            files.insert(source.id(), OverlayFile::Source(source));
+        }
+    }
+
+    /// Returns `true`, if the file existed.
+    pub fn remove<F>(&self, id: F) -> bool
+    where
+        F: Into<FileIdNewType>,
+    {
+        let FileIdNewType(id) = id.into();
+        self.files
+            .write()
+            .is_ok_and(|mut files| files.remove(&id).is_some())
+    }
+
+    pub fn contains<F>(&self, id: F) -> bool
+    where
+        F: Into<FileIdNewType>,
+    {
+        let FileIdNewType(id) = id.into();
+        self.files.read().is_ok_and(|files| files.contains_key(&id))
+    }
+
+    /// Remove all files.
+    pub fn clear(&self) {
+        if let Ok(mut files) = self.files.write() {
+            files.clear();
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.read().map_or(true, |files| files.is_empty())
+    }
+
+    fn get(&self, id: FileId) -> FileResult<OverlayFile> {
+        let files = self
+            .files
+            .read()
+            .map_err(|_| FileError::Other(Some(eco_format!("Could not lock files"))))?;
+        files.get(&id).cloned().ok_or_else(|| not_found(id))
+    }
+}
+
+impl FileResolver for OverlayResolver {
+    fn resolve_binary(&self, id: FileId) -> FileResult<Cow<'_, Bytes>> {
+        let bytes = match self.get(id)? {
+            OverlayFile::Binary(bytes) => bytes,
+            OverlayFile::Source(source) => Bytes::from(source.text().as_bytes().to_vec()),
+        };
+        Ok(Cow::Owned(bytes))
+    }
+
+    fn resolve_source(&self, id: FileId) -> FileResult<Cow<'_, Source>> {
+        let source = match self.get(id)? {
+            OverlayFile::Binary(bytes) => bytes_to_source(id, &bytes)?,
+            OverlayFile::Source(source) => source,
+        };
+        Ok(Cow::Owned(source))
+    }
+
+    fn is_in_memory(&self) -> bool {
+        true
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -109,22 +687,68 @@ impl StaticFileResolver {
 }
 
 impl FileResolver for StaticFileResolver {
-    fn resolve_binary(&self, id: FileId) -> FileResult<Cow<Bytes>> {
+    fn resolve_binary(&self, id: FileId) -> FileResult<Cow<'_, Bytes>> {
         self.binaries
             .get(&id)
-            .map(|b| Cow::Borrowed(b))
+            .map(Cow::Borrowed)
             .ok_or_else(|| not_found(id))
     }
 
-    fn resolve_source(&self, id: FileId) -> FileResult<Cow<Source>> {
+    fn resolve_source(&self, id: FileId) -> FileResult<Cow<'_, Source>> {
         Err(not_found(id))
     }
+
+    fn is_in_memory(&self) -> bool {
+        true
+    }
+}
+
+/// Resolver for a template directory, that was embedded into the binary. Usually created
+/// with the `embed_templates!` macro (feature `embed`).
+#[derive(Debug, Clone)]
+pub struct EmbeddedResolver {
+    sources: StaticSourceFileResolver,
+    binaries: StaticFileResolver,
+}
+
+impl EmbeddedResolver {
+    /// `sources` and `binaries` are pairs of virtual path and content.
+    pub fn new(sources: &[(&str, &'static str)], binaries: &[(&str, &'static [u8])]) -> Self {
+        Self {
+            sources: StaticSourceFileResolver::new(sources.iter().copied()),
+            binaries: StaticFileResolver::new(
+                sources
+                    .iter()
+                    .map(|&(path, source)| (path, Bytes::from_static(source.as_bytes())))
+                    .chain(
+                        binaries
+                            .iter()
+                            .map(|&(path, b)| (path, Bytes::from_static(b))),
+                    ),
+            ),
+        }
+    }
+}
+
+impl FileResolver for EmbeddedResolver {
+    fn resolve_binary(&self, id: FileId) -> FileResult<Cow<'_, Bytes>> {
+        self.binaries.resolve_binary(id)
+    }
+
+    fn resolve_source(&self, id: FileId) -> FileResult<Cow<'_, Source>> {
+        self.sources.resolve_source(id)
+    }
+
+    fn is_in_memory(&self) -> bool {
+        true
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct FileSystemResolver {
     root: PathBuf,
     local_package_root: Option<PathBuf>,
+    sandboxed: bool,
 }
 
 impl FileSystemResolver {
@@ -136,9 +760,17 @@ impl FileSystemResolver {
         Self {
             root,
             local_package_root: None,
+            sandboxed: false,
         }
     }
 
+    /// Jail the resolver in the root (or the package directory for package files), e.g. when
+    /// user supplied templates are compiled on a server. Paths with `..` and symlinks, that
+    /// point outside of the root, are rejected with `FileError::AccessDenied`.
+    pub fn sandboxed(self, sandboxed: bool) -> Self {
+        Self { sandboxed, ..self }
+    }
+
     /// Use other path to look for local packages
     pub fn with_local_package_root(self, path: PathBuf) -> Self {
         Self {
@@ -148,9 +780,16 @@ impl FileSystemResolver {
     }
 
     fn resolve_bytes(&self, id: FileId) -> FileResult<Vec<u8>> {
+        let path = self.resolve_file_path(id)?;
+        let content = std::fs::read(&path).map_err(|error| FileError::from_io(error, &path))?;
+        Ok(content)
+    }
+
+    fn resolve_file_path(&self, id: FileId) -> FileResult<PathBuf> {
         let Self {
             root,
             local_package_root,
+            sandboxed,
         } = self;
         // https://github.com/typst/typst/blob/16736feb13eec87eb9ca114deaeb4f7eeb7409d2/crates/typst-kit/src/package.rs#L102C16-L102C38
         let dir: Cow<Path> = if let Some(package) = id.package() {
@@ -169,12 +808,133 @@ impl FileSystemResolver {
             Cow::Borrowed(root)
         };
 
-        let path = id
-            .vpath()
+        // `resolve` fails, if the path escapes `dir` with `..`.
+        let path = id.vpath().resolve(&dir).ok_or_else(|| match sandboxed {
+            true => FileError::AccessDenied,
+            false => FileError::NotFound(dir.to_path_buf()),
+        })?;
+        if *sandboxed {
+            return jail(&dir, &path);
+        }
+        Ok(path)
+    }
+}
+
+/// Returns the canonical `path`, if it is inside of `root` after following all symlinks.
+fn jail(root: &Path, path: &Path) -> FileResult<PathBuf> {
+    if path
+        .components()
+        .any(|component| component == std::path::Component::ParentDir)
+    {
+        return Err(FileError::AccessDenied);
+    }
+    let root = root
+        .canonicalize()
+        .map_err(|error| FileError::from_io(error, root))?;
+    let path = path
+        .canonicalize()
+        .map_err(|error| FileError::from_io(error, path))?;
+    if !path.starts_with(&root) {
+        return Err(FileError::AccessDenied);
+    }
+    Ok(path)
+}
+
+/// Resolves packages from the local package directories, the same way the typst cli does,
+/// so `@local/...` imports and pre-vendored `@preview` packages work without network access.
+/// Packages are looked up as `{namespace}/{name}/{version}` in the package path
+/// (`{data-dir}/typst/packages`, e.g. `~/.local/share/typst/packages`) first, then in
+/// the package cache (`{cache-dir}/typst/packages`). `TYPST_PACKAGE_PATH` and
+/// `TYPST_PACKAGE_CACHE_PATH` override the defaults.
+#[derive(Debug, Clone)]
+pub struct LocalPackagesResolver {
+    package_path: Option<PathBuf>,
+    package_cache_path: Option<PathBuf>,
+}
+
+impl LocalPackagesResolver {
+    pub fn new() -> Self {
+        let from_env = |env: &str, dir: Option<PathBuf>| {
+            std::env::var_os(env)
+                .map(PathBuf::from)
+                .or_else(|| dir.map(|dir| dir.join(DEFAULT_PACKAGES_SUBDIR)))
+        };
+        Self {
+            package_path: from_env(PACKAGE_PATH_ENV, dirs::data_dir()),
+            package_cache_path: from_env(PACKAGE_CACHE_PATH_ENV, dirs::cache_dir()),
+        }
+    }
+
+    pub fn with_package_path(self, path: PathBuf) -> Self {
+        Self {
+            package_path: Some(path),
+            ..self
+        }
+    }
+
+    pub fn with_package_cache_path(self, path: PathBuf) -> Self {
+        Self {
+            package_cache_path: Some(path),
+            ..self
+        }
+    }
+
+    fn resolve_file_path(&self, id: FileId) -> FileResult<PathBuf> {
+        let Self {
+            package_path,
+            package_cache_path,
+        } = self;
+        let Some(package) = id.package() else {
+            return Err(not_found(id));
+        };
+        let subdir = Path::new(package.namespace.as_str())
+            .join(package.name.as_str())
+            .join(package.version.to_string());
+        let dir = [package_path, package_cache_path]
+            .into_iter()
+            .flatten()
+            .map(|root| root.join(&subdir))
+            .find(|dir| dir.is_dir())
+            .ok_or_else(|| FileError::Package(PackageError::NotFound(package.clone())))?;
+        id.vpath()
             .resolve(&dir)
-            .ok_or_else(|| FileError::NotFound(dir.to_path_buf()))?;
-        let content = std::fs::read(&path).map_err(|error| FileError::from_io(error, &path))?;
-        Ok(content.into())
+            .ok_or_else(|| FileError::AccessDenied)
+    }
+
+    fn resolve_bytes(&self, id: FileId) -> FileResult<Vec<u8>> {
+        let path = self.resolve_file_path(id)?;
+        std::fs::read(&path).map_err(|error| FileError::from_io(error, &path))
+    }
+}
+
+impl Default for LocalPackagesResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IntoCachedFileResolver for LocalPackagesResolver {
+    fn into_cached(self) -> CachedFileResolver<Self> {
+        CachedFileResolver::new(self)
+            .with_in_memory_source_cache()
+            .with_in_memory_binary_cache()
+    }
+}
+
+impl FileResolver for LocalPackagesResolver {
+    fn resolve_binary(&self, id: FileId) -> FileResult<Cow<'_, Bytes>> {
+        let b = self.resolve_bytes(id)?;
+        Ok(Cow::Owned(b.into()))
+    }
+
+    fn resolve_source(&self, id: FileId) -> FileResult<Cow<'_, Source>> {
+        let file = self.resolve_bytes(id)?;
+        let source = bytes_to_source(id, &file)?;
+        Ok(Cow::Owned(source))
+    }
+
+    fn resolve_path(&self, id: FileId) -> Option<PathBuf> {
+        self.resolve_file_path(id).ok().filter(|path| path.exists())
     }
 }
 
@@ -187,14 +947,18 @@ impl IntoCachedFileResolver for FileSystemResolver {
 }
 
 impl FileResolver for FileSystemResolver {
-    fn resolve_binary(&self, id: FileId) -> FileResult<Cow<Bytes>> {
+    fn resolve_binary(&self, id: FileId) -> FileResult<Cow<'_, Bytes>> {
         let b = self.resolve_bytes(id)?;
         Ok(Cow::Owned(b.into()))
     }
 
-    fn resolve_source(&self, id: FileId) -> FileResult<Cow<Source>> {
+    fn resolve_source(&self, id: FileId) -> FileResult<Cow<'_, Source>> {
         let file = self.resolve_bytes(id)?;
         let source = bytes_to_source(id, &file)?;
         Ok(Cow::Owned(source))
     }
+
+    fn resolve_path(&self, id: FileId) -> Option<PathBuf> {
+        self.resolve_file_path(id).ok().filter(|path| path.exists())
+    }
 }
@@ -0,0 +1,109 @@
+use std::collections::BTreeSet;
+
+use ecow::EcoString;
+use typst::{
+    diag::{Severity, SourceDiagnostic, Warned},
+    layout::FrameItem,
+    model::Document,
+    text::{Font, FontVariant},
+};
+
+use crate::document_ext::FrameExt;
+
+/// Prefix of the warning, that typst emits for font families, that are not available.
+static UNKNOWN_FONT_FAMILY: &str = "unknown font family: ";
+
+/// Which fonts a compiled document uses and which fonts and characters are missing, e.g. to
+/// fail or alert, when a template needs a font, that was not loaded. Typst does not fail on
+/// missing fonts, it falls back to other fonts and draws missing characters as empty boxes.
+///
+/// ```rust,ignore
+/// let output = template.compile();
+/// let report = FontReport::from_warned(&output);
+/// if !report.is_complete() {
+///     log::error!("missing fonts: {:?}", report.missing_families);
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FontReport {
+    /// Fonts, that glyphs were drawn with, in the order of their first use.
+    pub used_fonts: Vec<UsedFont>,
+    /// Families, that were requested (e.g. with `#set text(font: ..)`), but are not available.
+    /// Lowercase, as typst compares families case-insensitively. Sorted.
+    pub missing_families: Vec<EcoString>,
+    /// Characters, that no available font covers. Sorted.
+    pub missing_characters: Vec<char>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsedFont {
+    pub family: EcoString,
+    pub variant: FontVariant,
+    /// Number of glyphs drawn with the font.
+    pub glyphs: usize,
+}
+
+impl FontReport {
+    /// `warnings` are the warnings of the compile of `document`, the missing families are
+    /// taken from them.
+    pub fn new(document: &Document, warnings: &[SourceDiagnostic]) -> Self {
+        let mut used_fonts: Vec<UsedFont> = Vec::new();
+        // Documents use only a few fonts, a linear search is fast enough.
+        let mut fonts: Vec<&Font> = Vec::new();
+        let mut missing_characters = BTreeSet::new();
+        for page in &document.pages {
+            for (_, _, item) in page.frame.leaf_items() {
+                let FrameItem::Text(text) = item else {
+                    continue;
+                };
+                let i = match fonts.iter().position(|font| **font == text.font) {
+                    Some(i) => i,
+                    None => {
+                        let info = text.font.info();
+                        fonts.push(&text.font);
+                        used_fonts.push(UsedFont {
+                            family: info.family.as_str().into(),
+                            variant: info.variant,
+                            glyphs: 0,
+                        });
+                        used_fonts.len() - 1
+                    }
+                };
+                used_fonts[i].glyphs += text.glyphs.len();
+                // Glyph 0 is `.notdef`, the box, that is drawn for missing characters.
+                missing_characters.extend(
+                    text.glyphs
+                        .iter()
+                        .filter(|glyph| glyph.id == 0)
+                        .filter_map(|glyph| text.text.get(glyph.range()))
+                        .flat_map(str::chars)
+                        .filter(|c| !c.is_whitespace()),
+                );
+            }
+        }
+        let missing_families: BTreeSet<EcoString> = warnings
+            .iter()
+            .filter(|warning| warning.severity == Severity::Warning)
+            .filter_map(|warning| warning.message.strip_prefix(UNKNOWN_FONT_FAMILY))
+            .map(Into::into)
+            .collect();
+        Self {
+            used_fonts,
+            missing_families: missing_families.into_iter().collect(),
+            missing_characters: missing_characters.into_iter().collect(),
+        }
+    }
+
+    /// Report of a compile. Empty, if the compile failed.
+    pub fn from_warned<E>(output: &Warned<Result<Document, E>>) -> Self {
+        match &output.output {
+            Ok(document) => Self::new(document, &output.warnings),
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// `true`, if no families and no characters are missing.
+    pub fn is_complete(&self) -> bool {
+        self.missing_families.is_empty() && self.missing_characters.is_empty()
+    }
+}
@@ -0,0 +1,80 @@
+use std::{
+    hash::{Hash, Hasher},
+    sync::{Arc, Mutex},
+};
+
+use typst::{
+    text::{Font, FontBook},
+    utils::{hash128, LazyHash},
+};
+
+/// Fonts, that are only visible to the compiles they are passed to (e.g. fonts uploaded by
+/// one tenant). The fonts of the collection stay visible as well. See
+/// `TypstTemplateCollection::compile_with_fonts`.
+///
+/// Typst needs a single `FontBook` for a compile, so the set builds one with the fonts of the
+/// collection on first use and keeps it, until the fonts of the collection change. Reuse the
+/// set (or a clone of it) for the compiles of the same tenant.
+#[derive(Debug, Clone, Default)]
+pub struct FontSet {
+    pub(crate) fonts: Vec<Font>,
+    pub(crate) book: ExtendedBook,
+}
+
+impl FontSet {
+    pub fn new<V>(fonts: V) -> Self
+    where
+        V: Into<Vec<Font>>,
+    {
+        Self {
+            fonts: fonts.into(),
+            book: Default::default(),
+        }
+    }
+
+    pub fn fonts(&self) -> &[Font] {
+        &self.fonts
+    }
+}
+
+impl Hash for FontSet {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.fonts.hash(state);
+    }
+}
+
+/// Book of the collection followed by additional fonts (of a `FontSet` or `SharedRegistry`).
+/// Only built again, if the book or the fonts changed. Cloning is cheap, all clones share the
+/// same book.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ExtendedBook(Arc<Mutex<Option<CachedBook>>>);
+
+/// Hash of the collection book and the additional fonts, and the extended book.
+type CachedBook = (u128, Arc<LazyHash<FontBook>>);
+
+impl ExtendedBook {
+    pub(crate) fn get(
+        &self,
+        collection_book: &LazyHash<FontBook>,
+        fonts: &[Font],
+    ) -> Arc<LazyHash<FontBook>> {
+        // The hashes of a `LazyHash` and of the data of a `Font` are only computed once.
+        let hash = hash128(&(collection_book, fonts));
+        let ExtendedBook(cached) = self;
+        let mut cached = cached.lock().ok();
+        if let Some(Some((cached_hash, book))) = cached.as_deref() {
+            if *cached_hash == hash {
+                return book.clone();
+            }
+        }
+        let mut book = FontBook::clone(collection_book);
+        for font in fonts {
+            book.push(font.info().clone());
+        }
+        let book = Arc::new(LazyHash::new(book));
+        if let Some(cached) = cached.as_deref_mut() {
+            *cached = Some((hash, book.clone()));
+        }
+        book
+    }
+}
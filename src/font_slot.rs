@@ -0,0 +1,238 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use ecow::eco_format;
+use typst::{
+    diag::{FileError, FileResult},
+    foundations::Bytes,
+    text::{Font, FontInfo},
+};
+
+/// A font of a collection. Fonts, that were created from a file or from bytes, are only parsed,
+/// when they are used, and can be dropped again, when they were not used for a while
+/// (see `TypstTemplateCollection::with_font_eviction`). Only the font info is read up front.
+pub struct FontSlot {
+    info: FontInfo,
+    source: FontSource,
+    font: Mutex<Option<Font>>,
+    last_used: Mutex<Option<Instant>>,
+}
+
+enum FontSource {
+    /// Fonts passed as `Font` are never dropped.
+    Loaded,
+    Path {
+        path: PathBuf,
+        index: u32,
+    },
+    /// The data is kept in memory, only the parsed font is dropped.
+    Bytes {
+        data: Bytes,
+        index: u32,
+    },
+}
+
+impl FontSlot {
+    /// Font face `index` of the font file at `path`. The file is read once to get the font
+    /// info and again, when the font is used.
+    pub fn from_path<P>(path: P, index: u32) -> FileResult<Self>
+    where
+        P: Into<PathBuf>,
+    {
+        let path = path.into();
+        let data = std::fs::read(&path).map_err(|error| FileError::from_io(error, &path))?;
+        let info = FontInfo::new(&data, index).ok_or_else(|| invalid_font(&path, index))?;
+        Ok(Self::from_path_with_info(path, index, info))
+    }
+
+    /// All font faces of the font file (or font collection) at `path`. The file is read
+    /// once to get the font infos and again, when a font is used.
+    pub fn all_from_path<P>(path: P) -> FileResult<Vec<Self>>
+    where
+        P: Into<PathBuf>,
+    {
+        let path = path.into();
+        let data = std::fs::read(&path).map_err(|error| FileError::from_io(error, &path))?;
+        let slots: Vec<_> = face_infos(&data)
+            .map(|(index, info)| Self::from_path_with_info(path.clone(), index, info))
+            .collect();
+        if slots.is_empty() {
+            return Err(invalid_font(&path, 0));
+        }
+        Ok(slots)
+    }
+
+    /// All font faces of the font files (`.ttf`, `.otf`, `.ttc` and `.otc`) in the directory
+    /// `path` and its subdirectories, sorted by path. Files, that are not valid fonts, are
+    /// skipped. Faces with the same font info (e.g. copies of a file) are only added once.
+    pub fn all_from_dir<P>(path: P) -> FileResult<Vec<Self>>
+    where
+        P: AsRef<Path>,
+    {
+        let mut files = Vec::new();
+        collect_font_files(path.as_ref(), &mut files)?;
+        files.sort();
+        let mut infos = HashSet::new();
+        Ok(files
+            .into_iter()
+            .filter_map(|file| Self::all_from_path(file).ok())
+            .flatten()
+            .filter(|slot| infos.insert(slot.info.clone()))
+            .collect())
+    }
+
+    /// Font face `index` of `data`. Returns `None`, if there is no valid font with `index`.
+    pub fn from_bytes<B>(data: B, index: u32) -> Option<Self>
+    where
+        B: Into<Bytes>,
+    {
+        let data = data.into();
+        let info = FontInfo::new(&data, index)?;
+        Some(Self::lazy(info, FontSource::Bytes { data, index }))
+    }
+
+    /// All font faces of `data` (e.g. a font collection).
+    pub fn all_from_bytes<B>(data: B) -> Vec<Self>
+    where
+        B: Into<Bytes>,
+    {
+        let data = data.into();
+        face_infos(&data)
+            .map(|(index, info)| {
+                let data = data.clone();
+                Self::lazy(info, FontSource::Bytes { data, index })
+            })
+            .collect()
+    }
+
+    /// Like `from_path`, but with an already known font info.
+    pub(crate) fn from_path_with_info(path: PathBuf, index: u32, info: FontInfo) -> Self {
+        Self::lazy(info, FontSource::Path { path, index })
+    }
+
+    fn lazy(info: FontInfo, source: FontSource) -> Self {
+        Self {
+            info,
+            source,
+            font: Default::default(),
+            last_used: Default::default(),
+        }
+    }
+
+    pub fn info(&self) -> &FontInfo {
+        &self.info
+    }
+
+    /// Whether the font is loaded without reading a file, i.e. it was not created with
+    /// `from_path`. See `TypstTemplateCollection::with_in_memory_only`.
+    pub fn is_in_memory(&self) -> bool {
+        !matches!(self.source, FontSource::Path { .. })
+    }
+
+    /// The font, parsed on first use.
+    pub(crate) fn get(&self) -> Option<Font> {
+        if let Ok(mut last_used) = self.last_used.lock() {
+            *last_used = Some(Instant::now());
+        }
+        let mut font = self.font.lock().ok()?;
+        if font.is_none() {
+            #[cfg(feature = "tracing")]
+            let start = Instant::now();
+            *font = match &self.source {
+                FontSource::Loaded => None,
+                FontSource::Path { path, index } => {
+                    let data = std::fs::read(path).ok()?;
+                    Font::new(Bytes::from(data), *index)
+                }
+                FontSource::Bytes { data, index } => Font::new(data.clone(), *index),
+            };
+            #[cfg(feature = "tracing")]
+            tracing::debug!(
+                family = %self.info.family,
+                loaded = font.is_some(),
+                duration = ?start.elapsed(),
+                "load font"
+            );
+        }
+        font.clone()
+    }
+
+    /// Drops the parsed font, if it can be loaded again and was not used for `max_idle`.
+    pub(crate) fn evict_if_idle(&self, max_idle: Duration) -> bool {
+        if matches!(self.source, FontSource::Loaded) {
+            return false;
+        }
+        let idle = match self.last_used.lock() {
+            Ok(last_used) => !last_used.is_some_and(|last_used| last_used.elapsed() < max_idle),
+            Err(_) => false,
+        };
+        if !idle {
+            return false;
+        }
+        match self.font.lock() {
+            Ok(mut font) => font.take().is_some(),
+            Err(_) => false,
+        }
+    }
+}
+
+impl From<Font> for FontSlot {
+    fn from(font: Font) -> Self {
+        Self {
+            info: font.info().clone(),
+            source: FontSource::Loaded,
+            font: Mutex::new(Some(font)),
+            last_used: Default::default(),
+        }
+    }
+}
+
+/// The default fonts of the typst cli (feature `embed-fonts`). They are part of the binary
+/// and only parsed, when they are used.
+#[cfg(feature = "embed-fonts")]
+pub fn embedded_fonts() -> Vec<FontSlot> {
+    typst_assets::fonts()
+        .flat_map(|data| FontSlot::all_from_bytes(Bytes::from_static(data)))
+        .collect()
+}
+
+/// Infos of the faces in `data` with their index, up to the first face, that is not valid.
+fn face_infos(data: &[u8]) -> impl Iterator<Item = (u32, FontInfo)> + '_ {
+    (0..).map_while(move |index| Some((index, FontInfo::new(data, index)?)))
+}
+
+fn collect_font_files(dir: &Path, files: &mut Vec<PathBuf>) -> FileResult<()> {
+    let entries = std::fs::read_dir(dir).map_err(|error| FileError::from_io(error, dir))?;
+    for entry in entries {
+        let path = entry
+            .map_err(|error| FileError::from_io(error, dir))?
+            .path();
+        if path.is_dir() {
+            collect_font_files(&path, files)?;
+            continue;
+        }
+        let is_font = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| {
+                ["ttf", "otf", "ttc", "otc"]
+                    .iter()
+                    .any(|font| extension.eq_ignore_ascii_case(font))
+            });
+        if is_font {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn invalid_font(path: &Path, index: u32) -> FileError {
+    FileError::Other(Some(eco_format!(
+        "No font with index {index} in {}",
+        path.display()
+    )))
+}
@@ -0,0 +1,75 @@
+use typst::{foundations::Dict, introspection::Introspector, model::Document, syntax::FileId};
+
+use crate::FileIdNewType;
+
+/// Name of the value next to the inputs (`sys.page_offset` by default), that contains the
+/// number of pages of all preceding fragments. See `Fragment::with_page_offset`.
+pub static PAGE_OFFSET_KEY: &str = "page_offset";
+
+/// Part of a document (e.g. cover, body section, appendix), that is compiled on its own.
+/// See `TypstTemplateCollection::compile_fragments`.
+#[derive(Debug, Clone)]
+pub struct Fragment {
+    pub main_source_id: FileId,
+    pub inputs: Option<Dict>,
+    /// See `with_page_offset`.
+    pub page_offset: bool,
+}
+
+impl Fragment {
+    pub fn new<F>(main_source_id: F) -> Self
+    where
+        F: Into<FileIdNewType>,
+    {
+        let FileIdNewType(main_source_id) = main_source_id.into();
+        Self {
+            main_source_id,
+            inputs: None,
+            page_offset: false,
+        }
+    }
+
+    pub fn with_input<D>(self, inputs: D) -> Self
+    where
+        D: Into<Dict>,
+    {
+        Self {
+            inputs: Some(inputs.into()),
+            ..self
+        }
+    }
+
+    /// Define the number of pages of all preceding fragments as `sys.page_offset` (next to
+    /// the inputs, also for a custom inject location), so the page numbering can be continued
+    /// with `#counter(page).update(sys.page_offset + 1)`. The offset is not part of the
+    /// inputs, but the cached document of the fragment is only reused for the same offset,
+    /// so only set it for fragments, that need it.
+    pub fn with_page_offset(self) -> Self {
+        Self {
+            page_offset: true,
+            ..self
+        }
+    }
+}
+
+impl<F> From<F> for Fragment
+where
+    F: Into<FileIdNewType>,
+{
+    fn from(main_source_id: F) -> Self {
+        Fragment::new(main_source_id)
+    }
+}
+
+/// Concatenates the pages of `documents`. The document info is taken from the first document.
+pub(crate) fn concat_documents(documents: Vec<Document>) -> Document {
+    let mut documents = documents.into_iter();
+    let Some(mut document) = documents.next() else {
+        return Document::default();
+    };
+    for other in documents {
+        document.pages.extend(other.pages);
+    }
+    document.introspector = Introspector::new(&document.pages);
+    document
+}
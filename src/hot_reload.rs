@@ -0,0 +1,149 @@
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use ecow::eco_format;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use typst::{
+    diag::{FileError, FileResult},
+    foundations::Bytes,
+    syntax::{FileId, Source, VirtualPath},
+};
+
+use crate::{
+    cached_file_resolver::{CachedFileResolver, IntoCachedFileResolver},
+    document_cache::DocumentCache,
+    file_resolver::{FileResolver, FileSystemResolver, PackageFailure},
+    metrics::ResolverStats,
+};
+
+/// Callback, that is called with the paths of the changed files.
+pub type OnFileChange = Box<dyn Fn(&[PathBuf]) + Send + Sync + 'static>;
+
+/// Cached `FileSystemResolver`, that watches `root` and drops changed files from its cache
+/// (feature `hot-reload`), so a long running process picks up edited templates.
+///
+/// Compiled documents in a `DocumentCache` are not affected by the file cache, use
+/// `with_document_cache` to clear them as well:
+///
+/// ```rust,ignore
+/// let cache = DocumentCache::new();
+/// let resolver = HotReloadResolver::new("./templates")?.with_document_cache(&cache);
+/// let collection = TypstTemplateCollection::new(fonts)
+///     .with_document_cache(cache)
+///     .add_file_resolver(resolver);
+/// ```
+pub struct HotReloadResolver {
+    resolver: CachedFileResolver<FileSystemResolver>,
+    on_change: Arc<Mutex<Vec<OnFileChange>>>,
+    _watcher: RecommendedWatcher,
+}
+
+impl HotReloadResolver {
+    pub fn new<P>(root: P) -> FileResult<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let root = root.as_ref();
+        let root = root
+            .canonicalize()
+            .map_err(|error| FileError::from_io(error, root))?;
+        let resolver = FileSystemResolver::new(root.clone()).into_cached();
+        let on_change: Arc<Mutex<Vec<OnFileChange>>> = Default::default();
+
+        let sources = resolver.source_cache.clone();
+        let binaries = resolver.binary_cache.clone();
+        let watched_root = root.clone();
+        let callbacks = on_change.clone();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                let Ok(event) = event else {
+                    return;
+                };
+                if matches!(event.kind, EventKind::Access(_)) {
+                    return;
+                }
+                for path in &event.paths {
+                    let Some(vpath) = VirtualPath::within_root(path, &watched_root) else {
+                        continue;
+                    };
+                    let id = FileId::new(None, vpath);
+                    if let Some(sources) = &sources {
+                        sources.invalidate(id);
+                    }
+                    if let Some(binaries) = &binaries {
+                        binaries.invalidate(id);
+                    }
+                }
+                if let Ok(callbacks) = callbacks.lock() {
+                    callbacks
+                        .iter()
+                        .for_each(|on_change| on_change(&event.paths));
+                }
+            })
+            .map_err(watch_error)?;
+        watcher
+            .watch(&root, RecursiveMode::Recursive)
+            .map_err(watch_error)?;
+
+        Ok(Self {
+            resolver,
+            on_change,
+            _watcher: watcher,
+        })
+    }
+
+    /// Call `on_change` with the paths of changed files. The callback runs on the thread
+    /// of the watcher.
+    pub fn on_change<F>(self, on_change: F) -> Self
+    where
+        F: Fn(&[PathBuf]) + Send + Sync + 'static,
+    {
+        if let Ok(mut callbacks) = self.on_change.lock() {
+            callbacks.push(Box::new(on_change));
+        }
+        self
+    }
+
+    /// Clear `document_cache`, when a file changes.
+    pub fn with_document_cache(self, document_cache: &DocumentCache) -> Self {
+        let document_cache = document_cache.clone();
+        self.on_change(move |_| document_cache.clear())
+    }
+}
+
+impl FileResolver for HotReloadResolver {
+    fn resolve_binary(&self, id: FileId) -> FileResult<Cow<'_, Bytes>> {
+        self.resolver.resolve_binary(id)
+    }
+
+    fn resolve_source(&self, id: FileId) -> FileResult<Cow<'_, Source>> {
+        self.resolver.resolve_source(id)
+    }
+
+    fn resolve_path(&self, id: FileId) -> Option<PathBuf> {
+        self.resolver.resolve_path(id)
+    }
+
+    fn invalidate(&self, id: FileId) {
+        self.resolver.invalidate(id)
+    }
+
+    fn clear_cache(&self) {
+        self.resolver.clear_cache()
+    }
+
+    fn stats(&self) -> ResolverStats {
+        self.resolver.stats()
+    }
+
+    fn package_failure(&self, id: FileId) -> Option<PackageFailure> {
+        self.resolver.package_failure(id)
+    }
+}
+
+fn watch_error(error: notify::Error) -> FileError {
+    FileError::Other(Some(eco_format!("Could not watch files: {error}")))
+}
@@ -0,0 +1,106 @@
+use std::{borrow::Cow, io::Read};
+
+use ecow::eco_format;
+use typst::{
+    diag::{FileError, FileResult},
+    foundations::Bytes,
+    syntax::{FileId, Source},
+};
+
+use crate::{
+    cached_file_resolver::{CachedFileResolver, IntoCachedFileResolver},
+    file_resolver::FileResolver,
+    util::{bytes_to_source, not_found},
+};
+
+/// Resolves local files (when `package` is not set in `FileId`) against a base url,
+/// e.g. `/assets/logo.png` with base url `https://cdn.example.com/templates` is downloaded from
+/// `https://cdn.example.com/templates/assets/logo.png` (feature `http`).
+/// Use `into_cached` (or `TypstTemplateCollection::with_http_resolver`) to download each
+/// file only once.
+#[derive(Debug, Clone)]
+pub struct HttpFileResolver {
+    base_url: String,
+    ureq: ureq::Agent,
+}
+
+impl HttpFileResolver {
+    pub fn new<S>(base_url: S) -> Self
+    where
+        S: Into<String>,
+    {
+        let mut base_url = base_url.into();
+        if !base_url.ends_with('/') {
+            base_url.push('/');
+        }
+        Self {
+            base_url,
+            ureq: ureq::Agent::new(),
+        }
+    }
+
+    /// Use a custom agent, e.g. with timeouts or a proxy.
+    pub fn ureq_agent(self, ureq: ureq::Agent) -> Self {
+        Self { ureq, ..self }
+    }
+
+    fn url(&self, id: FileId) -> FileResult<String> {
+        if id.package().is_some() {
+            return Err(not_found(id));
+        }
+        let path = id.vpath().as_rootless_path().to_string_lossy();
+        let mut url = self.base_url.clone();
+        for byte in path.bytes() {
+            match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                    url.push(byte as char)
+                }
+                b'\\' => url.push('/'),
+                byte => url.push_str(&format!("%{byte:02X}")),
+            }
+        }
+        Ok(url)
+    }
+
+    fn resolve_bytes(&self, id: FileId) -> FileResult<Vec<u8>> {
+        let url = self.url(id)?;
+        let response = match self.ureq.get(&url).call() {
+            Ok(response) => response,
+            Err(ureq::Error::Status(404 | 410, _)) => return Err(not_found(id)),
+            Err(error) => {
+                return Err(FileError::Other(Some(eco_format!(
+                    "Could not download {url}: {error}"
+                ))))
+            }
+        };
+        let mut content = Vec::new();
+        response
+            .into_reader()
+            .read_to_end(&mut content)
+            .map_err(|error| {
+                FileError::Other(Some(eco_format!("Could not download {url}: {error}")))
+            })?;
+        Ok(content)
+    }
+}
+
+impl IntoCachedFileResolver for HttpFileResolver {
+    fn into_cached(self) -> CachedFileResolver<Self> {
+        CachedFileResolver::new(self)
+            .with_in_memory_source_cache()
+            .with_in_memory_binary_cache()
+    }
+}
+
+impl FileResolver for HttpFileResolver {
+    fn resolve_binary(&self, id: FileId) -> FileResult<Cow<'_, Bytes>> {
+        let b = self.resolve_bytes(id)?;
+        Ok(Cow::Owned(b.into()))
+    }
+
+    fn resolve_source(&self, id: FileId) -> FileResult<Cow<'_, Source>> {
+        let file = self.resolve_bytes(id)?;
+        let source = bytes_to_source(id, &file)?;
+        Ok(Cow::Owned(source))
+    }
+}
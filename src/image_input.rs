@@ -0,0 +1,50 @@
+//! Images as input values, so templates can show images, that are generated in Rust (e.g.
+//! charts), without a file resolver. Images are passed as `bytes` and decoded in the template
+//! with `image.decode`:
+//!
+//! ```rust,ignore
+//! let mut inputs = Dict::new();
+//! inputs.insert("chart".into(), image_value(png_bytes));
+//! inputs.insert("logo".into(), svg_value(svg_text));
+//! let doc = template.compile_with_input(inputs).output?;
+//! ```
+//!
+//! ```typ
+//! #import sys: inputs
+//! #image.decode(inputs.chart, width: 50%)
+//! #image.decode(inputs.logo, format: "svg")
+//! ```
+//!
+//! PNG, JPEG and GIF are detected automatically, SVGs need `format: "svg"`. With the
+//! `serde` feature, byte slices (e.g. with `serde_bytes`) are converted to `bytes` as well.
+
+use typst::foundations::{Bytes, Value};
+
+/// Encoded PNG, JPEG or GIF data as input value.
+pub fn image_value<B>(data: B) -> Value
+where
+    B: Into<Bytes>,
+{
+    Value::Bytes(data.into())
+}
+
+/// SVG source as input value. Decode it with `image.decode(.., format: "svg")`.
+pub fn svg_value<S>(svg: S) -> Value
+where
+    S: Into<String>,
+{
+    Value::Bytes(Bytes::from(svg.into().into_bytes()))
+}
+
+/// `image` as PNG encoded input value (feature `image`).
+#[cfg(feature = "image")]
+pub fn dynamic_image_value(image: &image::DynamicImage) -> Result<Value, crate::TypstAsLibError> {
+    use ecow::eco_format;
+    use typst::diag::HintedString;
+
+    let mut png = std::io::Cursor::new(Vec::new());
+    image
+        .write_to(&mut png, image::ImageFormat::Png)
+        .map_err(|err| HintedString::from(eco_format!("Could not encode png: {err}")))?;
+    Ok(image_value(png.into_inner()))
+}
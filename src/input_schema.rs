@@ -0,0 +1,190 @@
+use std::fmt;
+
+use ecow::EcoString;
+use typst::foundations::{Dict, Value};
+
+/// Expected type of an input value.
+#[derive(Debug, Clone)]
+pub enum SchemaType {
+    Any,
+    Bool,
+    Int,
+    /// Integers are accepted as well.
+    Float,
+    Str,
+    Bytes,
+    Datetime,
+    /// Strings are accepted as well.
+    Content,
+    Array(Box<SchemaType>),
+    Dict(InputSchema),
+}
+
+impl SchemaType {
+    pub fn array(item: SchemaType) -> Self {
+        SchemaType::Array(Box::new(item))
+    }
+
+    fn matches(&self, value: &Value) -> bool {
+        matches!(
+            (self, value),
+            (SchemaType::Any, _)
+                | (SchemaType::Bool, Value::Bool(_))
+                | (SchemaType::Int, Value::Int(_))
+                | (SchemaType::Float, Value::Float(_) | Value::Int(_))
+                | (SchemaType::Str, Value::Str(_))
+                | (SchemaType::Bytes, Value::Bytes(_))
+                | (SchemaType::Datetime, Value::Datetime(_))
+                | (SchemaType::Content, Value::Content(_) | Value::Str(_))
+                | (SchemaType::Array(_), Value::Array(_))
+                | (SchemaType::Dict(_), Value::Dict(_))
+        )
+    }
+}
+
+impl fmt::Display for SchemaType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaType::Any => write!(f, "any"),
+            SchemaType::Bool => write!(f, "boolean"),
+            SchemaType::Int => write!(f, "integer"),
+            SchemaType::Float => write!(f, "float"),
+            SchemaType::Str => write!(f, "string"),
+            SchemaType::Bytes => write!(f, "bytes"),
+            SchemaType::Datetime => write!(f, "datetime"),
+            SchemaType::Content => write!(f, "content"),
+            SchemaType::Array(item) => write!(f, "array of {item}"),
+            SchemaType::Dict(_) => write!(f, "dictionary"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SchemaField {
+    name: EcoString,
+    ty: SchemaType,
+    required: bool,
+}
+
+/// Declares the fields, that are expected in the inputs of a template.
+/// The inputs are validated before the compile, see `TypstTemplateCollection::with_input_schema`.
+///
+/// Example:
+/// ```rust,ignore
+/// let schema = InputSchema::new()
+///     .required("title", SchemaType::Str)
+///     .required(
+///         "customer",
+///         SchemaType::Dict(InputSchema::new().required("address", SchemaType::Str)),
+///     )
+///     .optional("notes", SchemaType::array(SchemaType::Content));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct InputSchema {
+    fields: Vec<SchemaField>,
+}
+
+impl InputSchema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn required<S>(mut self, name: S, ty: SchemaType) -> Self
+    where
+        S: Into<EcoString>,
+    {
+        self.fields.push(SchemaField {
+            name: name.into(),
+            ty,
+            required: true,
+        });
+        self
+    }
+
+    /// Optional fields may be missing or `none`.
+    pub fn optional<S>(mut self, name: S, ty: SchemaType) -> Self
+    where
+        S: Into<EcoString>,
+    {
+        self.fields.push(SchemaField {
+            name: name.into(),
+            ty,
+            required: false,
+        });
+        self
+    }
+
+    /// Validate `inputs`. Returns all errors, that were found.
+    pub fn validate(&self, inputs: &Dict) -> Result<(), Vec<InputSchemaError>> {
+        let mut errors = Vec::new();
+        self.validate_dict(inputs, "", &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn validate_dict(&self, dict: &Dict, prefix: &str, errors: &mut Vec<InputSchemaError>) {
+        for SchemaField { name, ty, required } in &self.fields {
+            let path = if prefix.is_empty() {
+                name.clone()
+            } else {
+                EcoString::from(format!("{prefix}.{name}"))
+            };
+            match dict.get(name) {
+                Ok(Value::None) | Err(_) if !required => {}
+                Err(_) => errors.push(InputSchemaError::MissingField(path)),
+                Ok(value) => validate_value(ty, value, path, errors),
+            }
+        }
+    }
+}
+
+fn validate_value(
+    ty: &SchemaType,
+    value: &Value,
+    path: EcoString,
+    errors: &mut Vec<InputSchemaError>,
+) {
+    if !ty.matches(value) {
+        errors.push(InputSchemaError::WrongType {
+            path,
+            expected: ty.to_string().into(),
+            found: value.ty().long_name().into(),
+        });
+        return;
+    }
+    match (ty, value) {
+        (SchemaType::Dict(schema), Value::Dict(dict)) => schema.validate_dict(dict, &path, errors),
+        (SchemaType::Array(item), Value::Array(array)) => {
+            for (i, value) in array.iter().enumerate() {
+                validate_value(item, value, EcoString::from(format!("{path}[{i}]")), errors);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputSchemaError {
+    MissingField(EcoString),
+    WrongType {
+        path: EcoString,
+        expected: EcoString,
+        found: EcoString,
+    },
+}
+
+impl fmt::Display for InputSchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputSchemaError::MissingField(path) => write!(f, "missing field '{path}'"),
+            InputSchemaError::WrongType {
+                path,
+                expected,
+                found,
+            } => write!(f, "field '{path}' is {found}, expected {expected}"),
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use std::{
+    collections::{HashMap, HashSet},
+    num::NonZeroUsize,
+};
+
+use typst::{
+    foundations::{Label, Selector},
+    introspection::{Location, Tag},
+    layout::{Frame, FrameItem, Point, Size, Transform},
+    model::Document,
+};
+
+use crate::{
+    document_ext::FrameExt,
+    util::{item_box, transformed_corners},
+};
+
+/// Bounding box of an element on one page. Coordinates are from the top left of the page.
+/// An element, that spans multiple pages, has one `ElementPosition` per page.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ElementPosition {
+    pub location: Location,
+    /// The page, starting at 1.
+    pub page: NonZeroUsize,
+    pub min: Point,
+    pub max: Point,
+}
+
+impl ElementPosition {
+    pub fn size(&self) -> Size {
+        (self.max - self.min).to_size()
+    }
+}
+
+/// Positions of all elements with the label `label` (e.g. `<hotspot>`).
+pub fn label_positions(document: &Document, label: &str) -> Vec<ElementPosition> {
+    element_positions(document, &Selector::Label(Label::new(label)))
+}
+
+/// Positions of all elements matching `selector`, in document order. The bounding boxes
+/// contain everything, that was laid out for the element. Text is approximated by its font size.
+/// Elements without visible content are reported with an empty box at their location.
+pub fn element_positions(document: &Document, selector: &Selector) -> Vec<ElementPosition> {
+    let locations: Vec<Location> = document
+        .introspector
+        .query(selector)
+        .iter()
+        .filter_map(|content| content.location())
+        .collect();
+    let targets: HashSet<Location> = locations.iter().copied().collect();
+
+    let mut boxes: HashMap<Location, Vec<ElementPosition>> = HashMap::new();
+    for (i, page) in document.pages.iter().enumerate() {
+        let page_number = NonZeroUsize::new(i + 1).unwrap();
+        let mut walker = FrameWalker {
+            targets: &targets,
+            open: Vec::new(),
+            boxes: HashMap::new(),
+        };
+        walker.walk(&page.frame);
+        for (location, (min, max)) in walker.boxes {
+            boxes.entry(location).or_default().push(ElementPosition {
+                location,
+                page: page_number,
+                min,
+                max,
+            });
+        }
+    }
+
+    let mut positions = Vec::new();
+    for location in locations {
+        match boxes.remove(&location) {
+            Some(mut found) => {
+                found.sort_by_key(|p| p.page);
+                positions.extend(found);
+            }
+            None => {
+                let position = document.introspector.position(location);
+                positions.push(ElementPosition {
+                    location,
+                    page: position.page,
+                    min: position.point,
+                    max: position.point,
+                });
+            }
+        }
+    }
+    positions
+}
+
+struct FrameWalker<'a> {
+    targets: &'a HashSet<Location>,
+    open: Vec<Location>,
+    boxes: HashMap<Location, (Point, Point)>,
+}
+
+impl FrameWalker<'_> {
+    fn walk(&mut self, frame: &Frame) {
+        for (ts, pos, item) in frame.leaf_items() {
+            match item {
+                FrameItem::Tag(Tag::Start(content)) => {
+                    if let Some(location) = content.location() {
+                        if self.targets.contains(&location) {
+                            self.open.push(location);
+                        }
+                    }
+                }
+                FrameItem::Tag(Tag::End(location, _)) => {
+                    self.open.retain(|open| open != location);
+                }
+                item => {
+                    if let Some((pos, size)) = item_box(pos, item) {
+                        self.extend(pos, size, ts);
+                    }
+                }
+            }
+        }
+    }
+
+    fn extend(&mut self, pos: Point, size: Size, ts: Transform) {
+        if self.open.is_empty() {
+            return;
+        }
+        let corners = transformed_corners(pos, size, ts);
+        for location in &self.open {
+            let (min, max) = self
+                .boxes
+                .entry(*location)
+                .or_insert((corners[0], corners[0]));
+            for corner in corners {
+                *min = min.min(corner);
+                *max = max.max(corner);
+            }
+        }
+    }
+}
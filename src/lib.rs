@@ -1,47 +1,198 @@
 use std::borrow::Cow;
-use std::ops::Deref;
+use std::collections::{HashMap, HashSet};
+use std::ops::{Deref, Range};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use cached_file_resolver::IntoCachedFileResolver;
-use chrono::{DateTime, Datelike, Duration, Utc};
-use ecow::EcoVec;
+use cancellation::CancellationToken;
+use chrono::{DateTime, Datelike, NaiveDateTime, Timelike, Utc};
+use comemo::Track;
+use compile_options::CompileOptions;
+use compile_output::{CompileOutput, CompileStats};
+use compile_session::CompileSession;
+use depfile::Depfile;
+use diagnostics::Diagnostic;
+use document_cache::DocumentCache;
+use document_target::DocumentTarget;
+use ecow::{eco_format, EcoString, EcoVec};
 use file_resolver::{
-    FileResolver, FileSystemResolver, MainSourceFileResolver, StaticFileResolver,
+    FileResolver, FileSystemResolver, LocalPackagesResolver, MainSourceFileResolver,
+    MutableSourceFileResolver, OverlayResolver, PackageFailure, ResolverErrors, StaticFileResolver,
     StaticSourceFileResolver,
 };
+use font_set::{ExtendedBook, FontSet};
+use font_slot::FontSlot;
+use fragment::{concat_documents, Fragment, PAGE_OFFSET_KEY};
+use input_schema::{InputSchema, InputSchemaError};
+use metrics::{CompileCounter, Metrics, ResolverMetrics};
+use preflight::MissingFile;
+use redaction::{redact_diagnostics, SensitiveInputs};
+use resolver_chain::{BoxedFileResolver, ResolverChain, ResolverHandle, DEFAULT_RESOLVER_PRIORITY};
+use shared_registry::SharedRegistry;
+use style_defaults::StyleDefaults;
+use template_registry::{registered_template_file_id, TemplateRegistry};
+use template_store::{template_file_id, TemplateInfo, TemplateStore, TemplateStoreResolver};
 use thiserror::Error;
-use typst::diag::{FileError, FileResult, HintedString, SourceDiagnostic, Warned};
-use typst::foundations::{Bytes, Datetime, Dict, Module, Scope, Value};
+use typst::diag::{FileError, FileResult, HintedString, SourceDiagnostic, SourceResult, Warned};
+use typst::eval::{eval_string, EvalMode};
+use typst::foundations::{
+    Args, Bytes, Datetime, Dict, IntoValue, LocatableSelector, Module, Scope, Value,
+};
+use typst::introspection::MetadataElem;
 use typst::model::Document;
-use typst::syntax::{package::PackageSpec, FileId, Source, VirtualPath};
-use typst::text::{Font, FontBook};
+use typst::syntax::{package::PackageSpec, FileId, Source, Span, VirtualPath};
+use typst::text::{Font, FontBook, FontInfo};
 use typst::utils::LazyHash;
 use typst::Library;
-use util::not_found;
+use util::{bytes_to_source, csv_bytes, merge_dicts, short_type_name};
+use virtual_packages::VirtualPackages;
+use warnings::WarningPolicy;
 
 pub mod cached_file_resolver;
+pub mod cancellation;
+pub mod chunked_data;
+pub mod compile_options;
+pub mod compile_output;
+pub mod compile_session;
+pub mod date_helpers;
+mod depfile;
+pub mod diagnostics;
+pub mod diff;
+pub mod document_cache;
+pub mod document_ext;
+pub mod document_target;
 pub mod file_resolver;
+pub mod font_report;
+pub mod font_set;
+pub mod font_slot;
+pub mod fragment;
+pub mod image_input;
+pub mod input_schema;
+pub mod introspection;
+mod macros;
+pub mod metrics;
+pub mod native_functions;
+pub mod outline;
+pub mod page_geometry;
+pub mod prefix_map_resolver;
+pub mod preflight;
+mod redaction;
+pub mod resolver_chain;
+pub mod shared_registry;
+pub mod style_defaults;
+pub mod template_registry;
+pub mod template_store;
+pub mod text_extraction;
 pub(crate) mod util;
+pub mod virtual_packages;
+pub mod warnings;
+
+#[cfg(any(feature = "tar", feature = "zip"))]
+pub mod archive_resolver;
+
+#[cfg(feature = "async")]
+pub mod async_file_resolver;
+
+#[cfg(feature = "diag")]
+pub mod diag;
+
+#[cfg(feature = "hot-reload")]
+pub mod hot_reload;
+
+#[cfg(feature = "http")]
+pub mod http_resolver;
+
+#[cfg(feature = "packages")]
+pub mod package_lock;
 
 #[cfg(feature = "packages")]
 pub mod package_resolver;
 
+#[cfg(feature = "pdf")]
+pub mod pdf;
+
+#[cfg(feature = "render")]
+pub mod render;
+
+#[cfg(feature = "serde")]
+pub mod serialize;
+
+#[cfg(feature = "sqlite")]
+pub mod sqlite_cache;
+
+#[cfg(feature = "system-fonts")]
+pub mod system_fonts;
+
+#[cfg(feature = "timings")]
+pub mod timings;
+
+#[cfg(feature = "embed")]
+pub use typst_as_lib_macros::embed_templates;
+
+#[cfg(feature = "image")]
+pub use image;
+
+#[cfg(feature = "timezone")]
+pub use chrono_tz;
+
+/// The typst version, that this crate is built against.
+pub use typst;
+
 // Inspired by https://github.com/tfachmann/typst-as-library/blob/main/src/lib.rs
 
+static NEXT_COLLECTION_ID: AtomicU64 = AtomicU64::new(0);
+
 pub struct TypstTemplateCollection {
     book: LazyHash<FontBook>,
-    fonts: Vec<Font>,
+    fonts: Vec<FontSlot>,
+    font_max_idle: Option<Duration>,
     inject_location: Option<InjectLocation>,
-    file_resolvers: Vec<Box<dyn FileResolver + Send + Sync + 'static>>,
+    file_resolvers: ResolverChain,
     library: LazyHash<Library>,
     comemo_evict_max_age: Option<usize>,
+    document_cache: Option<DocumentCache>,
+    /// Watched, so changed templates are removed from the `document_cache`.
+    template_stores: Vec<Arc<dyn TemplateStore>>,
+    depfile: Option<Depfile>,
+    input_schema: Option<InputSchema>,
+    default_inputs: Option<Dict>,
+    sensitive_inputs: SensitiveInputs,
+    in_memory_only: bool,
+    /// The first resolver, font or depfile, that was refused, because it is not in memory.
+    in_memory_violation: Option<EcoString>,
+    virtual_packages: Option<VirtualPackages>,
+    template_registry: Option<TemplateRegistry>,
+    mutable_sources: Option<MutableSourceFileResolver>,
+    overlays: Vec<OverlayResolver>,
+    data_files: Option<OverlayResolver>,
+    /// Their fonts are read at each compile, see `with_shared_registry`.
+    shared_registries: Vec<SharedRegistry>,
+    /// Book with the fonts of the `shared_registries`.
+    registry_book: ExtendedBook,
+    fixed_now: Option<DateTime<Utc>>,
+    #[cfg(feature = "timezone")]
+    timezone: Option<chrono_tz::Tz>,
+    date_helpers: bool,
+    inject_now: Option<String>,
+    timeout: Option<Duration>,
+    access_policy: Option<AccessPolicy>,
+    warning_policy: Option<WarningPolicy>,
+    compile_counter: CompileCounter,
+    /// Unique per collection, so collections, that share a `DocumentCache`, don't get the
+    /// documents of each other.
+    id: u64,
 }
 
+type AccessPolicy = Box<dyn Fn(FileId) -> bool + Send + Sync + 'static>;
+
 impl TypstTemplateCollection {
     /// Initialize with fonts.
     ///
     /// Example:
-    /// ```rust
+    /// ```rust,ignore
     /// static TEMPLATE: &str = include_str!("./templates/template.typ");
     /// static FONT: &[u8] = include_bytes!("./fonts/texgyrecursor-regular.otf");
     /// // ...
@@ -50,19 +201,70 @@ impl TypstTemplateCollection {
     /// let template = TypstTemplate::new(vec![font])
     ///     .with_static_file_resolver([TEMPLATE], []);
     /// ```
+    ///
+    /// With the feature `embed-fonts`, the default fonts of the typst cli are added after `fonts`.
     pub fn new<V>(fonts: V) -> Self
     where
         V: Into<Vec<Font>>,
     {
-        let fonts = fonts.into();
-        Self {
+        let fonts: Vec<Font> = fonts.into();
+        #[allow(unused_mut)]
+        let mut collection = Self {
             book: LazyHash::new(FontBook::from_fonts(&fonts)),
-            fonts,
+            fonts: fonts.into_iter().map(Into::into).collect(),
+            font_max_idle: None,
             inject_location: Default::default(),
             file_resolvers: Default::default(),
             library: Default::default(),
             comemo_evict_max_age: Some(0),
-        }
+            document_cache: None,
+            template_stores: Vec::new(),
+            depfile: None,
+            input_schema: None,
+            default_inputs: None,
+            sensitive_inputs: Default::default(),
+            in_memory_only: false,
+            in_memory_violation: None,
+            virtual_packages: None,
+            template_registry: None,
+            mutable_sources: None,
+            overlays: Vec::new(),
+            data_files: None,
+            shared_registries: Vec::new(),
+            registry_book: Default::default(),
+            fixed_now: None,
+            #[cfg(feature = "timezone")]
+            timezone: None,
+            date_helpers: false,
+            inject_now: None,
+            timeout: None,
+            access_policy: None,
+            warning_policy: None,
+            compile_counter: Default::default(),
+            id: NEXT_COLLECTION_ID.fetch_add(1, Ordering::Relaxed),
+        };
+        #[cfg(feature = "embed-fonts")]
+        collection.add_font_slots_mut(font_slot::embedded_fonts());
+        collection
+    }
+
+    /// Setup, that behaves like `typst compile` in the current directory: local files are
+    /// resolved relative to the current directory, packages (feature `packages`) are
+    /// downloaded into the default cache directory and the fonts of the system
+    /// (feature `system-fonts`) are available. Add other fonts with `add_fonts`.
+    pub fn with_defaults() -> Self {
+        let mut collection = Self::new(Vec::new());
+        collection.add_defaults_mut();
+        collection
+    }
+
+    fn add_defaults_mut(&mut self) {
+        let current_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        self.with_file_system_resolver_mut(current_dir);
+        #[cfg(feature = "packages")]
+        self.with_package_file_resolver_mut(None);
+        #[cfg(feature = "system-fonts")]
+        self.with_system_fonts_mut();
     }
 
     /// Use other typst location for injected inputs
@@ -87,558 +289,3721 @@ impl TypstTemplateCollection {
             module_name: module_name.into(),
             value_name: value_name.into(),
         });
+        self.clear_document_cache();
     }
 
-    /// Add Fonts
-    pub fn add_fonts<I, F>(mut self, fonts: I) -> Self
+    /// Inject `value` as `value_name` into the module `module_name` for all compiles, e.g.
+    /// deployment settings as `#import env: config` or branding as `#import theme: colors`,
+    /// next to the inputs of each compile in `#import sys: inputs`. Other values of the
+    /// module are kept, so several values can be injected into the same module.
+    ///
+    /// ```rust,ignore
+    /// let collection = collection
+    ///     .with_injected_module("env", "config", dict! { "stage" => "production" })?
+    ///     .with_injected_module("theme", "colors", dict! { "primary" => "#0050a0" })?;
+    /// ```
+    pub fn with_injected_module<M, N, V>(
+        mut self,
+        module_name: M,
+        value_name: N,
+        value: V,
+    ) -> Result<Self, TypstAsLibError>
     where
-        I: IntoIterator<Item = F>,
-        F: Into<Font>,
+        M: AsRef<str>,
+        N: AsRef<str>,
+        V: IntoValue,
     {
-        self.add_fonts_mut(fonts);
-        self
+        self.with_injected_module_mut(module_name, value_name, value)?;
+        Ok(self)
     }
 
-    /// Add Fonts
-    pub fn add_fonts_mut<I, F>(&mut self, fonts: I) -> &mut Self
+    /// Inject `value` as `value_name` into the module `module_name` for all compiles.
+    /// See `with_injected_module`.
+    pub fn with_injected_module_mut<M, N, V>(
+        &mut self,
+        module_name: M,
+        value_name: N,
+        value: V,
+    ) -> Result<(), TypstAsLibError>
     where
-        I: IntoIterator<Item = F>,
-        F: Into<Font>,
+        M: AsRef<str>,
+        N: AsRef<str>,
+        V: IntoValue,
     {
-        let fonts = fonts.into_iter().map(Into::into);
-        self.fonts.extend(fonts);
+        define_in_module(
+            &mut self.library,
+            module_name.as_ref(),
+            value_name.as_ref(),
+            value,
+        )?;
+        self.clear_document_cache();
+        Ok(())
+    }
+
+    /// Use `library` instead of `Library::default()`, e.g. one built with `Library::builder()`.
+    /// Replaces everything, that was added to the library before (e.g. `with_global`,
+    /// `with_function`), so call it first.
+    pub fn with_library(mut self, library: Library) -> Self {
+        self.with_library_mut(library);
         self
     }
 
-    /// Add file resolver, that implements the `FileResolver`` trait to a vec of file resolvers.
-    /// When a `FileId`` needs to be resolved by Typst, the vec will be iterated over until
-    /// one file resolver returns a file.
-    pub fn add_file_resolver<F>(mut self, file_resolver: F) -> Self
+    /// Use `library` instead of `Library::default()`. See `with_library`.
+    pub fn with_library_mut(&mut self, library: Library) {
+        self.library = LazyHash::new(library);
+        self.clear_document_cache();
+    }
+
+    /// Change the typst standard library, that is used for all compiles, e.g. to pre-define
+    /// styles or to remove definitions.
+    ///
+    /// ```rust,ignore
+    /// let collection = collection.modify_library(|library| {
+    ///     library.styles.set(TextElem::set_lang(Lang::GERMAN));
+    /// });
+    /// ```
+    pub fn modify_library<F>(mut self, f: F) -> Self
     where
-        F: FileResolver + Send + Sync + 'static,
+        F: FnOnce(&mut Library),
     {
-        self.add_file_resolver_mut(file_resolver);
+        self.modify_library_mut(f);
         self
     }
 
-    /// Add file resolver, that implements the `FileResolver`` trait to a vec of file resolvers.
-    /// When a `FileId`` needs to be resolved by Typst, the vec will be iterated over until
-    /// one file resolver returns a file.
-    pub fn add_file_resolver_mut<F>(&mut self, file_resolver: F)
+    /// Change the typst standard library, that is used for all compiles.
+    /// See `modify_library`.
+    pub fn modify_library_mut<F>(&mut self, f: F)
     where
-        F: FileResolver + Send + Sync + 'static,
+        F: FnOnce(&mut Library),
     {
-        self.file_resolvers.push(Box::new(file_resolver));
+        f(&mut self.library);
+        self.clear_document_cache();
     }
 
-    /// Adds the `StaticSourceFileResolver` to the file resolvers. It creates `HashMap`s for sources.
-    ///
-    /// `sources` The item of the IntoIterator can be of types:
-    ///   - `&str/String`, creating a detached Source (Has vpath `/main.typ`)
-    ///   - `(&str, &str/String)`, where &str is the absolute
-    ///     virtual path of the Source file.
-    ///   - `(typst::syntax::FileId, &str/String)`
-    ///   - `typst::syntax::Source`
+    /// Default page size, margins and text styles for all documents, e.g. for branding.
+    /// Set rules of the templates still win. See `StyleDefaults`.
+    pub fn with_style_defaults(mut self, defaults: StyleDefaults) -> Self {
+        self.with_style_defaults_mut(defaults);
+        self
+    }
+
+    /// Default page size, margins and text styles for all documents.
+    /// See `with_style_defaults`.
+    pub fn with_style_defaults_mut(&mut self, defaults: StyleDefaults) {
+        self.modify_library_mut(|library| defaults.apply(&mut library.styles));
+    }
+
+    /// Define `value` as `name` in the global scope, so templates can use `#name` without an
+    /// import, e.g. theming values, that are referenced everywhere. Replaces an existing
+    /// binding with the same name (including the ones of the standard library).
     ///
-    /// (`&str/String` is always the template file content)
-    pub fn with_static_source_file_resolver<IS, S>(mut self, sources: IS) -> Self
+    /// ```rust,ignore
+    /// let collection = collection.with_global("company_logo", "/assets/logo.svg")?;
+    /// // In the template: #image(company_logo)
+    /// ```
+    pub fn with_global<N, V>(mut self, name: N, value: V) -> Result<Self, TypstAsLibError>
     where
-        IS: IntoIterator<Item = S>,
-        S: Into<SourceNewType>,
+        N: AsRef<str>,
+        V: IntoValue,
     {
-        self.with_static_source_file_resolver_mut(sources);
-        self
+        self.with_global_mut(name, value)?;
+        Ok(self)
     }
 
-    /// Adds the `StaticSourceFileResolver` to the file resolvers. It creates `HashMap`s for sources.
-    pub fn with_static_source_file_resolver_mut<IS, S>(&mut self, sources: IS)
+    /// Define `value` as `name` in the global scope. See `with_global`.
+    pub fn with_global_mut<N, V>(&mut self, name: N, value: V) -> Result<(), TypstAsLibError>
     where
-        IS: IntoIterator<Item = S>,
-        S: Into<SourceNewType>,
+        N: AsRef<str>,
+        V: IntoValue,
     {
-        self.add_file_resolver_mut(StaticSourceFileResolver::new(sources));
+        define_in_scope(self.library.global.scope_mut(), name.as_ref(), value)?;
+        self.clear_document_cache();
+        Ok(())
     }
 
-    /// Adds the `StaticFileResolver` to the file resolvers. It creates `HashMap`s for binaries.
-    pub fn with_static_file_resolver<IB, F, B>(mut self, binaries: IB) -> Self
+    /// Validate the inputs of `compile_with_input` against `schema` before compiling.
+    /// Returns `TypstAsLibError::InvalidInput` with all errors, if the inputs do not match.
+    /// Compiles without inputs (`compile`) are validated as empty inputs.
+    pub fn with_input_schema(mut self, schema: InputSchema) -> Self {
+        self.with_input_schema_mut(schema);
+        self
+    }
+
+    /// Validate the inputs of `compile_with_input` against `schema` before compiling.
+    /// Returns `TypstAsLibError::InvalidInput` with all errors, if the inputs do not match.
+    pub fn with_input_schema_mut(&mut self, schema: InputSchema) {
+        self.input_schema = Some(schema);
+    }
+
+    /// Default values for the inputs (e.g. branding colors, company address), that are deep
+    /// merged with the inputs of each compile. Values of the compile inputs take precedence.
+    pub fn with_default_inputs<D>(mut self, defaults: D) -> Self
     where
-        IB: IntoIterator<Item = (F, B)>,
-        F: Into<FileIdNewType>,
-        B: Into<Bytes>,
+        D: Into<Dict>,
     {
-        self.with_static_file_resolver_mut(binaries);
+        self.with_default_inputs_mut(defaults);
         self
     }
 
-    /// Adds the `StaticFileResolver` to the file resolvers. It creates `HashMap`s for binaries.
-    pub fn with_static_file_resolver_mut<IB, F, B>(&mut self, binaries: IB)
+    /// Default values for the inputs (e.g. branding colors, company address), that are deep
+    /// merged with the inputs of each compile. Values of the compile inputs take precedence.
+    pub fn with_default_inputs_mut<D>(&mut self, defaults: D)
     where
-        IB: IntoIterator<Item = (F, B)>,
-        F: Into<FileIdNewType>,
-        B: Into<Bytes>,
+        D: Into<Dict>,
     {
-        self.add_file_resolver_mut(StaticFileResolver::new(binaries));
+        self.default_inputs = Some(defaults.into());
     }
 
-    /// Adds `FileSystemResolver` to the file resolvers, a resolver that can resolve
-    /// local files (when `package` is not set in `FileId`).
-    pub fn with_file_system_resolver<P>(mut self, root: P) -> Self
+    /// Mark input keys as sensitive. Their values are replaced with `[redacted]` in the
+    /// diagnostics and errors of each compile. Nested keys are written as dotted paths
+    /// (`customer.iban`). If the value is a dictionary or array, everything inside is redacted.
+    /// Booleans, `none`, `auto` and values with less than 3 characters are not redacted,
+    /// because they would also replace unrelated text.
+    pub fn with_sensitive_inputs<I, S>(mut self, keys: I) -> Self
     where
-        P: Into<PathBuf>,
+        I: IntoIterator<Item = S>,
+        S: Into<EcoString>,
     {
-        self.with_file_system_resolver_mut(root);
+        self.with_sensitive_inputs_mut(keys);
         self
     }
 
-    /// Adds `FileSystemResolver` to the file resolvers, a resolver that can resolve
-    /// local files (when `package` is not set in `FileId`).
-    pub fn with_file_system_resolver_mut<P>(&mut self, root: P)
+    /// Mark input keys as sensitive. Their values are replaced with `[redacted]` in the
+    /// diagnostics and errors of each compile. Nested keys are written as dotted paths
+    /// (`customer.iban`). If the value is a dictionary or array, everything inside is redacted.
+    /// Booleans, `none`, `auto` and values with less than 3 characters are not redacted,
+    /// because they would also replace unrelated text.
+    pub fn with_sensitive_inputs_mut<I, S>(&mut self, keys: I)
     where
-        P: Into<PathBuf>,
+        I: IntoIterator<Item = S>,
+        S: Into<EcoString>,
     {
-        self.add_file_resolver_mut(FileSystemResolver::new(root.into()).into_cached());
+        self.sensitive_inputs.extend(keys);
     }
 
-    pub fn comemo_evict_max_age(&mut self, comemo_evict_max_age: Option<usize>) -> &mut Self {
-        self.comemo_evict_max_age = comemo_evict_max_age;
+    /// Add Fonts
+    pub fn add_fonts<I, F>(mut self, fonts: I) -> Self
+    where
+        I: IntoIterator<Item = F>,
+        F: Into<Font>,
+    {
+        self.add_fonts_mut(fonts);
         self
     }
 
-    #[cfg(feature = "packages")]
-    /// Adds `PackageResolver` to the file resolvers.
-    /// When `package` is set in `FileId`, it will download the package from the typst package
-    /// repository. It caches the results into `cache` (which is either in memory or cache folder (default)).
-    /// Example
-    /// ```rust
-    ///     let template = TypstTemplateCollection::new(vec![font])
-    ///         .with_package_file_resolver(None);
-    /// ```
-    pub fn with_package_file_resolver(mut self, ureq: Option<ureq::Agent>) -> Self {
-        self.with_package_file_resolver_mut(ureq);
+    /// Add Fonts
+    pub fn add_fonts_mut<I, F>(&mut self, fonts: I) -> &mut Self
+    where
+        I: IntoIterator<Item = F>,
+        F: Into<Font>,
+    {
+        let fonts = fonts.into_iter().map(|f| FontSlot::from(f.into()));
+        self.add_font_slots_mut(fonts);
         self
     }
 
-    #[cfg(feature = "packages")]
-    pub fn with_package_file_resolver_mut(&mut self, ureq: Option<ureq::Agent>) {
-        use package_resolver::PackageResolverBuilder;
-        let mut builder = PackageResolverBuilder::new().with_file_system_cache();
-        if let Some(ureq) = ureq {
-            builder = builder.ureq_agent(ureq);
+    /// Remove the fonts, for which `predicate` returns `true`, e.g. to swap the fonts of a
+    /// brand at runtime. Returns the number of removed fonts.
+    ///
+    /// ```rust,ignore
+    /// collection.remove_fonts(|info| info.family == "Old Brand Sans");
+    /// collection.add_fonts_mut(new_brand_fonts);
+    /// ```
+    pub fn remove_fonts<P>(&mut self, mut predicate: P) -> usize
+    where
+        P: FnMut(&FontInfo) -> bool,
+    {
+        let len = self.fonts.len();
+        self.fonts.retain(|slot| !predicate(slot.info()));
+        let removed = len - self.fonts.len();
+        if removed > 0 {
+            self.rebuild_font_book();
         }
-        self.add_file_resolver_mut(builder.build().into_cached());
+        removed
     }
 
-    /// Call `typst::compile()` with our template and a `Dict` as input, that will be availible
-    /// in a typst script with `#import sys: inputs`.
-    ///
-    /// Example:
-    ///
-    /// ```rust
-    /// static TEMPLATE: &str = include_str!("./templates/template.typ");
-    /// static FONT: &[u8] = include_bytes!("./fonts/texgyrecursor-regular.otf");
-    /// static TEMPLATE_ID: &str = "/template.typ";
-    /// // ...
-    /// let font = Font::new(Bytes::from(FONT), 0).expect("Could not parse font!");
-    /// let template_collection = TypstTemplateCollection::new(vec![font])
-    ///     .add_static_file_resolver([(TEMPLATE_ID, TEMPLATE)]);
-    /// // Struct that implements Into<Dict>.
-    /// let inputs = todo!();
-    /// let tracer = Default::default();
-    /// let doc = template_collection.compile_with_input(&mut tracer, TEMPLATE_ID, inputs)
-    ///     .expect("Typst error!");
-    /// ```
-    pub fn compile_with_input<F, D>(
-        &self,
-        main_source_id: F,
-        input: D,
-    ) -> Warned<Result<Document, TypstAsLibError>>
+    /// Remove all fonts (including the embedded fonts of feature `embed-fonts`).
+    pub fn clear_fonts(&mut self) {
+        self.fonts.clear();
+        self.rebuild_font_book();
+    }
+
+    /// Build the `FontBook` again from the fonts of the collection. `add_fonts`,
+    /// `remove_fonts` etc. already keep the book up to date.
+    pub fn rebuild_font_book(&mut self) {
+        let mut book = FontBook::new();
+        for slot in &self.fonts {
+            book.push(slot.info().clone());
+        }
+        self.book = LazyHash::new(book);
+        self.clear_document_cache();
+    }
+
+    /// Add fonts, that are only parsed, when they are used
+    /// (e.g. `FontSlot::from_path` or `FontSlot::from_bytes`).
+    pub fn add_font_slots<I>(mut self, slots: I) -> Self
     where
-        F: Into<FileIdNewType>,
-        D: Into<Dict>,
+        I: IntoIterator<Item = FontSlot>,
     {
-        self.compile_helper(main_source_id, Some(input))
+        self.add_font_slots_mut(slots);
+        self
     }
 
-    /// Call `typst::compile()` with our template and a `Dict` as input, that will be availible
-    /// in a typst script with `#import sys: inputs`. Mutates the library each call.
-    ///
-    /// Example:
-    ///
-    /// ```rust
-    /// static TEMPLATE: &str = include_str!("./templates/template.typ");
-    /// static FONT: &[u8] = include_bytes!("./fonts/texgyrecursor-regular.otf");
-    /// static TEMPLATE_ID: &str = "/template.typ";
-    /// // ...
-    /// let font = Font::new(Bytes::from(FONT), 0).expect("Could not parse font!");
-    /// let template_collection = TypstTemplateCollection::new(vec![font])
-    ///     .add_static_file_resolver([(TEMPLATE_ID, TEMPLATE)]);
-    /// // Struct that implements Into<Dict>.
-    /// let inputs = todo!();
-    /// let tracer = Default::default();
-    /// let doc = template_collection.compile_with_input_fast(&mut tracer, TEMPLATE_ID, inputs)
-    ///     .expect("Typst error!");
-    /// ```
-    #[deprecated(
-        since = "0.11.1",
-        note = "Use TypstTemplate::compile_with_input() instead!"
-    )]
-    pub fn compile_with_input_fast<F, D>(
-        &mut self,
-        main_source_id: F,
-        input: D,
-    ) -> Warned<Result<Document, TypstAsLibError>>
+    /// Add fonts, that are only parsed, when they are used
+    /// (e.g. `FontSlot::from_path` or `FontSlot::from_bytes`).
+    pub fn add_font_slots_mut<I>(&mut self, slots: I)
     where
-        F: Into<FileIdNewType>,
-        D: Into<Dict>,
+        I: IntoIterator<Item = FontSlot>,
     {
-        let Self {
-            library,
-            inject_location,
-            ..
-        } = self;
-        let res = inject_input_into_library(library, inject_location.as_ref(), input);
-        match res {
-            Ok(_) => (),
-            Err(err) => {
-                return Warned {
-                    output: Err(err),
-                    warnings: Default::default(),
-                }
+        for slot in slots {
+            if !slot.is_in_memory()
+                && self.refuse_not_in_memory(|| eco_format!("Font {}", slot.info().family))
+            {
+                continue;
             }
+            self.book.push(slot.info().clone());
+            self.fonts.push(slot);
         }
-        let collection = &*self;
+        self.clear_document_cache();
+    }
 
-        let FileIdNewType(main_source_id) = main_source_id.into();
-        let world = TypstWorld {
-            collection,
-            main_source_id,
-            library: Cow::Borrowed(&collection.library),
-            now: Utc::now(),
-        };
-        let Warned { output, warnings } = typst::compile(&world);
+    /// Add all fonts in the directory `path` and its subdirectories, including all faces of
+    /// font collections. Fonts, that are already part of the collection, are skipped.
+    /// They are only parsed, when they are used. See `FontSlot::all_from_dir`.
+    pub fn with_fonts_dir<P>(mut self, path: P) -> Result<Self, TypstAsLibError>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        self.with_fonts_dir_mut(path)?;
+        Ok(self)
+    }
 
-        Warned {
-            output: output.map_err(Into::into),
-            warnings,
+    /// Add all fonts in the directory `path` and its subdirectories. See `with_fonts_dir`.
+    pub fn with_fonts_dir_mut<P>(&mut self, path: P) -> Result<(), TypstAsLibError>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let path = path.as_ref();
+        let name = || eco_format!("Fonts dir {}", path.display());
+        if self.refuse_not_in_memory(name) {
+            return Err(TypstAsLibError::NotInMemory(name()));
         }
+        let known: HashSet<_> = self.fonts.iter().map(|slot| slot.info().clone()).collect();
+        let slots = FontSlot::all_from_dir(path)?
+            .into_iter()
+            .filter(|slot| !known.contains(slot.info()));
+        self.add_font_slots_mut(slots);
+        Ok(())
     }
 
-    /// Just call `typst::compile()`
-    pub fn compile<F>(&self, main_source_id: F) -> Warned<Result<Document, TypstAsLibError>>
+    /// Add the fonts, that are installed on the system (feature `system-fonts`).
+    /// They are only parsed, when they are used.
+    #[cfg(feature = "system-fonts")]
+    pub fn with_system_fonts(mut self) -> Self {
+        self.with_system_fonts_mut();
+        self
+    }
+
+    /// Add the fonts, that are installed on the system (feature `system-fonts`).
+    /// They are only parsed, when they are used.
+    #[cfg(feature = "system-fonts")]
+    pub fn with_system_fonts_mut(&mut self) {
+        if self.refuse_not_in_memory(|| "System fonts".into()) {
+            return;
+        }
+        self.add_font_slots_mut(system_fonts::system_fonts());
+    }
+
+    /// After each compile, drop the parsed data of fonts, that were not used for `max_idle`.
+    /// Fonts, that were added as `Font`, are never dropped.
+    pub fn with_font_eviction(mut self, max_idle: Duration) -> Self {
+        self.with_font_eviction_mut(max_idle);
+        self
+    }
+
+    /// After each compile, drop the parsed data of fonts, that were not used for `max_idle`.
+    /// Fonts, that were added as `Font`, are never dropped.
+    pub fn with_font_eviction_mut(&mut self, max_idle: Duration) {
+        self.font_max_idle = Some(max_idle);
+    }
+
+    /// Drop the parsed data of fonts, that were not used for `max_idle`.
+    /// Returns the number of dropped fonts.
+    pub fn evict_idle_fonts(&self, max_idle: Duration) -> usize {
+        self.fonts
+            .iter()
+            .filter(|slot| slot.evict_if_idle(max_idle))
+            .count()
+    }
+
+    /// Remove the file `id` from the caches of all file resolvers (see
+    /// `FileResolver::invalidate`), so it is resolved again in the next compile. Documents,
+    /// that depend on `id`, are removed from the `DocumentCache`.
+    pub fn invalidate_file<F>(&self, id: F)
     where
         F: Into<FileIdNewType>,
     {
-        self.compile_helper::<_, Dict>(main_source_id, None)
+        let FileIdNewType(id) = id.into();
+        self.file_resolvers
+            .iter()
+            .for_each(|file_resolver| file_resolver.invalidate(id));
+        self.invalidate_documents(id);
     }
 
-    fn compile_helper<F, D>(
+    /// Check, without compiling, that all files and packages, that `main_source_id` imports,
+    /// includes or reads (e.g. with `image("logo.png")`), can be resolved. The imported
+    /// sources are checked recursively. Only references with string literals are found.
+    /// Returns all referenced files or the missing ones.
+    ///
+    /// ```rust,ignore
+    /// if let Err(missing) = collection.check_resolvable("/invoice.typ") {
+    ///     for file in missing {
+    ///         log::error!("{} (in {:?}): {}", file.path, file.referenced_from, file.errors);
+    ///     }
+    /// }
+    /// ```
+    pub fn check_resolvable<F>(&self, main_source_id: F) -> Result<Vec<FileId>, Vec<MissingFile>>
+    where
+        F: Into<FileIdNewType>,
+    {
+        let FileIdNewType(main_source_id) = main_source_id.into();
+        preflight::check_resolvable(self, main_source_id)
+    }
+
+    /// Parse `main_source_id` and the sources it imports or includes, and return the syntax
+    /// errors as `TypstAsLibError::TypstSource`, without evaluating or laying out anything,
+    /// e.g. to reject broken templates on upload. Errors, that only occur during the
+    /// evaluation (unknown variables, wrong arguments, ...), are not found.
+    pub fn validate<F>(&self, main_source_id: F) -> Result<(), TypstAsLibError>
+    where
+        F: Into<FileIdNewType>,
+    {
+        let FileIdNewType(main_source_id) = main_source_id.into();
+        preflight::validate(self, main_source_id)
+    }
+
+    /// Remove all files from the caches of all file resolvers.
+    pub fn clear_file_caches(&self) {
+        self.file_resolvers
+            .iter()
+            .for_each(|file_resolver| file_resolver.clear_cache());
+    }
+
+    /// Cache statistics of each file resolver, the number of package downloads, the number of
+    /// compiles and the duration of the last compile.
+    pub fn metrics(&self) -> Metrics {
+        let resolvers = self
+            .file_resolvers
+            .iter()
+            .map(|file_resolver| ResolverMetrics {
+                name: short_type_name(file_resolver.name()),
+                stats: file_resolver.stats(),
+            })
+            .collect();
+        Metrics {
+            resolvers,
+            compiles: self.compile_counter.compiles(),
+            last_compile_duration: self.compile_counter.last_duration(),
+        }
+    }
+
+    /// Add file resolver, that implements the `FileResolver`` trait to a vec of file resolvers.
+    /// When a `FileId`` needs to be resolved by Typst, the vec will be iterated over until
+    /// one file resolver returns a file.
+    pub fn add_file_resolver<F>(mut self, file_resolver: F) -> Self
+    where
+        F: FileResolver + Send + Sync + 'static,
+    {
+        self.add_file_resolver_mut(file_resolver);
+        self
+    }
+
+    /// Add file resolver, that implements the `FileResolver`` trait to a vec of file resolvers.
+    /// When a `FileId`` needs to be resolved by Typst, the vec will be iterated over until
+    /// one file resolver returns a file.
+    pub fn add_file_resolver_mut<F>(&mut self, file_resolver: F)
+    where
+        F: FileResolver + Send + Sync + 'static,
+    {
+        self.register_file_resolver(file_resolver, DEFAULT_RESOLVER_PRIORITY);
+    }
+
+    /// Add a file resolver with `priority` and return a handle, that can be used to remove or
+    /// replace it later, e.g. to swap a static resolver for a file system resolver at
+    /// runtime. Resolvers with a higher priority are resolved first, resolvers with the same
+    /// priority in the order they were added. `add_file_resolver` uses
+    /// `DEFAULT_RESOLVER_PRIORITY` (`0`). Changing the resolvers clears the `DocumentCache`.
+    ///
+    /// ```rust,ignore
+    /// let handle = collection.register_file_resolver(StaticSourceFileResolver::new(sources), 10);
+    /// // ...
+    /// collection.replace_file_resolver(handle, FileSystemResolver::new(root));
+    /// ```
+    ///
+    /// After `with_in_memory_only`, resolvers, that are not in memory, are refused (see
+    /// `check_in_memory`) and the returned handle belongs to no resolver.
+    pub fn register_file_resolver<F>(&mut self, file_resolver: F, priority: i32) -> ResolverHandle
+    where
+        F: FileResolver + Send + Sync + 'static,
+    {
+        if self.refuse_resolver(&file_resolver) {
+            return self.file_resolvers.unused_handle();
+        }
+        self.clear_document_cache();
+        self.file_resolvers.push(Box::new(file_resolver), priority)
+    }
+
+    /// Remove the file resolver `handle`. Returns `false`, if it was already removed.
+    pub fn remove_file_resolver(&mut self, handle: ResolverHandle) -> bool {
+        self.clear_document_cache();
+        self.file_resolvers.remove(handle).is_some()
+    }
+
+    /// Replace the file resolver `handle` with `file_resolver`, which keeps the handle and the
+    /// priority. Returns `false` (and drops `file_resolver`), if the handle was removed or
+    /// `file_resolver` was refused by `with_in_memory_only`.
+    pub fn replace_file_resolver<F>(&mut self, handle: ResolverHandle, file_resolver: F) -> bool
+    where
+        F: FileResolver + Send + Sync + 'static,
+    {
+        if self.refuse_resolver(&file_resolver) {
+            return false;
+        }
+        self.clear_document_cache();
+        self.file_resolvers
+            .replace(handle, Box::new(file_resolver))
+            .is_some()
+    }
+
+    /// Change the priority of the file resolver `handle`. It is resolved after the other
+    /// resolvers with the same priority. Returns `false`, if the handle was removed.
+    pub fn set_file_resolver_priority(&mut self, handle: ResolverHandle, priority: i32) -> bool {
+        self.clear_document_cache();
+        self.file_resolvers.set_priority(handle, priority)
+    }
+
+    /// The priority of the file resolver `handle`, if it was not removed.
+    pub fn file_resolver_priority(&self, handle: ResolverHandle) -> Option<i32> {
+        self.file_resolvers.priority(handle)
+    }
+
+    /// Add a package, that only exists in memory, so templates can import it without
+    /// downloading it (e.g. `#import "@mycompany/branding:1.0.0"`). Virtual packages are
+    /// resolved before all other file resolvers. See `VirtualPackages::add_package`.
+    pub fn with_virtual_package<I, P, B>(mut self, spec: PackageSpec, files: I) -> Self
+    where
+        I: IntoIterator<Item = (P, B)>,
+        P: AsRef<std::path::Path>,
+        B: AsRef<[u8]>,
+    {
+        self.with_virtual_package_mut(spec, files);
+        self
+    }
+
+    /// Add a package, that only exists in memory, so templates can import it without
+    /// downloading it (e.g. `#import "@mycompany/branding:1.0.0"`). Virtual packages are
+    /// resolved before all other file resolvers. See `VirtualPackages::add_package`.
+    pub fn with_virtual_package_mut<I, P, B>(&mut self, spec: PackageSpec, files: I)
+    where
+        I: IntoIterator<Item = (P, B)>,
+        P: AsRef<std::path::Path>,
+        B: AsRef<[u8]>,
+    {
+        let packages = match &self.virtual_packages {
+            Some(packages) => packages.clone(),
+            None => {
+                let packages = VirtualPackages::new();
+                self.file_resolvers
+                    .push_built_in(Box::new(packages.clone()));
+                self.virtual_packages = Some(packages.clone());
+                packages
+            }
+        };
+        if let Some(document_cache) = &self.document_cache {
+            document_cache.invalidate_package(&spec);
+        }
+        packages.add_package(spec, files);
+    }
+
+    /// Register `source` as template `name`, so it can be compiled with `compile_by_name`.
+    /// Replaces the template, if it is already registered. Registered templates are resolved
+    /// before all other file resolvers. Returns the `FileId` of the template.
+    pub fn register_template<N, S>(&mut self, name: N, source: S) -> FileId
+    where
+        N: Into<EcoString>,
+        S: Into<String>,
+    {
+        let registry = match &self.template_registry {
+            Some(registry) => registry.clone(),
+            None => {
+                let registry = TemplateRegistry::new();
+                self.file_resolvers
+                    .push_built_in(Box::new(registry.clone()));
+                self.template_registry = Some(registry.clone());
+                registry
+            }
+        };
+        let id = registry.register(name, source);
+        self.invalidate_documents(id);
+        id
+    }
+
+    /// Returns `true`, if the template was registered.
+    pub fn remove_template(&mut self, name: &str) -> bool {
+        let removed = self
+            .template_registry
+            .as_ref()
+            .is_some_and(|registry| registry.remove(name));
+        if removed {
+            self.invalidate_documents(registered_template_file_id(name));
+        }
+        removed
+    }
+
+    /// Names of all registered templates, sorted.
+    pub fn template_names(&self) -> Vec<EcoString> {
+        self.template_registry
+            .as_ref()
+            .map(TemplateRegistry::names)
+            .unwrap_or_default()
+    }
+
+    /// Compile the template, that was registered as `name` (see `register_template`), with
+    /// `input` available as `#import sys: inputs`.
+    pub fn compile_by_name<D>(
         &self,
-        main_source_id: F,
-        inputs: Option<D>,
+        name: &str,
+        input: D,
     ) -> Warned<Result<Document, TypstAsLibError>>
+    where
+        D: Into<Dict>,
+    {
+        if !self
+            .template_registry
+            .as_ref()
+            .is_some_and(|registry| registry.contains(name))
+        {
+            return Warned {
+                output: Err(TypstAsLibError::TemplateNotRegistered(name.into())),
+                warnings: Default::default(),
+            };
+        }
+        self.compile_with_input(registered_template_file_id(name), input)
+    }
+
+    /// Replace the text of the source `id`, e.g. on each keystroke of a live preview.
+    /// Only the changed part is reparsed (see `Source::replace`). The first update of a file
+    /// starts from the source of the file resolvers, later ones from the previous update.
+    /// Updated sources take precedence over all file resolvers. Removes the documents, that
+    /// depend on `id`, from the `DocumentCache`.
+    /// Returns the range in the new text, that was changed.
+    pub fn update_source<F>(&mut self, id: F, new_text: &str) -> Range<usize>
+    where
+        F: Into<FileIdNewType>,
+    {
+        let FileIdNewType(id) = id.into();
+        let range = self.mutable_sources(id).update(id, new_text);
+        self.invalidate_documents(id);
+        range
+    }
+
+    /// Replace the byte range `replace` of the source `id` with `with`, like `update_source`,
+    /// but for editors, that report changes instead of the whole text (see `Source::edit`).
+    pub fn edit_source<F>(
+        &mut self,
+        id: F,
+        replace: Range<usize>,
+        with: &str,
+    ) -> Result<Range<usize>, TypstAsLibError>
+    where
+        F: Into<FileIdNewType>,
+    {
+        let FileIdNewType(id) = id.into();
+        let range = self.mutable_sources(id).edit(id, replace, with)?;
+        self.invalidate_documents(id);
+        Ok(range)
+    }
+
+    /// Returns `true`, if `id` was updated. Afterwards `id` is resolved by the file resolvers
+    /// again.
+    pub fn reset_source<F>(&mut self, id: F) -> bool
+    where
+        F: Into<FileIdNewType>,
+    {
+        let FileIdNewType(id) = id.into();
+        let removed = self
+            .mutable_sources
+            .as_ref()
+            .is_some_and(|sources| sources.remove(id));
+        if removed {
+            self.invalidate_documents(id);
+        }
+        removed
+    }
+
+    fn mutable_sources(&mut self, id: FileId) -> MutableSourceFileResolver {
+        let sources = match &self.mutable_sources {
+            Some(sources) => sources.clone(),
+            None => {
+                let sources = MutableSourceFileResolver::new();
+                self.file_resolvers.push_built_in(Box::new(sources.clone()));
+                self.mutable_sources = Some(sources.clone());
+                sources
+            }
+        };
+        if !sources.contains(id) {
+            if let Ok(source) = self.resolve_source(id) {
+                sources.insert(source.into_owned());
+            }
+        }
+        sources
+    }
+
+    /// Remove the documents, that depend on the file `id`, from the `DocumentCache`, e.g.
+    /// after the file changed on disk. Returns the number of removed documents.
+    pub fn invalidate_documents<F>(&self, id: F) -> usize
     where
         F: Into<FileIdNewType>,
+    {
+        let FileIdNewType(id) = id.into();
+        self.document_cache
+            .as_ref()
+            .map_or(0, |document_cache| document_cache.invalidate(id))
+    }
+
+    fn clear_document_cache(&self) {
+        if let Some(document_cache) = &self.document_cache {
+            document_cache.clear();
+        }
+    }
+
+    /// Query the elements of a compiled `document`, like `typst query` does. `selector` is
+    /// typst code, e.g. `<total>`, `heading` or `heading.where(level: 1)`.
+    /// For `metadata` elements the embedded value is returned, for all other elements the
+    /// element itself (serialized as a dictionary of its fields).
+    ///
+    /// ```rust,ignore
+    /// // In the template: #metadata(total) <total>
+    /// let totals = collection.query(&document, "<total>")?;
+    /// ```
+    pub fn query(
+        &self,
+        document: &Document,
+        selector: &str,
+    ) -> Result<Vec<Value>, TypstAsLibError> {
+        self.check_in_memory()?;
+        let world = TypstWorld {
+            collection: self,
+            main_source_id: FileId::new(None, VirtualPath::new("query.typ")),
+            library: Cow::Borrowed(&self.library),
+            book: Cow::Borrowed(&self.book),
+            extra_fonts: &[],
+            files: None,
+            now: self.fixed_now.unwrap_or_else(Utc::now),
+            cancellation: None,
+            dependencies: Default::default(),
+            package_error: Default::default(),
+            main_source_errors: Default::default(),
+            used_today: Default::default(),
+        };
+        let world: &dyn typst::World = &world;
+        let LocatableSelector(selector) = eval_string(
+            world.track(),
+            selector,
+            Span::detached(),
+            EvalMode::Code,
+            Scope::default(),
+        )?
+        .cast::<LocatableSelector>()?;
+        let values = document
+            .introspector
+            .query(&selector)
+            .into_iter()
+            .map(|element| match element.to_packed::<MetadataElem>() {
+                Some(metadata) => metadata.value.clone(),
+                None => Value::Content(element),
+            })
+            .collect();
+        Ok(values)
+    }
+
+    /// Evaluate the typst expression `expr` (code mode) with the library, inputs and file
+    /// resolvers of the collection, without producing a document, e.g. for formatting helpers
+    /// of a template. `inputs` are available as `sys.inputs` (or the custom inject location),
+    /// merged with the default inputs. Like compiles, the evaluation is cancelled after
+    /// `with_timeout` and sensitive inputs are redacted in the errors.
+    ///
+    /// ```rust,ignore
+    /// let value = collection.eval_expr("upper(sys.inputs.name)", dict! { "name" => "Alice" })?;
+    /// let greeting = collection.eval_expr(r#"import "/helpers.typ": greet; greet("Bob")"#, Dict::new())?;
+    /// ```
+    pub fn eval_expr<D>(&self, expr: &str, inputs: D) -> Result<Value, TypstAsLibError>
+    where
         D: Into<Dict>,
     {
-        let FileIdNewType(main_source_id) = main_source_id.into();
+        self.check_in_memory()?;
+        let mut inputs = inputs.into();
+        if let Some(defaults) = &self.default_inputs {
+            inputs = merge_dicts(defaults.clone(), inputs);
+        }
+        let sensitive_values = self.sensitive_inputs.values(&inputs);
+        let cancellation = self
+            .timeout
+            .map(|timeout| CancellationToken::new().with_timeout(timeout));
+        let now = self.fixed_now.unwrap_or_else(Utc::now);
+        let main_source_id = FileId::new(None, VirtualPath::new("eval.typ"));
+        // A span in the root, so imports and file paths are resolved relative to the root.
+        let span = Source::new(main_source_id, String::new()).root().span();
+        let extra_fonts = self.extra_fonts(None)?;
         let world = TypstWorld {
             collection: self,
             main_source_id,
-            library: if let Some(inputs) = inputs {
-                let lib = self.create_injected_library(inputs);
-                match lib {
-                    Ok(lib) => Cow::Owned(lib),
-                    Err(err) => {
-                        return Warned {
-                            output: Err(err),
-                            warnings: Default::default(),
-                        };
-                    }
-                }
-            } else {
-                Cow::Borrowed(&self.library)
+            library: Cow::Owned(self.create_injected_library(Some(inputs), now, None)?),
+            book: match &extra_fonts {
+                Some((book, _)) => Cow::Borrowed(book),
+                None => Cow::Borrowed(&self.book),
             },
-            now: Utc::now(),
+            extra_fonts: extra_fonts.as_ref().map_or(&[], |(_, fonts)| fonts),
+            files: None,
+            now,
+            cancellation: cancellation.clone(),
+            dependencies: Default::default(),
+            package_error: Default::default(),
+            main_source_errors: Default::default(),
+            used_today: Default::default(),
         };
-        let Warned { output, warnings } = typst::compile(&world);
+        let tracked: &dyn typst::World = &world;
+        let value = eval_string(
+            tracked.track(),
+            expr,
+            span,
+            EvalMode::Code,
+            Scope::default(),
+        );
+        if cancellation.is_some_and(|cancellation| cancellation.is_cancelled()) {
+            return Err(TypstAsLibError::Cancelled);
+        }
+        value.map_err(|mut errors| {
+            let package_error = world.package_error.lock().ok().and_then(|mut e| e.take());
+            if let Some(package_error) = package_error {
+                return package_error;
+            }
+            redact_diagnostics(&mut errors, &sensitive_values);
+            errors.into()
+        })
+    }
+
+    /// Same as `query`, but returns the values as JSON (feature `json`).
+    #[cfg(feature = "json")]
+    pub fn query_json(
+        &self,
+        document: &Document,
+        selector: &str,
+    ) -> Result<serde_json::Value, TypstAsLibError> {
+        let values = self.query(document, selector)?;
+        serde_json::to_value(values).map_err(|err| {
+            HintedString::new(eco_format!("Could not serialize query result: {err}")).into()
+        })
+    }
+
+    /// Guarantee, that compiles never touch the file system or network: all file resolvers
+    /// (also the built-in ones) must be in memory (`FileResolver::is_in_memory`), fonts must
+    /// not be read from a path (`FontSlot::is_in_memory`) and no depfile is written.
+    /// Returns `TypstAsLibError::NotInMemory`, if the collection already breaks this.
+    /// Resolvers, fonts and depfiles, that are not in memory and added afterwards, are refused:
+    /// they are not added and `check_in_memory` (and each compile) returns `NotInMemory`.
+    pub fn with_in_memory_only(mut self) -> Result<Self, TypstAsLibError> {
+        self.with_in_memory_only_mut()?;
+        Ok(self)
+    }
 
-        if let Some(comemo_evict_max_age) = self.comemo_evict_max_age {
-            comemo::evict(comemo_evict_max_age);
+    /// Guarantee, that compiles never touch the file system or network. See
+    /// `with_in_memory_only`.
+    pub fn with_in_memory_only_mut(&mut self) -> Result<(), TypstAsLibError> {
+        self.in_memory_only = true;
+        let checked = self.check_in_memory();
+        self.in_memory_only = checked.is_ok();
+        checked
+    }
+
+    /// Err, if `with_in_memory_only` was set and a file resolver, font or depfile, that is not
+    /// in memory, was refused or is part of the collection. Call it after setting up the
+    /// collection, compiles fail with the same error.
+    pub fn check_in_memory(&self) -> Result<(), TypstAsLibError> {
+        if !self.in_memory_only {
+            return Ok(());
+        }
+        if let Some(violation) = &self.in_memory_violation {
+            return Err(TypstAsLibError::NotInMemory(violation.clone()));
+        }
+        if let Some(resolver) = self.file_resolvers.iter().find(|r| !r.is_in_memory()) {
+            return Err(TypstAsLibError::NotInMemory(short_type_name(
+                resolver.name(),
+            )));
         }
+        if let Some(slot) = self.fonts.iter().find(|slot| !slot.is_in_memory()) {
+            let family = &slot.info().family;
+            return Err(TypstAsLibError::NotInMemory(eco_format!("Font {family}")));
+        }
+        if let Some(depfile) = &self.depfile {
+            let path = depfile.path.display();
+            return Err(TypstAsLibError::NotInMemory(eco_format!("Depfile {path}")));
+        }
+        Ok(())
+    }
 
-        Warned {
-            output: output.map_err(Into::into),
-            warnings,
+    /// Whether `with_in_memory_only` refuses something, that is not in memory. Remembers the
+    /// first refused one (`name`) for `check_in_memory`.
+    fn refuse_not_in_memory<N>(&mut self, name: N) -> bool
+    where
+        N: FnOnce() -> EcoString,
+    {
+        if !self.in_memory_only {
+            return false;
         }
+        self.in_memory_violation.get_or_insert_with(name);
+        true
+    }
+
+    fn refuse_resolver<F>(&mut self, file_resolver: &F) -> bool
+    where
+        F: FileResolver,
+    {
+        !file_resolver.is_in_memory()
+            && self.refuse_not_in_memory(|| short_type_name(file_resolver.name()))
+    }
+
+    /// Adds the `StaticSourceFileResolver` to the file resolvers. It creates `HashMap`s for sources.
+    ///
+    /// `sources` The item of the IntoIterator can be of types:
+    ///   - `&str/String`, creating a detached Source (Has vpath `/main.typ`)
+    ///   - `(&str, &str/String)`, where &str is the absolute
+    ///     virtual path of the Source file.
+    ///   - `(typst::syntax::FileId, &str/String)`
+    ///   - `typst::syntax::Source`
+    ///
+    /// (`&str/String` is always the template file content)
+    pub fn with_static_source_file_resolver<IS, S>(mut self, sources: IS) -> Self
+    where
+        IS: IntoIterator<Item = S>,
+        S: Into<SourceNewType>,
+    {
+        self.with_static_source_file_resolver_mut(sources);
+        self
+    }
+
+    /// Adds the `StaticSourceFileResolver` to the file resolvers. It creates `HashMap`s for sources.
+    pub fn with_static_source_file_resolver_mut<IS, S>(&mut self, sources: IS)
+    where
+        IS: IntoIterator<Item = S>,
+        S: Into<SourceNewType>,
+    {
+        self.add_file_resolver_mut(StaticSourceFileResolver::new(sources));
+    }
+
+    /// Adds the `StaticFileResolver` to the file resolvers. It creates `HashMap`s for binaries.
+    pub fn with_static_file_resolver<IB, F, B>(mut self, binaries: IB) -> Self
+    where
+        IB: IntoIterator<Item = (F, B)>,
+        F: Into<FileIdNewType>,
+        B: Into<Bytes>,
+    {
+        self.with_static_file_resolver_mut(binaries);
+        self
+    }
+
+    /// Adds the `StaticFileResolver` to the file resolvers. It creates `HashMap`s for binaries.
+    pub fn with_static_file_resolver_mut<IB, F, B>(&mut self, binaries: IB)
+    where
+        IB: IntoIterator<Item = (F, B)>,
+        F: Into<FileIdNewType>,
+        B: Into<Bytes>,
+    {
+        self.add_file_resolver_mut(StaticFileResolver::new(binaries));
+    }
+
+    /// Adds `FileSystemResolver` to the file resolvers, a resolver that can resolve
+    /// local files (when `package` is not set in `FileId`).
+    pub fn with_file_system_resolver<P>(mut self, root: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.with_file_system_resolver_mut(root);
+        self
+    }
+
+    /// Adds `FileSystemResolver` to the file resolvers, a resolver that can resolve
+    /// local files (when `package` is not set in `FileId`).
+    pub fn with_file_system_resolver_mut<P>(&mut self, root: P)
+    where
+        P: Into<PathBuf>,
+    {
+        self.add_file_resolver_mut(FileSystemResolver::new(root.into()).into_cached());
+    }
+
+    /// Adds `LocalPackagesResolver` to the file resolvers, a resolver that reads packages from
+    /// the local package directories like the typst cli (e.g. `@local/...` imports), without
+    /// network access.
+    pub fn with_local_packages_resolver(mut self) -> Self {
+        self.with_local_packages_resolver_mut();
+        self
+    }
+
+    /// Adds `LocalPackagesResolver` to the file resolvers, a resolver that reads packages from
+    /// the local package directories like the typst cli (e.g. `@local/...` imports), without
+    /// network access.
+    pub fn with_local_packages_resolver_mut(&mut self) {
+        self.add_file_resolver_mut(LocalPackagesResolver::new().into_cached());
+    }
+
+    /// Adds a cached `HttpFileResolver` to the file resolvers, a resolver that downloads
+    /// local files (when `package` is not set in `FileId`) relative to `base_url`
+    /// (feature `http`).
+    #[cfg(feature = "http")]
+    pub fn with_http_resolver<S>(mut self, base_url: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.with_http_resolver_mut(base_url);
+        self
+    }
+
+    /// Adds a cached `HttpFileResolver` to the file resolvers, a resolver that downloads
+    /// local files (when `package` is not set in `FileId`) relative to `base_url`
+    /// (feature `http`).
+    #[cfg(feature = "http")]
+    pub fn with_http_resolver_mut<S>(&mut self, base_url: S)
+    where
+        S: Into<String>,
+    {
+        self.add_file_resolver_mut(http_resolver::HttpFileResolver::new(base_url).into_cached());
     }
 
-    fn create_injected_library<D>(&self, input: D) -> Result<LazyHash<Library>, TypstAsLibError>
+    /// Adds the fonts of the `SharedRegistry` and the registry itself to the file resolvers.
+    /// Use `SharedRegistry::global()` to share fonts and static files between all collections
+    /// of the process.
+    pub fn with_shared_registry(mut self, registry: SharedRegistry) -> Self {
+        self.with_shared_registry_mut(registry);
+        self
+    }
+
+    /// After each compile, write a Make/Ninja style depfile to `path`, that lists the files
+    /// on disk, that were read during the compile, as dependencies of `target`. Compiles
+    /// served by the document cache write the depfile as well. A failed write is reported as
+    /// warning.
+    pub fn with_depfile<P, T>(mut self, path: P, target: T) -> Self
+    where
+        P: Into<PathBuf>,
+        T: Into<String>,
+    {
+        self.with_depfile_mut(path, target);
+        self
+    }
+
+    /// After each compile, write a Make/Ninja style depfile to `path`, that lists the files
+    /// on disk, that were read during the compile, as dependencies of `target`. Compiles
+    /// served by the document cache write the depfile as well. A failed write is reported as
+    /// warning.
+    pub fn with_depfile_mut<P, T>(&mut self, path: P, target: T)
+    where
+        P: Into<PathBuf>,
+        T: Into<String>,
+    {
+        let path = path.into();
+        if self.refuse_not_in_memory(|| eco_format!("Depfile {}", path.display())) {
+            return;
+        }
+        self.depfile = Some(Depfile {
+            path,
+            target: target.into(),
+        });
+    }
+
+    /// Adds the fonts of the `SharedRegistry` and the registry itself to the file resolvers.
+    /// Use `SharedRegistry::global()` to share fonts and static files between all collections
+    /// of the process.
+    pub fn with_shared_registry_mut(&mut self, registry: SharedRegistry) {
+        self.shared_registries.push(registry.clone());
+        self.add_file_resolver_mut(registry);
+    }
+
+    /// The fonts of the shared registries and `font_set`, that are available in addition to
+    /// the fonts of the collection, with the book of all fonts. `None`, if there are none.
+    /// The registries are read on each call, so fonts, that were added to a registry after
+    /// `with_shared_registry`, are available as well.
+    fn extra_fonts(&self, font_set: Option<&FontSet>) -> Result<Option<ExtraFonts>, FileError> {
+        let mut fonts = Vec::new();
+        for registry in &self.shared_registries {
+            fonts.extend(registry.fonts()?);
+        }
+        if let Some(font_set) = font_set {
+            fonts.extend_from_slice(font_set.fonts());
+        }
+        if fonts.is_empty() {
+            return Ok(None);
+        }
+        let extended_book = font_set.map_or(&self.registry_book, |font_set| &font_set.book);
+        Ok(Some((extended_book.get(&self.book, &fonts), fonts)))
+    }
+
+    /// Use `now` as current time in all compiles (`datetime.today()`) instead of the system
+    /// time, e.g. for reproducible builds and golden tests.
+    pub fn with_fixed_now(mut self, now: DateTime<Utc>) -> Self {
+        self.with_fixed_now_mut(now);
+        self
+    }
+
+    /// Use `now` as current time in all compiles (`datetime.today()`) instead of the system
+    /// time, e.g. for reproducible builds and golden tests.
+    pub fn with_fixed_now_mut(&mut self, now: DateTime<Utc>) {
+        self.fixed_now = Some(now);
+        self.clear_document_cache();
+    }
+
+    /// Use the local time of `timezone` for `datetime.today()` (without offset) and for the
+    /// value of `with_injected_now`, instead of UTC. Daylight saving time is taken into
+    /// account.
+    #[cfg(feature = "timezone")]
+    pub fn with_timezone(mut self, timezone: chrono_tz::Tz) -> Result<Self, TypstAsLibError> {
+        self.with_timezone_mut(timezone)?;
+        Ok(self)
+    }
+
+    /// Use the local time of `timezone` for `datetime.today()` (without offset) and for the
+    /// value of `with_injected_now`, instead of UTC. Daylight saving time is taken into
+    /// account.
+    #[cfg(feature = "timezone")]
+    pub fn with_timezone_mut(&mut self, timezone: chrono_tz::Tz) -> Result<(), TypstAsLibError> {
+        self.timezone = Some(timezone);
+        if self.date_helpers {
+            self.define_date_helpers()?;
+        }
+        self.clear_document_cache();
+        Ok(())
+    }
+
+    /// Inject the current time as `datetime` with date and time components into the module
+    /// of the inputs (`sys` by default, see `custom_inject_location`) as `value_name`.
+    /// `datetime.today()` only has a date. The time is local to `with_timezone` (feature
+    /// `timezone`) or UTC.
+    ///
+    /// ```rust,ignore
+    /// let collection = collection.with_injected_now("now");
+    /// // In the template:
+    /// // #import sys: now
+    /// // #now.display("[year]-[month]-[day] [hour]:[minute]")
+    /// ```
+    ///
+    /// Compiles with an injected time are not cached by `with_document_cache`.
+    pub fn with_injected_now<S>(mut self, value_name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.with_injected_now_mut(value_name);
+        self
+    }
+
+    /// Inject the current time as `datetime` with date and time components into the module
+    /// of the inputs (`sys` by default, see `custom_inject_location`) as `value_name`.
+    /// See `with_injected_now`.
+    pub fn with_injected_now_mut<S>(&mut self, value_name: S)
+    where
+        S: Into<String>,
+    {
+        self.inject_now = Some(value_name.into());
+    }
+
+    /// Hash of the settings of the collection, that change the documents of a compile
+    /// besides the files, the inputs and the fonts: the library (with the globals and the
+    /// injected modules), the inject location, the fixed time, the date of `now` and the
+    /// generations of the shared registries.
+    fn fingerprint(&self, now: DateTime<Utc>) -> u128 {
+        let today = self.local_now(now).date();
+        let generations: Vec<u64> = self
+            .shared_registries
+            .iter()
+            .map(SharedRegistry::generation)
+            .collect();
+        typst::utils::hash128(&(
+            &self.library,
+            &self.inject_location,
+            &self.book,
+            self.fixed_now,
+            today,
+            generations,
+        ))
+    }
+
+    /// `now` in the timezone of `with_timezone` or in UTC.
+    fn local_now(&self, now: DateTime<Utc>) -> NaiveDateTime {
+        #[cfg(feature = "timezone")]
+        if let Some(timezone) = &self.timezone {
+            return now.with_timezone(timezone).naive_local();
+        }
+        now.naive_utc()
+    }
+
+    /// Cancel compiles, that take longer than `timeout`. They return
+    /// `TypstAsLibError::Cancelled`. This is not a watchdog: the timeout is only checked, when
+    /// the compile accesses a file, a font or the date, so e.g. an endless loop in a show rule
+    /// is not stopped (see `CancellationToken`).
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.with_timeout_mut(timeout);
+        self
+    }
+
+    /// Cancel compiles, that take longer than `timeout`. They return
+    /// `TypstAsLibError::Cancelled`. See `CancellationToken` for the limits of cancelling.
+    pub fn with_timeout_mut(&mut self, timeout: Duration) {
+        self.timeout = Some(timeout);
+    }
+
+    /// Files, for which `policy` returns `false`, are denied with `FileError::AccessDenied`,
+    /// before any file resolver is asked, e.g. to deny certain paths, extensions or packages
+    /// regardless of the resolver, that would serve them. Also applies to the files of a
+    /// single compile (`CompileOptions::files`).
+    ///
+    /// ```rust,ignore
+    /// let collection = collection.with_access_policy(|id| {
+    ///     id.package().is_none() && id.vpath().as_rootless_path().extension() != Some("csv".as_ref())
+    /// });
+    /// ```
+    pub fn with_access_policy<P>(mut self, policy: P) -> Self
+    where
+        P: Fn(FileId) -> bool + Send + Sync + 'static,
+    {
+        self.with_access_policy_mut(policy);
+        self
+    }
+
+    /// Files, for which `policy` returns `false`, are denied with `FileError::AccessDenied`.
+    /// See `with_access_policy`.
+    pub fn with_access_policy_mut<P>(&mut self, policy: P)
+    where
+        P: Fn(FileId) -> bool + Send + Sync + 'static,
+    {
+        self.access_policy = Some(Box::new(policy));
+    }
+
+    /// Suppress warnings or fail compiles on warnings, see `WarningPolicy`.
+    pub fn with_warning_policy(mut self, policy: WarningPolicy) -> Self {
+        self.with_warning_policy_mut(policy);
+        self
+    }
+
+    /// Suppress warnings or fail compiles on warnings, see `WarningPolicy`.
+    pub fn with_warning_policy_mut(&mut self, policy: WarningPolicy) {
+        self.warning_policy = Some(policy);
+    }
+
+    /// Inject the `dates` module with helper functions for date arithmetic and formatting.
+    /// `format` uses the timezone of `with_timezone` (feature `timezone`) for offsets. See
+    /// `date_helpers` for the available functions. Fails like `with_global`, if `dates` can't
+    /// be replaced.
+    pub fn with_date_helpers(mut self) -> Result<Self, TypstAsLibError> {
+        self.with_date_helpers_mut()?;
+        Ok(self)
+    }
+
+    /// Inject the `dates` module with helper functions for date arithmetic and formatting.
+    /// See `with_date_helpers`.
+    pub fn with_date_helpers_mut(&mut self) -> Result<(), TypstAsLibError> {
+        self.define_date_helpers()?;
+        self.date_helpers = true;
+        Ok(())
+    }
+
+    /// (Re)define the `dates` module with the timezone of the collection.
+    fn define_date_helpers(&mut self) -> Result<(), TypstAsLibError> {
+        #[allow(unused_mut)]
+        let mut options = date_helpers::DateHelperOptions::default();
+        #[cfg(feature = "timezone")]
+        {
+            options.timezone = self.timezone.map(|timezone| timezone.name().into());
+        }
+        let name = date_helpers::DEFAULT_MODULE_NAME;
+        let module = date_helpers::module(name, &options);
+        define_in_scope(self.library.global.scope_mut(), name, module)?;
+        self.clear_document_cache();
+        Ok(())
+    }
+
+    /// Define a typst function `name` in the global scope, that calls the Rust closure `f`.
+    /// Fails like `with_global`, if `name` can't be replaced. See `native_functions`.
+    pub fn with_function<S, F>(mut self, name: S, f: F) -> Result<Self, TypstAsLibError>
+    where
+        S: Into<String>,
+        F: Fn(&mut Args) -> SourceResult<Value> + Send + Sync + 'static,
+    {
+        self.with_function_mut(name, f)?;
+        Ok(self)
+    }
+
+    /// Define a typst function `name` in the global scope, that calls the Rust closure `f`.
+    /// See `with_function`.
+    pub fn with_function_mut<S, F>(&mut self, name: S, f: F) -> Result<(), TypstAsLibError>
+    where
+        S: Into<String>,
+        F: Fn(&mut Args) -> SourceResult<Value> + Send + Sync + 'static,
+    {
+        let name = name.into();
+        let func = native_functions::native_function(name.clone(), f);
+        define_in_scope(self.library.global.scope_mut(), &name, func)?;
+        self.clear_document_cache();
+        Ok(())
+    }
+
+    /// Define a typst function `name` in the module `module` (`#module.name(..)`), that calls
+    /// the Rust closure `f`. The module is created in the global scope, if it does not exist yet.
+    /// Fails like `with_global`, if `module` can't be replaced. See `native_functions`.
+    pub fn with_module_function<M, S, F>(
+        mut self,
+        module: M,
+        name: S,
+        f: F,
+    ) -> Result<Self, TypstAsLibError>
+    where
+        M: Into<String>,
+        S: Into<String>,
+        F: Fn(&mut Args) -> SourceResult<Value> + Send + Sync + 'static,
+    {
+        self.with_module_function_mut(module, name, f)?;
+        Ok(self)
+    }
+
+    /// Define a typst function `name` in the module `module` (`#module.name(..)`), that calls
+    /// the Rust closure `f`. See `with_module_function`.
+    pub fn with_module_function_mut<M, S, F>(
+        &mut self,
+        module: M,
+        name: S,
+        f: F,
+    ) -> Result<(), TypstAsLibError>
+    where
+        M: Into<String>,
+        S: Into<String>,
+        F: Fn(&mut Args) -> SourceResult<Value> + Send + Sync + 'static,
+    {
+        let module_name = module.into();
+        let name = name.into();
+        let func = native_functions::native_function(name.clone(), f);
+        let scope = self.library.global.scope_mut();
+        let mut module = match scope.get(&module_name) {
+            Some(Value::Module(module)) => module.clone(),
+            _ => Module::new(module_name.clone(), Scope::new()),
+        };
+        define_in_scope(module.scope_mut(), &name, func)?;
+        define_in_scope(scope, &module_name, module)?;
+        self.clear_document_cache();
+        Ok(())
+    }
+
+    /// Adds the `TemplateStore` to the file resolvers. Compile a stored template with
+    /// `compile(template_store::template_file_id(name, version))`. Changed templates are
+    /// removed from the `DocumentCache`, if the store supports `TemplateStore::watch`.
+    pub fn with_template_store<S>(mut self, store: S) -> Self
+    where
+        S: TemplateStore + 'static,
+    {
+        self.with_template_store_mut(store);
+        self
+    }
+
+    /// Adds the `TemplateStore` to the file resolvers. Compile a stored template with
+    /// `compile(template_store::template_file_id(name, version))`. Changed templates are
+    /// removed from the `DocumentCache`, if the store supports `TemplateStore::watch`.
+    pub fn with_template_store_mut<S>(&mut self, store: S)
+    where
+        S: TemplateStore + 'static,
+    {
+        let store: Arc<dyn TemplateStore> = Arc::new(store);
+        if let Some(document_cache) = &self.document_cache {
+            watch_template_store(store.as_ref(), document_cache);
+        }
+        self.template_stores.push(store.clone());
+        self.add_file_resolver_mut(TemplateStoreResolver(store));
+    }
+
+    /// Adds the `OverlayResolver` before all other file resolvers, so files can be staged
+    /// (and shadow files of other resolvers) without rebuilding the collection. Compiles are
+    /// not cached by `with_document_cache`, while an overlay has files.
+    pub fn with_overlay_resolver(mut self, overlay: OverlayResolver) -> Self {
+        self.with_overlay_resolver_mut(overlay);
+        self
+    }
+
+    /// Adds the `OverlayResolver` before all other file resolvers, so files can be staged
+    /// (and shadow files of other resolvers) without rebuilding the collection. Compiles are
+    /// not cached by `with_document_cache`, while an overlay has files.
+    pub fn with_overlay_resolver_mut(&mut self, overlay: OverlayResolver) {
+        self.file_resolvers.push_built_in(Box::new(overlay.clone()));
+        self.overlays.push(overlay);
+    }
+
+    /// Register `data` as JSON file `path` (feature `json`), so templates can load big
+    /// datasets with `json("/data/orders.json")` instead of getting them through the inputs.
+    /// Data files are resolved before all other file resolvers. Replaces an existing data
+    /// file with the same path.
+    ///
+    /// ```rust,ignore
+    /// let collection = collection.with_data_file("/data/orders.json", &orders)?;
+    /// // In the template: #let orders = json("/data/orders.json")
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn with_data_file<P, T>(mut self, path: P, data: &T) -> Result<Self, TypstAsLibError>
+    where
+        P: Into<FileIdNewType>,
+        T: serde::Serialize + ?Sized,
+    {
+        self.with_data_file_mut(path, data)?;
+        Ok(self)
+    }
+
+    /// Register `data` as JSON file `path` (feature `json`). See `with_data_file`.
+    #[cfg(feature = "json")]
+    pub fn with_data_file_mut<P, T>(&mut self, path: P, data: &T) -> Result<(), TypstAsLibError>
+    where
+        P: Into<FileIdNewType>,
+        T: serde::Serialize + ?Sized,
+    {
+        let FileIdNewType(id) = path.into();
+        let json = serde_json::to_vec(data).map_err(|err| {
+            HintedString::new(eco_format!(
+                "Could not serialize {}: {err}",
+                util::display_path(id)
+            ))
+        })?;
+        self.insert_data_file(id, json);
+        Ok(())
+    }
+
+    /// Register `rows` as CSV file `path`, so templates can load it with
+    /// `csv("/data/orders.csv")`. Fields with commas, quotes or line breaks are quoted.
+    /// Data files are resolved before all other file resolvers. Replaces an existing data
+    /// file with the same path.
+    ///
+    /// ```rust,ignore
+    /// let rows = orders.iter().map(|order| [order.id.to_string(), order.customer.clone()]);
+    /// let collection = collection.with_csv_file("/data/orders.csv", rows);
+    /// ```
+    pub fn with_csv_file<P, I, R, S>(mut self, path: P, rows: I) -> Self
+    where
+        P: Into<FileIdNewType>,
+        I: IntoIterator<Item = R>,
+        R: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.with_csv_file_mut(path, rows);
+        self
+    }
+
+    /// Register `rows` as CSV file `path`. See `with_csv_file`.
+    pub fn with_csv_file_mut<P, I, R, S>(&mut self, path: P, rows: I)
+    where
+        P: Into<FileIdNewType>,
+        I: IntoIterator<Item = R>,
+        R: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let FileIdNewType(id) = path.into();
+        self.insert_data_file(id, csv_bytes(rows));
+    }
+
+    fn insert_data_file(&mut self, id: FileId, bytes: Vec<u8>) {
+        let data_files = match &self.data_files {
+            Some(data_files) => data_files.clone(),
+            None => {
+                let data_files = OverlayResolver::new();
+                self.file_resolvers
+                    .push_built_in(Box::new(data_files.clone()));
+                self.data_files = Some(data_files.clone());
+                data_files
+            }
+        };
+        data_files.insert(id, bytes);
+        self.invalidate_documents(id);
+    }
+
+    pub fn comemo_evict_max_age(&mut self, comemo_evict_max_age: Option<usize>) -> &mut Self {
+        self.comemo_evict_max_age = comemo_evict_max_age;
+        self
+    }
+
+    /// Cache compiled documents in memory. Documents are cached by the main source `FileId`,
+    /// the inputs, the library, the fonts and the current date (see `DocumentCache`). Use
+    /// `DocumentCache::with_cache_key` to ignore volatile inputs.
+    pub fn with_document_cache(mut self, document_cache: DocumentCache) -> Self {
+        self.with_document_cache_mut(document_cache);
+        self
+    }
+
+    /// Cache compiled documents in memory. Documents are cached by the main source `FileId`,
+    /// the inputs, the library, the fonts and the current date (see `DocumentCache`). Use
+    /// `DocumentCache::with_cache_key` to ignore volatile inputs.
+    pub fn with_document_cache_mut(&mut self, document_cache: DocumentCache) {
+        for store in &self.template_stores {
+            watch_template_store(store.as_ref(), &document_cache);
+        }
+        self.document_cache = Some(document_cache);
+    }
+
+    #[cfg(feature = "packages")]
+    /// Adds `PackageResolver` to the file resolvers.
+    /// When `package` is set in `FileId`, it will download the package from the typst package
+    /// repository. It caches the results into `cache` (which is either in memory or cache folder (default)).
+    /// Example
+    /// ```rust,ignore
+    ///     let template = TypstTemplateCollection::new(vec![font])
+    ///         .with_package_file_resolver(None);
+    /// ```
+    pub fn with_package_file_resolver(mut self, ureq: Option<ureq::Agent>) -> Self {
+        self.with_package_file_resolver_mut(ureq);
+        self
+    }
+
+    #[cfg(feature = "packages")]
+    pub fn with_package_file_resolver_mut(&mut self, ureq: Option<ureq::Agent>) {
+        use package_resolver::PackageResolverBuilder;
+        let mut builder = PackageResolverBuilder::new().with_file_system_cache();
+        if let Some(ureq) = ureq {
+            builder = builder.ureq_agent(ureq);
+        }
+        self.add_file_resolver_mut(builder.build().into_cached());
+    }
+
+    #[cfg(feature = "packages")]
+    /// Same as `with_package_file_resolver`, but caches the packages in `cache_dir`
+    /// instead of the default cache folder.
+    pub fn with_package_file_resolver_cache_dir<P>(
+        mut self,
+        cache_dir: P,
+        ureq: Option<ureq::Agent>,
+    ) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.with_package_file_resolver_cache_dir_mut(cache_dir, ureq);
+        self
+    }
+
+    #[cfg(feature = "packages")]
+    pub fn with_package_file_resolver_cache_dir_mut<P>(
+        &mut self,
+        cache_dir: P,
+        ureq: Option<ureq::Agent>,
+    ) where
+        P: Into<PathBuf>,
+    {
+        use package_resolver::PackageResolverBuilder;
+        let mut builder = PackageResolverBuilder::new().with_file_system_cache_dir(cache_dir);
+        if let Some(ureq) = ureq {
+            builder = builder.ureq_agent(ureq);
+        }
+        self.add_file_resolver_mut(builder.build().into_cached());
+    }
+
+    /// Call `typst::compile()` with our template and a `Dict` as input, that will be availible
+    /// in a typst script with `#import sys: inputs`.
+    ///
+    /// Example:
+    ///
+    /// ```rust,ignore
+    /// static TEMPLATE: &str = include_str!("./templates/template.typ");
+    /// static FONT: &[u8] = include_bytes!("./fonts/texgyrecursor-regular.otf");
+    /// static TEMPLATE_ID: &str = "/template.typ";
+    /// // ...
+    /// let font = Font::new(Bytes::from(FONT), 0).expect("Could not parse font!");
+    /// let template_collection = TypstTemplateCollection::new(vec![font])
+    ///     .add_static_file_resolver([(TEMPLATE_ID, TEMPLATE)]);
+    /// // Struct that implements Into<Dict>.
+    /// let inputs = todo!();
+    /// let tracer = Default::default();
+    /// let doc = template_collection.compile_with_input(&mut tracer, TEMPLATE_ID, inputs)
+    ///     .expect("Typst error!");
+    /// ```
+    pub fn compile_with_input<F, D>(
+        &self,
+        main_source_id: F,
+        input: D,
+    ) -> Warned<Result<Document, TypstAsLibError>>
+    where
+        F: Into<FileIdNewType>,
+        D: Into<Dict>,
+    {
+        self.compile_helper(main_source_id, Some(input), Default::default())
+            .into()
+    }
+
+    /// Call `typst::compile()` with our template and a `Dict` as input, that will be availible
+    /// in a typst script with `#import sys: inputs`. Mutates the library each call.
+    ///
+    /// Example:
+    ///
+    /// ```rust,ignore
+    /// static TEMPLATE: &str = include_str!("./templates/template.typ");
+    /// static FONT: &[u8] = include_bytes!("./fonts/texgyrecursor-regular.otf");
+    /// static TEMPLATE_ID: &str = "/template.typ";
+    /// // ...
+    /// let font = Font::new(Bytes::from(FONT), 0).expect("Could not parse font!");
+    /// let template_collection = TypstTemplateCollection::new(vec![font])
+    ///     .add_static_file_resolver([(TEMPLATE_ID, TEMPLATE)]);
+    /// // Struct that implements Into<Dict>.
+    /// let inputs = todo!();
+    /// let tracer = Default::default();
+    /// let doc = template_collection.compile_with_input_fast(&mut tracer, TEMPLATE_ID, inputs)
+    ///     .expect("Typst error!");
+    /// ```
+    #[deprecated(
+        since = "0.11.1",
+        note = "Use TypstTemplate::compile_with_input() instead!"
+    )]
+    pub fn compile_with_input_fast<F, D>(
+        &mut self,
+        main_source_id: F,
+        input: D,
+    ) -> Warned<Result<Document, TypstAsLibError>>
+    where
+        F: Into<FileIdNewType>,
+        D: Into<Dict>,
+    {
+        if let Err(err) = self.check_in_memory() {
+            return Warned {
+                output: Err(err),
+                warnings: Default::default(),
+            };
+        }
+        let Self {
+            library,
+            inject_location,
+            ..
+        } = self;
+        let res = inject_input_into_library(library, inject_location.as_ref(), input);
+        match res {
+            Ok(_) => (),
+            Err(err) => {
+                return Warned {
+                    output: Err(err),
+                    warnings: Default::default(),
+                }
+            }
+        }
+        let collection = &*self;
+        let extra_fonts = match collection.extra_fonts(None) {
+            Ok(extra_fonts) => extra_fonts,
+            Err(err) => {
+                return Warned {
+                    output: Err(err.into()),
+                    warnings: Default::default(),
+                }
+            }
+        };
+
+        let FileIdNewType(main_source_id) = main_source_id.into();
+        let world = TypstWorld {
+            collection,
+            main_source_id,
+            library: Cow::Borrowed(&collection.library),
+            book: match &extra_fonts {
+                Some((book, _)) => Cow::Borrowed(book),
+                None => Cow::Borrowed(&collection.book),
+            },
+            extra_fonts: extra_fonts.as_ref().map_or(&[], |(_, fonts)| fonts),
+            files: None,
+            now: collection.fixed_now.unwrap_or_else(Utc::now),
+            cancellation: None,
+            dependencies: Default::default(),
+            package_error: Default::default(),
+            main_source_errors: Default::default(),
+            used_today: Default::default(),
+        };
+        let Warned { output, warnings } = typst::compile(&world);
+
+        Warned {
+            output: output.map_err(Into::into),
+            warnings,
+        }
+    }
+
+    /// Just call `typst::compile()`
+    pub fn compile<F>(&self, main_source_id: F) -> Warned<Result<Document, TypstAsLibError>>
+    where
+        F: Into<FileIdNewType>,
+    {
+        self.compile_helper::<_, Dict>(main_source_id, None, Default::default())
+            .into()
+    }
+
+    /// Same as `compile`, but converts the document into the target `T`. See
+    /// `document_target`.
+    pub fn compile_as<T, F>(&self, main_source_id: F) -> Warned<Result<T, TypstAsLibError>>
+    where
+        T: DocumentTarget,
+        F: Into<FileIdNewType>,
+    {
+        let Warned { output, warnings } = self.compile(main_source_id);
+        Warned {
+            output: output.and_then(T::from_document),
+            warnings,
+        }
+    }
+
+    /// Same as `compile_with_input`, but converts the document into the target `T`. See
+    /// `document_target`.
+    pub fn compile_with_input_as<T, F, D>(
+        &self,
+        main_source_id: F,
+        input: D,
+    ) -> Warned<Result<T, TypstAsLibError>>
+    where
+        T: DocumentTarget,
+        F: Into<FileIdNewType>,
+        D: Into<Dict>,
+    {
+        let Warned { output, warnings } = self.compile_with_input(main_source_id, input);
+        Warned {
+            output: output.and_then(T::from_document),
+            warnings,
+        }
+    }
+
+    /// Same as `compile`, but with `now` as current time (`datetime.today()`).
+    /// Overrides `with_fixed_now`. Not cached by `with_document_cache`.
+    pub fn compile_at<F>(
+        &self,
+        main_source_id: F,
+        now: DateTime<Utc>,
+    ) -> Warned<Result<Document, TypstAsLibError>>
+    where
+        F: Into<FileIdNewType>,
+    {
+        self.compile_with_options(main_source_id, CompileOptions::new().with_now(now))
+    }
+
+    /// Same as `compile_with_input`, but with `now` as current time (`datetime.today()`).
+    /// Overrides `with_fixed_now`. Not cached by `with_document_cache`.
+    pub fn compile_with_input_at<F, D>(
+        &self,
+        main_source_id: F,
+        input: D,
+        now: DateTime<Utc>,
+    ) -> Warned<Result<Document, TypstAsLibError>>
+    where
+        F: Into<FileIdNewType>,
+        D: Into<Dict>,
+    {
+        let options = CompileOptions::new().with_inputs(input).with_now(now);
+        self.compile_with_options(main_source_id, options)
+    }
+
+    /// Same as `compile`, but returns `TypstAsLibError::Cancelled`, when `cancellation` is
+    /// cancelled during the compile.
+    pub fn compile_with_cancellation<F>(
+        &self,
+        main_source_id: F,
+        cancellation: CancellationToken,
+    ) -> Warned<Result<Document, TypstAsLibError>>
+    where
+        F: Into<FileIdNewType>,
+    {
+        let options = CompileOptions::new().with_cancellation(cancellation);
+        self.compile_with_options(main_source_id, options)
+    }
+
+    /// Same as `compile_with_input`, but returns `TypstAsLibError::Cancelled`, when
+    /// `cancellation` is cancelled during the compile.
+    pub fn compile_with_input_and_cancellation<F, D>(
+        &self,
+        main_source_id: F,
+        input: D,
+        cancellation: CancellationToken,
+    ) -> Warned<Result<Document, TypstAsLibError>>
+    where
+        F: Into<FileIdNewType>,
+        D: Into<Dict>,
+    {
+        let options = CompileOptions::new()
+            .with_inputs(input)
+            .with_cancellation(cancellation);
+        self.compile_with_options(main_source_id, options)
+    }
+
+    /// Same as `compile`, but returns a `CompileOutput`, that also contains the accessed files
+    /// and stats about the compile.
+    pub fn compile_detailed<F>(&self, main_source_id: F) -> CompileOutput
+    where
+        F: Into<FileIdNewType>,
+    {
+        self.compile_helper::<_, Dict>(main_source_id, None, Default::default())
+    }
+
+    /// Same as `compile_with_input`, but returns a `CompileOutput`, that also contains the
+    /// accessed files and stats about the compile.
+    pub fn compile_with_input_detailed<F, D>(&self, main_source_id: F, input: D) -> CompileOutput
+    where
+        F: Into<FileIdNewType>,
+        D: Into<Dict>,
+    {
+        self.compile_helper(main_source_id, Some(input), Default::default())
+    }
+
+    /// Compile with the settings of `options`, e.g. to pin the current time, set the inputs
+    /// and change the eviction of a single compile without changing the collection.
+    /// `options.main` overrides `main_source_id`.
+    pub fn compile_with_options<F>(
+        &self,
+        main_source_id: F,
+        options: CompileOptions,
+    ) -> Warned<Result<Document, TypstAsLibError>>
+    where
+        F: Into<FileIdNewType>,
+    {
+        self.compile_options_helper(main_source_id, options, false)
+            .into()
+    }
+
+    /// Compile with the settings of `options` and bypass the document cache, if `skip_cache`
+    /// is set. All compile variants with per-compile settings go through here.
+    fn compile_options_helper<F>(
+        &self,
+        main_source_id: F,
+        options: CompileOptions,
+        skip_cache: bool,
+    ) -> CompileOutput
+    where
+        F: Into<FileIdNewType>,
+    {
+        let CompileOptions {
+            now,
+            main,
+            inputs,
+            files,
+            font_set,
+            comemo_evict_max_age,
+            skip_evict,
+            cancellation,
+        } = options;
+        let main_source_id = main.map_or_else(|| main_source_id.into(), FileIdNewType);
+        let files = (!files.is_empty()).then(|| StaticFileResolver::new(files));
+        self.compile_helper(
+            main_source_id,
+            inputs,
+            CompileOverrides {
+                font_set: font_set.as_ref(),
+                files: files.as_ref(),
+                now,
+                cancellation,
+                comemo_evict_max_age,
+                skip_evict,
+                skip_cache,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Same as `compile`, but the fonts of `font_set` are available in addition to the fonts
+    /// of the collection. They are not visible to any other compile.
+    pub fn compile_with_fonts<F>(
+        &self,
+        main_source_id: F,
+        font_set: &FontSet,
+    ) -> Warned<Result<Document, TypstAsLibError>>
+    where
+        F: Into<FileIdNewType>,
+    {
+        let options = CompileOptions::new().with_font_set(font_set.clone());
+        self.compile_with_options(main_source_id, options)
+    }
+
+    /// Same as `compile_with_input`, but `files` (pairs of virtual path and content) are
+    /// available in addition to the files of the file resolvers, e.g. generated charts for
+    /// `image("/charts/plot.png")`. They shadow files of the resolvers and are not visible to
+    /// any other compile. Compiles with files are not cached by `with_document_cache`.
+    ///
+    /// ```rust,ignore
+    /// let doc = collection
+    ///     .compile_with_input_and_files("/report.typ", inputs, [("/charts/plot.png", png)])
+    ///     .output?;
+    /// ```
+    pub fn compile_with_input_and_files<F, D, IB, P, B>(
+        &self,
+        main_source_id: F,
+        input: D,
+        files: IB,
+    ) -> Warned<Result<Document, TypstAsLibError>>
+    where
+        F: Into<FileIdNewType>,
+        D: Into<Dict>,
+        IB: IntoIterator<Item = (P, B)>,
+        P: Into<FileIdNewType>,
+        B: Into<Bytes>,
+    {
+        let options = CompileOptions::new().with_inputs(input).with_files(files);
+        self.compile_with_options(main_source_id, options)
+    }
+
+    /// Same as `compile_with_input`, but the fonts of `font_set` are available in addition to
+    /// the fonts of the collection. They are not visible to any other compile.
+    pub fn compile_with_input_and_fonts<F, D>(
+        &self,
+        main_source_id: F,
+        input: D,
+        font_set: &FontSet,
+    ) -> Warned<Result<Document, TypstAsLibError>>
+    where
+        F: Into<FileIdNewType>,
+        D: Into<Dict>,
+    {
+        let options = CompileOptions::new()
+            .with_inputs(input)
+            .with_font_set(font_set.clone());
+        self.compile_with_options(main_source_id, options)
+    }
+
+    /// Same as `compile_detailed`, but records the timings of typst (feature `timings`), like
+    /// `typst compile --timings`, e.g. to find the show rules and layouts, that dominate the
+    /// compile time. The document cache is not used.
+    #[cfg(feature = "timings")]
+    pub fn compile_with_profile<F>(&self, main_source_id: F) -> timings::CompileProfile
+    where
+        F: Into<FileIdNewType>,
+    {
+        timings::profile(self, || {
+            self.compile_options_helper(main_source_id, CompileOptions::new(), true)
+        })
+    }
+
+    /// Same as `compile_with_input_detailed`, but records the timings of typst
+    /// (feature `timings`). See `compile_with_profile`.
+    #[cfg(feature = "timings")]
+    pub fn compile_with_input_and_profile<F, D>(
+        &self,
+        main_source_id: F,
+        input: D,
+    ) -> timings::CompileProfile
+    where
+        F: Into<FileIdNewType>,
+        D: Into<Dict>,
+    {
+        timings::profile(self, || {
+            let options = CompileOptions::new().with_inputs(input);
+            self.compile_options_helper(main_source_id, options, true)
+        })
+    }
+
+    /// Compile and export the document as pdf (feature `pdf`).
+    #[cfg(feature = "pdf")]
+    pub fn compile_to_pdf<F>(
+        &self,
+        main_source_id: F,
+        options: &pdf::PdfExportOptions,
+    ) -> Warned<Result<Vec<u8>, TypstAsLibError>>
+    where
+        F: Into<FileIdNewType>,
+    {
+        let Warned { output, warnings } = self.compile(main_source_id);
+        let output = output.and_then(|document| pdf::export_pdf(&document, options));
+        Warned { output, warnings }
+    }
+
+    /// Compile with input and export the document as pdf (feature `pdf`).
+    #[cfg(feature = "pdf")]
+    pub fn compile_with_input_to_pdf<F, D>(
+        &self,
+        main_source_id: F,
+        input: D,
+        options: &pdf::PdfExportOptions,
+    ) -> Warned<Result<Vec<u8>, TypstAsLibError>>
+    where
+        F: Into<FileIdNewType>,
+        D: Into<Dict>,
+    {
+        let Warned { output, warnings } = self.compile_with_input(main_source_id, input);
+        let output = output.and_then(|document| pdf::export_pdf(&document, options));
+        Warned { output, warnings }
+    }
+
+    /// Compile and render each page as png with `ppi` pixels per inch (feature `render`).
+    #[cfg(feature = "render")]
+    pub fn compile_to_png<F>(
+        &self,
+        main_source_id: F,
+        ppi: f32,
+    ) -> Warned<Result<Vec<Vec<u8>>, TypstAsLibError>>
+    where
+        F: Into<FileIdNewType>,
+    {
+        let Warned { output, warnings } = self.compile(main_source_id);
+        let output = output.and_then(|document| render::render_pages(&document, ppi));
+        Warned { output, warnings }
+    }
+
+    /// Compile with input and render each page as png with `ppi` pixels per inch
+    /// (feature `render`).
+    #[cfg(feature = "render")]
+    pub fn compile_with_input_to_png<F, D>(
+        &self,
+        main_source_id: F,
+        input: D,
+        ppi: f32,
+    ) -> Warned<Result<Vec<Vec<u8>>, TypstAsLibError>>
+    where
+        F: Into<FileIdNewType>,
+        D: Into<Dict>,
+    {
+        let Warned { output, warnings } = self.compile_with_input(main_source_id, input);
+        let output = output.and_then(|document| render::render_pages(&document, ppi));
+        Warned { output, warnings }
+    }
+
+    /// Same as `compile_with_input`, but with a json value (e.g. the body of a web request)
+    /// as input. The value has to be an object (feature `json`).
+    #[cfg(feature = "json")]
+    pub fn compile_with_json<F>(
+        &self,
+        main_source_id: F,
+        input: serde_json::Value,
+    ) -> Warned<Result<Document, TypstAsLibError>>
+    where
+        F: Into<FileIdNewType>,
+    {
+        match serialize::to_dict(&input) {
+            Ok(input) => self.compile_with_input(main_source_id, input),
+            Err(err) => Warned {
+                output: Err(err.into()),
+                warnings: Default::default(),
+            },
+        }
+    }
+
+    /// Same as `compile`, but runs on the blocking thread pool of tokio, so that it does not
+    /// block the async runtime (feature `async`). Has to be called inside of a tokio runtime.
+    #[cfg(feature = "async")]
+    pub async fn compile_async<F>(
+        self: &Arc<Self>,
+        main_source_id: F,
+    ) -> Warned<Result<Document, TypstAsLibError>>
+    where
+        F: Into<FileIdNewType>,
+    {
+        let collection = Arc::clone(self);
+        let main_source_id = main_source_id.into();
+        util::spawn_blocking(move || collection.compile(main_source_id)).await
+    }
+
+    /// Same as `compile_with_input`, but runs on the blocking thread pool of tokio, so that it
+    /// does not block the async runtime (feature `async`). Has to be called inside of a tokio
+    /// runtime.
+    #[cfg(feature = "async")]
+    pub async fn compile_with_input_async<F, D>(
+        self: &Arc<Self>,
+        main_source_id: F,
+        input: D,
+    ) -> Warned<Result<Document, TypstAsLibError>>
+    where
+        F: Into<FileIdNewType>,
+        D: Into<Dict>,
+    {
+        let collection = Arc::clone(self);
+        let main_source_id = main_source_id.into();
+        let input = input.into();
+        util::spawn_blocking(move || collection.compile_with_input(main_source_id, input)).await
+    }
+
+    /// Compile the same main source once per input set, e.g. for mail merges. Memoized
+    /// results are kept between the compiles of the batch, so the parts of the template,
+    /// that don't depend on the inputs, are only evaluated and laid out once.
+    pub fn compile_batch<F, I, D>(
+        &self,
+        main_source_id: F,
+        inputs: I,
+    ) -> Vec<Warned<Result<Document, TypstAsLibError>>>
+    where
+        F: Into<FileIdNewType>,
+        I: IntoIterator<Item = D>,
+        D: Into<Dict>,
+    {
+        let FileIdNewType(main_source_id) = main_source_id.into();
+        inputs
+            .into_iter()
+            .map(|input| self.compile_batch_item(main_source_id, input))
+            .collect()
+    }
+
+    /// Same as `compile_batch`, but compiles in parallel on the rayon thread pool
+    /// (feature `rayon`). The results are in the order of the inputs.
+    #[cfg(feature = "rayon")]
+    pub fn compile_batch_parallel<F, I, D>(
+        &self,
+        main_source_id: F,
+        inputs: I,
+    ) -> Vec<Warned<Result<Document, TypstAsLibError>>>
+    where
+        F: Into<FileIdNewType>,
+        I: IntoIterator<Item = D>,
+        D: Into<Dict>,
+    {
+        use rayon::prelude::*;
+
+        let FileIdNewType(main_source_id) = main_source_id.into();
+        let inputs: Vec<Dict> = inputs.into_iter().map(Into::into).collect();
+        inputs
+            .into_par_iter()
+            .map(|input| self.compile_batch_item(main_source_id, input))
+            .collect()
+    }
+
+    /// Start a `CompileSession` for `main_source_id`, which keeps the memoized results of
+    /// typst between compiles until it is told to evict.
+    pub fn session<F>(&self, main_source_id: F) -> CompileSession<'_>
+    where
+        F: Into<FileIdNewType>,
+    {
+        let FileIdNewType(main_source_id) = main_source_id.into();
+        CompileSession::new(self, main_source_id)
+    }
+
+    fn compile_batch_item<D>(
+        &self,
+        main_source_id: FileId,
+        input: D,
+    ) -> Warned<Result<Document, TypstAsLibError>>
+    where
+        D: Into<Dict>,
+    {
+        let overrides = CompileOverrides {
+            min_evict_max_age: 1,
+            ..Default::default()
+        };
+        self.compile_helper(main_source_id, Some(input), overrides)
+            .into()
+    }
+
+    /// Render diagnostics CLI-style, with the annotated source snippets and hints
+    /// (feature `diag`). Spans are resolved against the file resolvers of this collection.
+    /// If `color` is set, the output contains ANSI color codes.
+    #[cfg(feature = "diag")]
+    pub fn format_diagnostics(&self, diagnostics: &[SourceDiagnostic], color: bool) -> String {
+        diag::format_diagnostics(self, diagnostics, color)
+    }
+
+    /// All errors and warnings of a compile as `Diagnostic`s, with the spans resolved
+    /// against the file resolvers of this collection.
+    pub fn diagnostics<T>(&self, output: &Warned<Result<T, TypstAsLibError>>) -> Vec<Diagnostic> {
+        diagnostics::from_warned(self, output)
+    }
+
+    /// Compile with `inputs` and export the document with `export` (e.g. to pdf), or return
+    /// the artifact of an earlier compile from `cache` (feature `sqlite`). Artifacts are keyed
+    /// by `kind`, the key of the compile (`DocumentCache::key`, with the cache key of the
+    /// `DocumentCache` of the collection, if set), the library and fonts of the collection and
+    /// the current date. They are only reused, while all files of the compile still have the
+    /// same content. Use distinct `kind`s for collections, that share a database, but resolve
+    /// different files. The warnings of the compile are dropped.
+    ///
+    /// ```rust,ignore
+    /// let pdf = collection.compile_artifact(&cache, "pdf", "/main.typ", inputs, |document| {
+    ///     typst_pdf::pdf(document, &PdfOptions::default()).map_err(Into::into)
+    /// })?;
+    /// ```
+    #[cfg(feature = "sqlite")]
+    pub fn compile_artifact<F, D, E>(
+        &self,
+        cache: &sqlite_cache::SqliteCache,
+        kind: &str,
+        main_source_id: F,
+        inputs: D,
+        export: E,
+    ) -> Result<Vec<u8>, TypstAsLibError>
+    where
+        F: Into<FileIdNewType>,
+        D: Into<Dict>,
+        E: FnOnce(&Document) -> Result<Vec<u8>, TypstAsLibError>,
+    {
+        let FileIdNewType(main_source_id) = main_source_id.into();
+        sqlite_cache::compile_artifact(self, cache, kind, main_source_id, inputs.into(), export)
+    }
+
+    /// Key of a compile with `inputs` (merged with the default inputs) for the artifacts of
+    /// `compile_artifact`. Unlike the keys of the document cache, it is the same for equal
+    /// collections, so it can be stored.
+    #[cfg(feature = "sqlite")]
+    pub(crate) fn artifact_key(&self, main_source_id: FileId, inputs: Dict) -> u128 {
+        let inputs = match &self.default_inputs {
+            Some(defaults) => merge_dicts(defaults.clone(), inputs),
+            None => inputs,
+        };
+        let key = match &self.document_cache {
+            Some(document_cache) => document_cache.key(main_source_id, Some(&inputs)),
+            None => DocumentCache::default().key(main_source_id, Some(&inputs)),
+        };
+        let now = self.fixed_now.unwrap_or_else(Utc::now);
+        typst::utils::hash128(&(self.fingerprint(now), key))
+    }
+
+    /// Compile each fragment on its own and concatenate the pages into one document.
+    /// Fragments with `Fragment::with_page_offset` get the number of pages of all preceding
+    /// fragments as `sys.page_offset`, so the page numbering can be continued.
+    ///
+    /// Fragments are cached separately, if a `DocumentCache` is set. The cached document of a
+    /// fragment only depends on its own inputs (and its page offset, if it uses one).
+    pub fn compile_fragments<I, F>(&self, fragments: I) -> Warned<Result<Document, TypstAsLibError>>
+    where
+        I: IntoIterator<Item = F>,
+        F: Into<Fragment>,
+    {
+        let mut documents = Vec::new();
+        let mut warnings = EcoVec::new();
+        let mut page_offset = 0;
+        for fragment in fragments {
+            let Fragment {
+                main_source_id,
+                inputs,
+                page_offset: uses_page_offset,
+            } = fragment.into();
+            let overrides = CompileOverrides {
+                page_offset: uses_page_offset.then_some(page_offset),
+                ..Default::default()
+            };
+            let Warned {
+                output,
+                warnings: fragment_warnings,
+            } = self
+                .compile_helper(main_source_id, inputs, overrides)
+                .into();
+            warnings.extend(fragment_warnings);
+            match output {
+                Ok(document) => {
+                    page_offset += document.pages.len();
+                    documents.push(document);
+                }
+                Err(error) => {
+                    return Warned {
+                        output: Err(error),
+                        warnings,
+                    }
+                }
+            }
+        }
+        Warned {
+            output: Ok(concat_documents(documents)),
+            warnings,
+        }
+    }
+
+    fn compile_helper<F, D>(
+        &self,
+        main_source_id: F,
+        inputs: Option<D>,
+        overrides: CompileOverrides<'_>,
+    ) -> CompileOutput
+    where
+        F: Into<FileIdNewType>,
+        D: Into<Dict>,
+    {
+        let FileIdNewType(main_source_id) = main_source_id.into();
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("compile", main = ?main_source_id).entered();
+        let output = self.compile_document(main_source_id, inputs, overrides);
+        #[cfg(feature = "timings")]
+        timings::clear_unless_profiling();
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            ok = output.output.is_ok(),
+            warnings = output.warnings.len(),
+            from_cache = output.stats.from_cache,
+            duration = ?output.stats.duration,
+            "compile finished"
+        );
+        output
+    }
+
+    fn compile_document<D>(
+        &self,
+        main_source_id: FileId,
+        inputs: Option<D>,
+        overrides: CompileOverrides<'_>,
+    ) -> CompileOutput
+    where
+        D: Into<Dict>,
+    {
+        let start = Instant::now();
+        let CompileOverrides {
+            font_set,
+            files,
+            now,
+            cancellation,
+            min_evict_max_age,
+            comemo_evict_max_age,
+            skip_evict,
+            skip_cache,
+            page_offset,
+        } = overrides;
+        if let Err(err) = self.check_in_memory() {
+            return CompileOutput::from_error(err);
+        }
+        let cancellation = match (cancellation, self.timeout) {
+            (Some(cancellation), Some(timeout)) => {
+                Some(cancellation.with_deadline_at_most(start + timeout))
+            }
+            (None, Some(timeout)) => Some(CancellationToken::new().with_timeout(timeout)),
+            (cancellation, None) => cancellation,
+        };
+        let mut inputs: Option<Dict> = inputs.map(Into::into);
+        if let Some(defaults) = &self.default_inputs {
+            inputs = Some(merge_dicts(defaults.clone(), inputs.unwrap_or_default()));
+        }
+        if let Some(schema) = &self.input_schema {
+            // Compiles without inputs are validated as empty inputs, so required fields are
+            // still enforced.
+            let validated = match &inputs {
+                Some(inputs) => schema.validate(inputs),
+                None => schema.validate(&Dict::new()),
+            };
+            if let Err(errors) = validated {
+                return CompileOutput::from_error(TypstAsLibError::InvalidInput(errors));
+            }
+        }
+        let sensitive_values = inputs
+            .as_ref()
+            .map(|inputs| self.sensitive_inputs.values(inputs))
+            .unwrap_or_default();
+        let extra_fonts = match self.extra_fonts(font_set) {
+            Ok(extra_fonts) => extra_fonts,
+            Err(err) => return CompileOutput::from_error(err.into()),
+        };
+        let compile_at = now.is_some();
+        let pinned_now = compile_at || self.fixed_now.is_some();
+        let now = now.or(self.fixed_now).unwrap_or_else(Utc::now);
+        let cache_key = self
+            .document_cache
+            .as_ref()
+            .filter(|_| {
+                // Documents of another time (`compile_at`) would be served for all times.
+                self.inject_now.is_none()
+                    && !compile_at
+                    && !skip_cache
+                    && files.is_none()
+                    && self.overlays.iter().all(OverlayResolver::is_empty)
+            })
+            .map(|cache| {
+                let fonts = extra_fonts.as_ref().map(|(_, fonts)| fonts.as_slice());
+                let key = cache.key_with_fonts(
+                    main_source_id,
+                    inputs.as_ref(),
+                    fonts.unwrap_or_default(),
+                );
+                typst::utils::hash128(&(self.id, self.fingerprint(now), key, page_offset))
+            });
+        if let (Some(cache), Some(key)) = (&self.document_cache, cache_key) {
+            if let Some((document, mut warnings, dependencies, paths)) = cache.get(key) {
+                if let Some(depfile) = &self.depfile {
+                    write_depfile(depfile, &paths, &mut warnings);
+                }
+                return self.apply_warning_policy(CompileOutput {
+                    output: Ok(document),
+                    warnings,
+                    dependencies,
+                    stats: CompileStats {
+                        duration: start.elapsed(),
+                        from_cache: true,
+                    },
+                });
+            }
+        }
+        let world = TypstWorld {
+            collection: self,
+            main_source_id,
+            library: if inputs.is_some() || self.inject_now.is_some() || page_offset.is_some() {
+                let lib = self.create_injected_library(inputs, now, page_offset);
+                match lib {
+                    Ok(lib) => Cow::Owned(lib),
+                    Err(err) => return CompileOutput::from_error(err),
+                }
+            } else {
+                Cow::Borrowed(&self.library)
+            },
+            book: match &extra_fonts {
+                Some((book, _)) => Cow::Borrowed(book),
+                None => Cow::Borrowed(&self.book),
+            },
+            extra_fonts: extra_fonts.as_ref().map_or(&[], |(_, fonts)| fonts),
+            files,
+            now,
+            cancellation: cancellation.clone(),
+            dependencies: Default::default(),
+            package_error: Default::default(),
+            main_source_errors: Default::default(),
+            used_today: Default::default(),
+        };
+        let Warned {
+            mut output,
+            mut warnings,
+        } = typst::compile(&world);
+        // Memoized results, that depend on the cancelled file accesses, are not reused, as
+        // the accesses succeed in the next compile.
+        let cancelled = cancellation.is_some_and(|cancellation| cancellation.is_cancelled());
+        redact_diagnostics(&mut warnings, &sensitive_values);
+        if let Err(errors) = &mut output {
+            redact_diagnostics(errors, &sensitive_values);
+        }
+
+        let comemo_evict_max_age = comemo_evict_max_age.or(self.comemo_evict_max_age);
+        if let (Some(comemo_evict_max_age), false) = (comemo_evict_max_age, skip_evict) {
+            comemo::evict(comemo_evict_max_age.max(min_evict_max_age));
+        }
+        if let Some(font_max_idle) = self.font_max_idle {
+            self.evict_idle_fonts(font_max_idle);
+        }
+
+        // Documents, that show the date of the system clock, would be outdated the next day.
+        let uses_clock = !pinned_now && world.used_today.load(Ordering::Relaxed);
+        if let (Some(cache), Some(key), Ok(document), false, false) = (
+            &self.document_cache,
+            cache_key,
+            &output,
+            cancelled,
+            uses_clock,
+        ) {
+            cache.insert(
+                key,
+                (
+                    document.clone(),
+                    warnings.clone(),
+                    world.dependencies(),
+                    world.dependency_paths(),
+                ),
+            );
+        }
+
+        let mut output = output.map_err(|errors| {
+            let package_error = world.package_error.lock().ok().and_then(|mut e| e.take());
+            let main_source_errors = world
+                .main_source_errors
+                .lock()
+                .ok()
+                .and_then(|mut e| e.take());
+            match (package_error, main_source_errors) {
+                (Some(package_error), _) => package_error,
+                (None, Some(main_source_errors)) => TypstAsLibError::Unresolved(main_source_errors),
+                (None, None) => errors.into(),
+            }
+        });
+        if cancelled {
+            output = Err(TypstAsLibError::Cancelled);
+        }
+        if let (Some(depfile), false) = (&self.depfile, cancelled) {
+            write_depfile(depfile, &world.dependency_paths(), &mut warnings);
+        }
+
+        let duration = start.elapsed();
+        self.compile_counter.record(duration);
+        self.apply_warning_policy(CompileOutput {
+            output,
+            warnings,
+            dependencies: world.dependencies(),
+            stats: CompileStats {
+                duration,
+                from_cache: false,
+            },
+        })
+    }
+
+    fn apply_warning_policy(&self, mut output: CompileOutput) -> CompileOutput {
+        if let Some(policy) = &self.warning_policy {
+            policy.apply(&mut output.output, &mut output.warnings);
+        }
+        output
+    }
+
+    fn create_injected_library(
+        &self,
+        input: Option<Dict>,
+        now: DateTime<Utc>,
+        page_offset: Option<usize>,
+    ) -> Result<LazyHash<Library>, TypstAsLibError> {
+        let Self {
+            inject_location,
+            library,
+            inject_now,
+            ..
+        } = self;
+        let mut lib = library.deref().clone();
+        if let Some(input) = input {
+            inject_input_into_library(&mut lib, inject_location.as_ref(), input)?;
+        }
+        let module_name = inject_location
+            .as_ref()
+            .map_or("sys", |location| location.module_name.as_str());
+        if let Some(page_offset) = page_offset {
+            define_in_module(&mut lib, module_name, PAGE_OFFSET_KEY, page_offset as i64)?;
+        }
+        if let Some(value_name) = inject_now {
+            let now = datetime_from_chrono(self.local_now(now)).ok_or_else(|| {
+                FileError::Other(Some(eco_format!("current time is out of range")))
+            })?;
+            define_in_module(&mut lib, module_name, value_name, now)?;
+        }
+        Ok(LazyHash::new(lib))
+    }
+
+    /// Resolve the file `file_id` with the file resolvers of this collection, like typst does
+    /// during a compile. If no resolver has the file, the errors of all resolvers are returned.
+    pub fn resolve_file<F>(&self, file_id: F) -> Result<Cow<'_, Bytes>, ResolverErrors>
+    where
+        F: Into<FileIdNewType>,
+    {
+        let FileIdNewType(file_id) = file_id.into();
+        self.resolve_with(file_id, |file_resolver| {
+            file_resolver.resolve_binary(file_id)
+        })
+        .map(|(file, _)| file)
+    }
+
+    /// Same as `resolve_file`, but for typst sources.
+    pub fn resolve_source<F>(&self, file_id: F) -> Result<Cow<'_, Source>, ResolverErrors>
+    where
+        F: Into<FileIdNewType>,
+    {
+        let FileIdNewType(file_id) = file_id.into();
+        self.resolve_with(file_id, |file_resolver| {
+            file_resolver.resolve_source(file_id)
+        })
+        .map(|(source, _)| source)
+    }
+
+    /// Whether the access policy allows `file_id`, see `with_access_policy`.
+    fn is_allowed(&self, file_id: FileId) -> bool {
+        self.access_policy
+            .as_ref()
+            .map_or(true, |policy| policy(file_id))
+    }
+
+    /// Resolve `file_id` with the first file resolver, that has it. Returns the file together
+    /// with the resolver, that served it.
+    fn resolve_with<'a, T, R>(
+        &'a self,
+        file_id: FileId,
+        resolve: R,
+    ) -> Result<(T, &'a BoxedFileResolver), ResolverErrors>
+    where
+        R: Fn(&'a BoxedFileResolver) -> FileResult<T>,
+    {
+        let mut errors = ResolverErrors::new(file_id);
+        if !self.is_allowed(file_id) {
+            errors.push("AccessPolicy", FileError::AccessDenied, None);
+            return Err(errors);
+        }
+        for file_resolver in self.file_resolvers.iter() {
+            #[cfg(feature = "tracing")]
+            let start = Instant::now();
+            let result = resolve(file_resolver);
+            #[cfg(feature = "tracing")]
+            trace_resolution(file_resolver.name(), file_id, result.is_ok(), start);
+            match result {
+                Ok(file) => return Ok((file, file_resolver)),
+                Err(error) => {
+                    let package_failure = file_resolver.package_failure(file_id);
+                    errors.push(file_resolver.name(), error, package_failure);
+                }
+            }
+        }
+        Err(errors)
+    }
+}
+
+/// Remove the documents of changed templates from `document_cache`.
+fn watch_template_store(store: &dyn TemplateStore, document_cache: &DocumentCache) {
+    let document_cache = document_cache.clone();
+    // Stores, that can't notify about changes, are not watched.
+    let _ = store.watch(Box::new(move |TemplateInfo { name, version }| {
+        document_cache.invalidate(template_file_id(name, Some(version)));
+        document_cache.invalidate(template_file_id(name, None));
+    }));
+}
+
+/// Write the depfile after a compile. A failed write doesn't fail the compile, it is
+/// reported as warning.
+fn write_depfile(depfile: &Depfile, paths: &[PathBuf], warnings: &mut EcoVec<SourceDiagnostic>) {
+    if let Err(error) = depfile.write(paths) {
+        warnings.push(SourceDiagnostic::warning(
+            Span::detached(),
+            eco_format!(
+                "Could not write depfile {}: {error}",
+                depfile.path.display()
+            ),
+        ));
+    }
+}
+
+#[cfg(feature = "tracing")]
+fn trace_resolution(resolver: &str, file_id: FileId, hit: bool, start: Instant) {
+    tracing::trace!(
+        resolver = %short_type_name(resolver),
+        file = ?file_id,
+        hit,
+        duration = ?start.elapsed(),
+        "resolve file"
+    );
+}
+
+fn inject_input_into_library<'a, D>(
+    library: &'a mut Library,
+    inject_location: Option<&InjectLocation>,
+    input: D,
+) -> Result<&'a mut Library, TypstAsLibError>
+where
+    D: Into<Dict>,
+{
+    let (module_name, value_name) = if let Some(InjectLocation {
+        module_name,
+        value_name,
+    }) = inject_location
+    {
+        (module_name.as_str(), value_name.as_str())
+    } else {
+        ("sys", "inputs")
+    };
+    define_in_module(library, module_name, value_name, input.into())?;
+    Ok(library)
+}
+
+/// Define `value_name` in the global module `module_name`, which is created, if it does not
+/// exist. Other values of the module are kept.
+fn define_in_module<V>(
+    library: &mut Library,
+    module_name: &str,
+    value_name: &str,
+    value: V,
+) -> Result<(), TypstAsLibError>
+where
+    V: IntoValue,
+{
+    let global = library.global.scope_mut();
+    match global.get_mut(module_name).transpose()? {
+        Some(Value::Module(module)) => define_in_scope(module.scope_mut(), value_name, value)?,
+        existing => {
+            let mut scope = Scope::new();
+            scope.define(value_name, value);
+            let module = Value::Module(Module::new(module_name, scope));
+            match existing {
+                Some(existing) => *existing = module,
+                None => global.define(module_name, module),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Define `name` in `scope` or replace the existing value.
+fn define_in_scope<V>(scope: &mut Scope, name: &str, value: V) -> Result<(), TypstAsLibError>
+where
+    V: IntoValue,
+{
+    match scope.get_mut(name).transpose()? {
+        Some(existing) => *existing = value.into_value(),
+        None => scope.define(name, value),
+    }
+    Ok(())
+}
+
+pub struct TypstTemplate {
+    source_id: FileId,
+    collection: TypstTemplateCollection,
+}
+
+impl TypstTemplate {
+    /// Initialize with fonts and a source file.
+    ///
+    /// `source` can be of types:
+    ///   - `&str/String`, creating a detached Source (Has vpath `/main.typ`)
+    ///   - `(&str, &str/String)`, where &str is the absolute
+    ///     virtual path of the Source file.
+    ///   - `(typst::syntax::FileId, &str/String)`
+    ///   - `typst::syntax::Source`
+    ///
+    /// (`&str/String` is always the template file content)
+    ///
+    /// Example:
+    /// ```rust,ignore
+    /// static TEMPLATE: &str = include_str!("./templates/template.typ");
+    /// static FONT: &[u8] = include_bytes!("./fonts/texgyrecursor-regular.otf");
+    /// // ...
+    /// let font = Font::new(Bytes::from(FONT), 0).expect("Could not parse font!");
+    /// let template = TypstTemplate::new(vec![font], TEMPLATE);
+    /// ```
+    pub fn new<V, S>(fonts: V, source_id: S) -> Self
+    where
+        V: Into<Vec<Font>>,
+        S: Into<SourceNewType>,
+    {
+        let SourceNewType(source) = source_id.into();
+        let source_id = source.id();
+        let mut collection = TypstTemplateCollection::new(fonts);
+        collection.file_resolvers.push(
+            Box::new(MainSourceFileResolver::new(source)),
+            DEFAULT_RESOLVER_PRIORITY,
+        );
+        Self {
+            collection,
+            source_id,
+        }
+    }
+
+    /// Setup, that behaves like `typst compile` in the current directory. See
+    /// `TypstTemplateCollection::with_defaults`.
+    pub fn with_defaults<S>(source_id: S) -> Self
+    where
+        S: Into<SourceNewType>,
+    {
+        let mut template = Self::new(Vec::new(), source_id);
+        template.collection.add_defaults_mut();
+        template
+    }
+
+    pub fn comemo_evict_max_age(&mut self, comemo_evict_max_age: Option<usize>) -> &mut Self {
+        self.collection.comemo_evict_max_age = comemo_evict_max_age;
+        self
+    }
+
+    /// Cache compiled documents in memory. Documents are cached by the main source `FileId`,
+    /// the inputs, the library, the fonts and the current date (see `DocumentCache`). Use
+    /// `DocumentCache::with_cache_key` to ignore volatile inputs.
+    pub fn with_document_cache(mut self, document_cache: DocumentCache) -> Self {
+        self.collection.with_document_cache_mut(document_cache);
+        self
+    }
+
+    /// Use other typst location for injected inputs
+    /// (instead of`#import sys: inputs`, where `sys` is the `module_name`
+    /// and `inputs` is the `value_name`).
+    pub fn custom_inject_location<S>(mut self, module_name: S, value_name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.collection
+            .custom_inject_location_mut(module_name, value_name);
+        self
+    }
+
+    /// Use `library` instead of `Library::default()`.
+    /// See `TypstTemplateCollection::with_library`.
+    pub fn with_library(mut self, library: Library) -> Self {
+        self.collection.with_library_mut(library);
+        self
+    }
+
+    /// Change the typst standard library, that is used for all compiles.
+    /// See `TypstTemplateCollection::modify_library`.
+    pub fn modify_library<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut Library),
+    {
+        self.collection.modify_library_mut(f);
+        self
+    }
+
+    /// Default page size, margins and text styles for all documents.
+    /// See `TypstTemplateCollection::with_style_defaults`.
+    pub fn with_style_defaults(mut self, defaults: StyleDefaults) -> Self {
+        self.collection.with_style_defaults_mut(defaults);
+        self
+    }
+
+    /// Define `value` as `name` in the global scope, so templates can use `#name` without an
+    /// import. See `TypstTemplateCollection::with_global`.
+    pub fn with_global<N, V>(mut self, name: N, value: V) -> Result<Self, TypstAsLibError>
+    where
+        N: AsRef<str>,
+        V: IntoValue,
+    {
+        self.collection.with_global_mut(name, value)?;
+        Ok(self)
+    }
+
+    /// Inject `value` as `value_name` into the module `module_name` for all compiles.
+    /// See `TypstTemplateCollection::with_injected_module`.
+    pub fn with_injected_module<M, N, V>(
+        mut self,
+        module_name: M,
+        value_name: N,
+        value: V,
+    ) -> Result<Self, TypstAsLibError>
+    where
+        M: AsRef<str>,
+        N: AsRef<str>,
+        V: IntoValue,
+    {
+        self.collection
+            .with_injected_module_mut(module_name, value_name, value)?;
+        Ok(self)
+    }
+
+    /// Validate the inputs of `compile_with_input` against `schema` before compiling.
+    /// Returns `TypstAsLibError::InvalidInput` with all errors, if the inputs do not match.
+    /// Compiles without inputs (`compile`) are validated as empty inputs.
+    pub fn with_input_schema(mut self, schema: InputSchema) -> Self {
+        self.collection.with_input_schema_mut(schema);
+        self
+    }
+
+    /// Default values for the inputs (e.g. branding colors, company address), that are deep
+    /// merged with the inputs of each compile. Values of the compile inputs take precedence.
+    pub fn with_default_inputs<D>(mut self, defaults: D) -> Self
+    where
+        D: Into<Dict>,
+    {
+        self.collection.with_default_inputs_mut(defaults);
+        self
+    }
+
+    /// Mark input keys as sensitive. Their values are replaced with `[redacted]` in the
+    /// diagnostics and errors of each compile. Nested keys are written as dotted paths
+    /// (`customer.iban`). If the value is a dictionary or array, everything inside is redacted.
+    /// Booleans, `none`, `auto` and values with less than 3 characters are not redacted,
+    /// because they would also replace unrelated text.
+    pub fn with_sensitive_inputs<I, S>(mut self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<EcoString>,
+    {
+        self.collection.with_sensitive_inputs_mut(keys);
+        self
+    }
+
+    /// Add Fonts
+    pub fn add_fonts<I, F>(mut self, fonts: I) -> Self
+    where
+        I: IntoIterator<Item = F>,
+        F: Into<Font>,
+    {
+        self.collection.add_fonts_mut(fonts);
+        self
+    }
+
+    /// Add fonts, that are only parsed, when they are used
+    /// (e.g. `FontSlot::from_path` or `FontSlot::from_bytes`).
+    pub fn add_font_slots<I>(mut self, slots: I) -> Self
+    where
+        I: IntoIterator<Item = FontSlot>,
+    {
+        self.collection.add_font_slots_mut(slots);
+        self
+    }
+
+    /// Remove the fonts, for which `predicate` returns `true`. Returns the number of removed
+    /// fonts. See `TypstTemplateCollection::remove_fonts`.
+    pub fn remove_fonts<P>(&mut self, predicate: P) -> usize
+    where
+        P: FnMut(&FontInfo) -> bool,
+    {
+        self.collection.remove_fonts(predicate)
+    }
+
+    /// Remove all fonts (including the embedded fonts of feature `embed-fonts`).
+    pub fn clear_fonts(&mut self) {
+        self.collection.clear_fonts()
+    }
+
+    /// Build the `FontBook` again from the fonts of the template.
+    pub fn rebuild_font_book(&mut self) {
+        self.collection.rebuild_font_book()
+    }
+
+    /// Add all fonts in the directory `path` and its subdirectories.
+    /// See `TypstTemplateCollection::with_fonts_dir`.
+    pub fn with_fonts_dir<P>(mut self, path: P) -> Result<Self, TypstAsLibError>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        self.collection.with_fonts_dir_mut(path)?;
+        Ok(self)
+    }
+
+    /// Add the fonts, that are installed on the system (feature `system-fonts`).
+    /// They are only parsed, when they are used.
+    #[cfg(feature = "system-fonts")]
+    pub fn with_system_fonts(mut self) -> Self {
+        self.collection.with_system_fonts_mut();
+        self
+    }
+
+    /// After each compile, drop the parsed data of fonts, that were not used for `max_idle`.
+    /// Fonts, that were added as `Font`, are never dropped.
+    pub fn with_font_eviction(mut self, max_idle: Duration) -> Self {
+        self.collection.with_font_eviction_mut(max_idle);
+        self
+    }
+
+    /// Drop the parsed data of fonts, that were not used for `max_idle`.
+    /// Returns the number of dropped fonts.
+    pub fn evict_idle_fonts(&self, max_idle: Duration) -> usize {
+        self.collection.evict_idle_fonts(max_idle)
+    }
+
+    /// Remove the file `id` from the caches of all file resolvers, so it is resolved again
+    /// in the next compile.
+    pub fn invalidate_file<F>(&self, id: F)
+    where
+        F: Into<FileIdNewType>,
+    {
+        self.collection.invalidate_file(id)
+    }
+
+    /// Remove all files from the caches of all file resolvers.
+    pub fn clear_file_caches(&self) {
+        self.collection.clear_file_caches()
+    }
+
+    /// Cache statistics of each file resolver, the number of package downloads, the number of
+    /// compiles and the duration of the last compile.
+    pub fn metrics(&self) -> Metrics {
+        self.collection.metrics()
+    }
+
+    /// Replace the text of the template source, e.g. on each keystroke of a live preview.
+    /// See `TypstTemplateCollection::update_source`.
+    pub fn update_main_source(&mut self, new_text: &str) -> Range<usize> {
+        self.collection.update_source(self.source_id, new_text)
+    }
+
+    /// Replace the text of the source `id`. See `TypstTemplateCollection::update_source`.
+    pub fn update_source<F>(&mut self, id: F, new_text: &str) -> Range<usize>
+    where
+        F: Into<FileIdNewType>,
+    {
+        self.collection.update_source(id, new_text)
+    }
+
+    /// Replace the byte range `replace` of the source `id` with `with`.
+    /// See `TypstTemplateCollection::edit_source`.
+    pub fn edit_source<F>(
+        &mut self,
+        id: F,
+        replace: Range<usize>,
+        with: &str,
+    ) -> Result<Range<usize>, TypstAsLibError>
+    where
+        F: Into<FileIdNewType>,
+    {
+        self.collection.edit_source(id, replace, with)
+    }
+
+    /// Returns `true`, if `id` was updated. Afterwards `id` is resolved by the file resolvers
+    /// again.
+    pub fn reset_source<F>(&mut self, id: F) -> bool
+    where
+        F: Into<FileIdNewType>,
+    {
+        self.collection.reset_source(id)
+    }
+
+    /// Check, without compiling, that all files and packages, that the template imports,
+    /// includes or reads, can be resolved. See `TypstTemplateCollection::check_resolvable`.
+    pub fn check_resolvable(&self) -> Result<Vec<FileId>, Vec<MissingFile>> {
+        self.collection.check_resolvable(self.source_id)
+    }
+
+    /// Return the syntax errors of the template and the sources it imports or includes,
+    /// without compiling. See `TypstTemplateCollection::validate`.
+    pub fn validate(&self) -> Result<(), TypstAsLibError> {
+        self.collection.validate(self.source_id)
+    }
+
+    /// Remove the documents, that depend on the file `id`, from the `DocumentCache`.
+    /// See `TypstTemplateCollection::invalidate_documents`.
+    pub fn invalidate_documents<F>(&self, id: F) -> usize
+    where
+        F: Into<FileIdNewType>,
+    {
+        self.collection.invalidate_documents(id)
+    }
+
+    /// Add file resolver, that implements the `FileResolver`` trait to a vec of file resolvers.
+    /// When a `FileId`` needs to be resolved by Typst, the vec will be iterated over until
+    /// one file resolver returns a file.
+    pub fn add_file_resolver<F>(mut self, file_resolver: F) -> Self
+    where
+        F: FileResolver + Send + Sync + 'static,
+    {
+        self.collection.add_file_resolver_mut(file_resolver);
+        self
+    }
+
+    /// See `TypstTemplateCollection::register_file_resolver`.
+    pub fn register_file_resolver<F>(&mut self, file_resolver: F, priority: i32) -> ResolverHandle
+    where
+        F: FileResolver + Send + Sync + 'static,
+    {
+        self.collection
+            .register_file_resolver(file_resolver, priority)
+    }
+
+    /// See `TypstTemplateCollection::remove_file_resolver`.
+    pub fn remove_file_resolver(&mut self, handle: ResolverHandle) -> bool {
+        self.collection.remove_file_resolver(handle)
+    }
+
+    /// See `TypstTemplateCollection::replace_file_resolver`.
+    pub fn replace_file_resolver<F>(&mut self, handle: ResolverHandle, file_resolver: F) -> bool
+    where
+        F: FileResolver + Send + Sync + 'static,
+    {
+        self.collection.replace_file_resolver(handle, file_resolver)
+    }
+
+    /// See `TypstTemplateCollection::set_file_resolver_priority`.
+    pub fn set_file_resolver_priority(&mut self, handle: ResolverHandle, priority: i32) -> bool {
+        self.collection.set_file_resolver_priority(handle, priority)
+    }
+
+    /// See `TypstTemplateCollection::file_resolver_priority`.
+    pub fn file_resolver_priority(&self, handle: ResolverHandle) -> Option<i32> {
+        self.collection.file_resolver_priority(handle)
+    }
+
+    /// Adds the `StaticFileResolver` to the file resolvers. It creates `HashMap`s for sources.
+    ///
+    /// `sources` The item of the IntoIterator can be of types:
+    ///   - `&str/String`, creating a detached Source (Has vpath `/main.typ`)
+    ///   - `(&str, &str/String)`, where &str is the absolute
+    ///     virtual path of the Source file.
+    ///   - `(typst::syntax::FileId, &str/String)`
+    ///   - `typst::syntax::Source`
+    ///
+    /// (`&str/String` is always the template file content)
+    pub fn with_static_source_file_resolver<IS, S>(mut self, sources: IS) -> Self
+    where
+        IS: IntoIterator<Item = S>,
+        S: Into<SourceNewType>,
+    {
+        self.collection
+            .with_static_source_file_resolver_mut(sources);
+        self
+    }
+
+    /// Adds the `StaticFileResolver` to the file resolvers. It creates `HashMap`s for binaries.
+    pub fn with_static_file_resolver<IB, F, B>(mut self, binaries: IB) -> Self
+    where
+        IB: IntoIterator<Item = (F, B)>,
+        F: Into<FileIdNewType>,
+        B: Into<Bytes>,
+    {
+        self.collection.with_static_file_resolver_mut(binaries);
+        self
+    }
+
+    /// Adds the fonts of the `SharedRegistry` and the registry itself to the file resolvers.
+    /// Use `SharedRegistry::global()` to share fonts and static files between all collections
+    /// of the process.
+    pub fn with_shared_registry(mut self, registry: SharedRegistry) -> Self {
+        self.collection.with_shared_registry_mut(registry);
+        self
+    }
+
+    /// Inject the `dates` module with helper functions for date arithmetic and formatting.
+    /// See `date_helpers` for the available functions.
+    pub fn with_date_helpers(mut self) -> Result<Self, TypstAsLibError> {
+        self.collection.with_date_helpers_mut()?;
+        Ok(self)
+    }
+
+    /// Cancel compiles, that take longer than `timeout`. They return
+    /// `TypstAsLibError::Cancelled`. This is not a watchdog: the timeout is only checked, when
+    /// the compile accesses a file, a font or the date, so e.g. an endless loop in a show rule
+    /// is not stopped (see `CancellationToken`).
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.collection.with_timeout_mut(timeout);
+        self
+    }
+
+    /// Files, for which `policy` returns `false`, are denied with `FileError::AccessDenied`.
+    /// See `TypstTemplateCollection::with_access_policy`.
+    pub fn with_access_policy<P>(mut self, policy: P) -> Self
+    where
+        P: Fn(FileId) -> bool + Send + Sync + 'static,
+    {
+        self.collection.with_access_policy_mut(policy);
+        self
+    }
+
+    /// Suppress warnings or fail compiles on warnings, see `WarningPolicy`.
+    pub fn with_warning_policy(mut self, policy: WarningPolicy) -> Self {
+        self.collection.with_warning_policy_mut(policy);
+        self
+    }
+
+    /// Use `now` as current time in all compiles (`datetime.today()`) instead of the system
+    /// time, e.g. for reproducible builds and golden tests.
+    pub fn with_fixed_now(mut self, now: DateTime<Utc>) -> Self {
+        self.collection.with_fixed_now_mut(now);
+        self
+    }
+
+    /// Use the local time of `timezone` for `datetime.today()` (without offset) and for the
+    /// value of `with_injected_now`, instead of UTC.
+    /// See `TypstTemplateCollection::with_timezone`.
+    #[cfg(feature = "timezone")]
+    pub fn with_timezone(mut self, timezone: chrono_tz::Tz) -> Result<Self, TypstAsLibError> {
+        self.collection.with_timezone_mut(timezone)?;
+        Ok(self)
+    }
+
+    /// Inject the current time as `datetime` with date and time components as `value_name`.
+    /// See `TypstTemplateCollection::with_injected_now`.
+    pub fn with_injected_now<S>(mut self, value_name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.collection.with_injected_now_mut(value_name);
+        self
+    }
+
+    /// Define a typst function `name` in the global scope, that calls the Rust closure `f`.
+    /// See `TypstTemplateCollection::with_function`.
+    pub fn with_function<S, F>(mut self, name: S, f: F) -> Result<Self, TypstAsLibError>
+    where
+        S: Into<String>,
+        F: Fn(&mut Args) -> SourceResult<Value> + Send + Sync + 'static,
+    {
+        self.collection.with_function_mut(name, f)?;
+        Ok(self)
+    }
+
+    /// Define a typst function `name` in the module `module` (`#module.name(..)`), that calls
+    /// the Rust closure `f`. See `TypstTemplateCollection::with_module_function`.
+    pub fn with_module_function<M, S, F>(
+        mut self,
+        module: M,
+        name: S,
+        f: F,
+    ) -> Result<Self, TypstAsLibError>
+    where
+        M: Into<String>,
+        S: Into<String>,
+        F: Fn(&mut Args) -> SourceResult<Value> + Send + Sync + 'static,
+    {
+        self.collection.with_module_function_mut(module, name, f)?;
+        Ok(self)
+    }
+
+    /// Adds the `TemplateStore` to the file resolvers, e.g. for templates, that are imported
+    /// by the main source. Changed templates are removed from the `DocumentCache`, if the
+    /// store supports `TemplateStore::watch`.
+    pub fn with_template_store<S>(mut self, store: S) -> Self
+    where
+        S: TemplateStore + 'static,
+    {
+        self.collection.with_template_store_mut(store);
+        self
+    }
+
+    /// See `TypstTemplateCollection::with_overlay_resolver`.
+    pub fn with_overlay_resolver(mut self, overlay: OverlayResolver) -> Self {
+        self.collection.with_overlay_resolver_mut(overlay);
+        self
+    }
+
+    /// See `TypstTemplateCollection::with_data_file`.
+    #[cfg(feature = "json")]
+    pub fn with_data_file<P, T>(mut self, path: P, data: &T) -> Result<Self, TypstAsLibError>
+    where
+        P: Into<FileIdNewType>,
+        T: serde::Serialize + ?Sized,
+    {
+        self.collection.with_data_file_mut(path, data)?;
+        Ok(self)
+    }
+
+    /// See `TypstTemplateCollection::with_csv_file`.
+    pub fn with_csv_file<P, I, R, S>(mut self, path: P, rows: I) -> Self
+    where
+        P: Into<FileIdNewType>,
+        I: IntoIterator<Item = R>,
+        R: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.collection.with_csv_file_mut(path, rows);
+        self
+    }
+
+    /// After each compile, write a Make/Ninja style depfile to `path`, that lists the files
+    /// on disk, that were read during the compile, as dependencies of `target`. Compiles
+    /// served by the document cache write the depfile as well. A failed write is reported as
+    /// warning.
+    pub fn with_depfile<P, T>(mut self, path: P, target: T) -> Self
+    where
+        P: Into<PathBuf>,
+        T: Into<String>,
+    {
+        self.collection.with_depfile_mut(path, target);
+        self
+    }
+
+    /// Guarantee, that compiles never touch the file system or network. See
+    /// `TypstTemplateCollection::with_in_memory_only`.
+    pub fn with_in_memory_only(mut self) -> Result<Self, TypstAsLibError> {
+        self.collection.with_in_memory_only_mut()?;
+        Ok(self)
+    }
+
+    /// Err, if something, that is not in memory, was refused after `with_in_memory_only`.
+    /// See `TypstTemplateCollection::check_in_memory`.
+    pub fn check_in_memory(&self) -> Result<(), TypstAsLibError> {
+        self.collection.check_in_memory()
+    }
+
+    /// Add a package, that only exists in memory, so templates can import it without
+    /// downloading it (e.g. `#import "@mycompany/branding:1.0.0"`). Virtual packages are
+    /// resolved before all other file resolvers. See `VirtualPackages::add_package`.
+    pub fn with_virtual_package<I, P, B>(mut self, spec: PackageSpec, files: I) -> Self
+    where
+        I: IntoIterator<Item = (P, B)>,
+        P: AsRef<std::path::Path>,
+        B: AsRef<[u8]>,
+    {
+        self.collection.with_virtual_package_mut(spec, files);
+        self
+    }
+
+    /// Adds `FileSystemFileResolver` to the file resolvers, a resolver that can resolve
+    /// local files (when `package` is not set in `FileId`).
+    pub fn with_file_system_resolver<P>(mut self, root: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.collection.with_file_system_resolver_mut(root);
+        self
+    }
+
+    /// Adds `LocalPackagesResolver` to the file resolvers, a resolver that reads packages from
+    /// the local package directories like the typst cli (e.g. `@local/...` imports), without
+    /// network access.
+    pub fn with_local_packages_resolver(mut self) -> Self {
+        self.collection.with_local_packages_resolver_mut();
+        self
+    }
+
+    /// Adds a cached `HttpFileResolver` to the file resolvers, a resolver that downloads
+    /// local files (when `package` is not set in `FileId`) relative to `base_url`
+    /// (feature `http`).
+    #[cfg(feature = "http")]
+    pub fn with_http_resolver<S>(mut self, base_url: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.collection.with_http_resolver_mut(base_url);
+        self
+    }
+
+    #[cfg(feature = "packages")]
+    /// Adds `PackageResolver` to the file resolvers.
+    /// When `package` is set in `FileId`, it will download the package from the typst package
+    /// repository. It caches the results into `cache` (which is either in memory or cache folder (default)).
+    /// Example
+    /// ```rust,ignore
+    ///     let template = TypstTemplate::new(vec![font], TEMPLATE_FILE)
+    ///         .with_package_file_resolver(None);
+    /// ```
+    pub fn with_package_file_resolver(mut self, ureq: Option<ureq::Agent>) -> Self {
+        self.collection.with_package_file_resolver_mut(ureq);
+        self
+    }
+
+    #[cfg(feature = "packages")]
+    /// Same as `with_package_file_resolver`, but caches the packages in `cache_dir`
+    /// instead of the default cache folder.
+    pub fn with_package_file_resolver_cache_dir<P>(
+        mut self,
+        cache_dir: P,
+        ureq: Option<ureq::Agent>,
+    ) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.collection
+            .with_package_file_resolver_cache_dir_mut(cache_dir, ureq);
+        self
+    }
+
+    /// Call `typst::compile()` with our template and a `Dict` as input, that will be availible
+    /// in a typst script with `#import sys: inputs`.
+    pub fn compile_with_input<D>(&self, inputs: D) -> Warned<Result<Document, TypstAsLibError>>
+    where
+        D: Into<Dict>,
+    {
+        let Self {
+            source_id,
+            collection,
+            ..
+        } = self;
+        collection.compile_with_input(*source_id, inputs)
+    }
+
+    /// Same as `compile_with_input`, but compiles `main` instead of the template source, e.g.
+    /// `/letter.typ` or `/invoice.typ` served by one of the file resolvers. Resolvers, fonts
+    /// and other settings of the template are used as usual.
+    ///
+    /// ```rust,ignore
+    /// let template = TypstTemplate::new(vec![font], INVOICE).with_file_system_resolver("./templates");
+    /// let letter = template.compile_with_main("/letter.typ", inputs);
+    /// ```
+    pub fn compile_with_main<F, D>(
+        &self,
+        main: F,
+        inputs: D,
+    ) -> Warned<Result<Document, TypstAsLibError>>
+    where
+        F: Into<FileIdNewType>,
+        D: Into<Dict>,
+    {
+        self.collection.compile_with_input(main, inputs)
+    }
+
+    /// Compile with the settings of `options`, e.g. to pin the current time, set the inputs
+    /// or compile another main file. See `TypstTemplateCollection::compile_with_options`.
+    pub fn compile_with_options(
+        &self,
+        options: CompileOptions,
+    ) -> Warned<Result<Document, TypstAsLibError>> {
+        self.collection
+            .compile_with_options(self.source_id, options)
+    }
+
+    /// Call `typst::compile()` with our template and a `Dict` as input, that will be availible
+    /// in a typst script with `#import sys: inputs`. Mutates the library each call.
+    ///
+    /// Example:
+    ///
+    /// ```rust,ignore
+    /// static TEMPLATE: &str = include_str!("./templates/template.typ");
+    /// static FONT: &[u8] = include_bytes!("./fonts/texgyrecursor-regular.otf");
+    /// static TEMPLATE_ID: &str = "/template.typ";
+    /// // ...
+    /// let font = Font::new(Bytes::from(FONT), 0).expect("Could not parse font!");
+    /// let template = TypstTemplate::new(vec![font], TEMPLATE);
+    /// // Struct that implements Into<Dict>.
+    /// let inputs = todo!();
+    /// let tracer = Default::default();
+    /// let doc = template.compile_with_input_fast(&mut tracer, TEMPLATE_ID, inputs)
+    ///     .expect("Typst error!");
+    /// ```
+    #[deprecated(
+        since = "0.11.1",
+        note = "Use TypstTemplate::compile_with_input() instead!"
+    )]
+    pub fn compile_with_input_fast<D>(
+        &mut self,
+        input: D,
+    ) -> Warned<Result<Document, TypstAsLibError>>
+    where
+        D: Into<Dict>,
+    {
+        let Self {
+            source_id,
+            collection,
+            ..
+        } = self;
+        #[allow(deprecated)]
+        collection.compile_with_input_fast(*source_id, input)
+    }
+
+    /// Just call `typst::compile()`
+    pub fn compile(&self) -> Warned<Result<Document, TypstAsLibError>> {
+        let Self {
+            source_id,
+            collection,
+            ..
+        } = self;
+        collection.compile(*source_id)
+    }
+
+    /// Same as `compile`, but converts the document into the target `T`. See
+    /// `document_target`.
+    pub fn compile_as<T>(&self) -> Warned<Result<T, TypstAsLibError>>
+    where
+        T: DocumentTarget,
+    {
+        let Self {
+            source_id,
+            collection,
+            ..
+        } = self;
+        collection.compile_as(*source_id)
+    }
+
+    /// Same as `compile_with_input`, but converts the document into the target `T`. See
+    /// `document_target`.
+    pub fn compile_with_input_as<T, D>(&self, inputs: D) -> Warned<Result<T, TypstAsLibError>>
+    where
+        T: DocumentTarget,
+        D: Into<Dict>,
+    {
+        let Self {
+            source_id,
+            collection,
+            ..
+        } = self;
+        collection.compile_with_input_as(*source_id, inputs)
+    }
+
+    /// Same as `compile`, but with `now` as current time (`datetime.today()`).
+    /// Overrides `with_fixed_now`. Not cached by `with_document_cache`.
+    pub fn compile_at(&self, now: DateTime<Utc>) -> Warned<Result<Document, TypstAsLibError>> {
+        let Self {
+            source_id,
+            collection,
+            ..
+        } = self;
+        collection.compile_at(*source_id, now)
+    }
+
+    /// Same as `compile_with_input`, but with `now` as current time (`datetime.today()`).
+    /// Overrides `with_fixed_now`. Not cached by `with_document_cache`.
+    pub fn compile_with_input_at<D>(
+        &self,
+        inputs: D,
+        now: DateTime<Utc>,
+    ) -> Warned<Result<Document, TypstAsLibError>>
+    where
+        D: Into<Dict>,
+    {
+        let Self {
+            source_id,
+            collection,
+            ..
+        } = self;
+        collection.compile_with_input_at(*source_id, inputs, now)
+    }
+
+    /// Same as `compile`, but returns `TypstAsLibError::Cancelled`, when `cancellation` is
+    /// cancelled during the compile.
+    pub fn compile_with_cancellation(
+        &self,
+        cancellation: CancellationToken,
+    ) -> Warned<Result<Document, TypstAsLibError>> {
+        let Self {
+            source_id,
+            collection,
+            ..
+        } = self;
+        collection.compile_with_cancellation(*source_id, cancellation)
+    }
+
+    /// Same as `compile_with_input`, but returns `TypstAsLibError::Cancelled`, when
+    /// `cancellation` is cancelled during the compile.
+    pub fn compile_with_input_and_cancellation<D>(
+        &self,
+        inputs: D,
+        cancellation: CancellationToken,
+    ) -> Warned<Result<Document, TypstAsLibError>>
+    where
+        D: Into<Dict>,
+    {
+        let Self {
+            source_id,
+            collection,
+            ..
+        } = self;
+        collection.compile_with_input_and_cancellation(*source_id, inputs, cancellation)
+    }
+
+    /// Same as `compile`, but returns a `CompileOutput`, that also contains the accessed files
+    /// and stats about the compile.
+    pub fn compile_detailed(&self) -> CompileOutput {
+        let Self {
+            source_id,
+            collection,
+            ..
+        } = self;
+        collection.compile_detailed(*source_id)
+    }
+
+    /// Same as `compile_with_input`, but returns a `CompileOutput`, that also contains the
+    /// accessed files and stats about the compile.
+    pub fn compile_with_input_detailed<D>(&self, inputs: D) -> CompileOutput
+    where
+        D: Into<Dict>,
+    {
+        let Self {
+            source_id,
+            collection,
+            ..
+        } = self;
+        collection.compile_with_input_detailed(*source_id, inputs)
+    }
+
+    /// Compile and export the document as pdf (feature `pdf`).
+    #[cfg(feature = "pdf")]
+    pub fn compile_to_pdf(
+        &self,
+        options: &pdf::PdfExportOptions,
+    ) -> Warned<Result<Vec<u8>, TypstAsLibError>> {
+        let Self {
+            source_id,
+            collection,
+            ..
+        } = self;
+        collection.compile_to_pdf(*source_id, options)
+    }
+
+    /// Compile with input and export the document as pdf (feature `pdf`).
+    #[cfg(feature = "pdf")]
+    pub fn compile_with_input_to_pdf<D>(
+        &self,
+        inputs: D,
+        options: &pdf::PdfExportOptions,
+    ) -> Warned<Result<Vec<u8>, TypstAsLibError>>
+    where
+        D: Into<Dict>,
+    {
+        let Self {
+            source_id,
+            collection,
+            ..
+        } = self;
+        collection.compile_with_input_to_pdf(*source_id, inputs, options)
+    }
+
+    /// Compile and render each page as png with `ppi` pixels per inch (feature `render`).
+    #[cfg(feature = "render")]
+    pub fn compile_to_png(&self, ppi: f32) -> Warned<Result<Vec<Vec<u8>>, TypstAsLibError>> {
+        let Self {
+            source_id,
+            collection,
+            ..
+        } = self;
+        collection.compile_to_png(*source_id, ppi)
+    }
+
+    /// Compile with input and render each page as png with `ppi` pixels per inch
+    /// (feature `render`).
+    #[cfg(feature = "render")]
+    pub fn compile_with_input_to_png<D>(
+        &self,
+        inputs: D,
+        ppi: f32,
+    ) -> Warned<Result<Vec<Vec<u8>>, TypstAsLibError>>
     where
         D: Into<Dict>,
     {
         let Self {
-            inject_location,
-            library,
+            source_id,
+            collection,
             ..
         } = self;
-        let mut lib = library.deref().clone();
-        inject_input_into_library(&mut lib, inject_location.as_ref(), input)?;
-        Ok(LazyHash::new(lib))
+        collection.compile_with_input_to_png(*source_id, inputs, ppi)
     }
 
-    fn resolve_file(&self, file_id: FileId) -> FileResult<Cow<Bytes>> {
-        let TypstTemplateCollection { file_resolvers, .. } = self;
-        let mut last_error = not_found(file_id);
-        for file_resolver in file_resolvers {
-            match file_resolver.resolve_binary(file_id) {
-                Ok(source) => return Ok(source),
-                Err(error) => last_error = error,
-            }
-        }
-        Err(last_error)
+    /// Same as `compile_with_input`, but with a json value (e.g. the body of a web request)
+    /// as input. The value has to be an object (feature `json`).
+    #[cfg(feature = "json")]
+    pub fn compile_with_json(
+        &self,
+        input: serde_json::Value,
+    ) -> Warned<Result<Document, TypstAsLibError>> {
+        let Self {
+            source_id,
+            collection,
+            ..
+        } = self;
+        collection.compile_with_json(*source_id, input)
     }
 
-    fn resolve_source(&self, file_id: FileId) -> FileResult<Cow<Source>> {
-        let TypstTemplateCollection { file_resolvers, .. } = self;
-        let mut last_error = not_found(file_id);
-        for file_resolver in file_resolvers {
-            match file_resolver.resolve_source(file_id) {
-                Ok(source) => return Ok(source),
-                Err(error) => last_error = error,
-            }
-        }
-        Err(last_error)
+    /// Same as `compile`, but runs on the blocking thread pool of tokio, so that it does not
+    /// block the async runtime (feature `async`). Has to be called inside of a tokio runtime.
+    #[cfg(feature = "async")]
+    pub async fn compile_async(self: &Arc<Self>) -> Warned<Result<Document, TypstAsLibError>> {
+        let template = Arc::clone(self);
+        util::spawn_blocking(move || template.compile()).await
     }
-}
 
-fn inject_input_into_library<'a, D>(
-    library: &'a mut Library,
-    inject_location: Option<&InjectLocation>,
-    input: D,
-) -> Result<&'a mut Library, TypstAsLibError>
-where
-    D: Into<Dict>,
-{
-    let (module_name, value_name) = if let Some(InjectLocation {
-        module_name,
-        value_name,
-    }) = inject_location
+    /// Same as `compile_with_input`, but runs on the blocking thread pool of tokio, so that it
+    /// does not block the async runtime (feature `async`). Has to be called inside of a tokio
+    /// runtime.
+    #[cfg(feature = "async")]
+    pub async fn compile_with_input_async<D>(
+        self: &Arc<Self>,
+        inputs: D,
+    ) -> Warned<Result<Document, TypstAsLibError>>
+    where
+        D: Into<Dict>,
     {
-        (module_name.as_str(), value_name.as_str())
-    } else {
-        ("sys", "inputs")
-    };
-    let global = library.global.scope_mut();
-    let mut scope = Scope::new();
-    scope.define(value_name, input.into());
-    if let Some(value) = global.get_mut(module_name).transpose()? {
-        if let Value::Module(module) = value {
-            *module.scope_mut() = scope;
-        } else {
-            let module = Module::new(module_name, scope);
-            *value = Value::Module(module);
-        }
-    } else {
-        let module = Module::new(module_name, scope);
-        global.define_module(module);
+        let template = Arc::clone(self);
+        let inputs = inputs.into();
+        util::spawn_blocking(move || template.compile_with_input(inputs)).await
     }
-    Ok(library)
-}
-
-pub struct TypstTemplate {
-    source_id: FileId,
-    collection: TypstTemplateCollection,
-}
 
-impl TypstTemplate {
-    /// Initialize with fonts and a source file.
+    /// Compile the template once per input set, e.g. for mail merges. Memoized results are
+    /// kept between the compiles of the batch, so the parts of the template, that don't
+    /// depend on the inputs, are only evaluated and laid out once.
     ///
-    /// `source` can be of types:
-    ///   - `&str/String`, creating a detached Source (Has vpath `/main.typ`)
-    ///   - `(&str, &str/String)`, where &str is the absolute
-    ///     virtual path of the Source file.
-    ///   - `(typst::syntax::FileId, &str/String)`
-    ///   - `typst::syntax::Source`
-    ///
-    /// (`&str/String` is always the template file content)
-    ///
-    /// Example:
-    /// ```rust
-    /// static TEMPLATE: &str = include_str!("./templates/template.typ");
-    /// static FONT: &[u8] = include_bytes!("./fonts/texgyrecursor-regular.otf");
-    /// // ...
-    /// let font = Font::new(Bytes::from(FONT), 0).expect("Could not parse font!");
-    /// let template = TypstTemplate::new(vec![font], TEMPLATE);
+    /// ```rust,ignore
+    /// let invoices = template.compile_batch(customers.iter().map(|customer| {
+    ///     dict! { "name" => customer.name.clone(), "amount" => customer.amount }
+    /// }));
     /// ```
-    pub fn new<V, S>(fonts: V, source_id: S) -> Self
+    pub fn compile_batch<I, D>(&self, inputs: I) -> Vec<Warned<Result<Document, TypstAsLibError>>>
     where
-        V: Into<Vec<Font>>,
-        S: Into<SourceNewType>,
+        I: IntoIterator<Item = D>,
+        D: Into<Dict>,
     {
-        let SourceNewType(source) = source_id.into();
-        let source_id = source.id();
-        let mut collection = TypstTemplateCollection::new(fonts);
-        collection
-            .file_resolvers
-            .push(Box::new(MainSourceFileResolver::new(source)));
-        Self {
-            collection,
-            source_id,
-        }
-    }
-
-    pub fn comemo_evict_max_age(&mut self, comemo_evict_max_age: Option<usize>) -> &mut Self {
-        self.collection.comemo_evict_max_age = comemo_evict_max_age;
-        self
+        self.collection.compile_batch(self.source_id, inputs)
     }
 
-    /// Use other typst location for injected inputs
-    /// (instead of`#import sys: inputs`, where `sys` is the `module_name`
-    /// and `inputs` is the `value_name`).
-    pub fn custom_inject_location<S>(mut self, module_name: S, value_name: S) -> Self
+    /// Same as `compile_batch`, but compiles in parallel on the rayon thread pool
+    /// (feature `rayon`). The results are in the order of the inputs.
+    #[cfg(feature = "rayon")]
+    pub fn compile_batch_parallel<I, D>(
+        &self,
+        inputs: I,
+    ) -> Vec<Warned<Result<Document, TypstAsLibError>>>
     where
-        S: Into<String>,
+        I: IntoIterator<Item = D>,
+        D: Into<Dict>,
     {
         self.collection
-            .custom_inject_location_mut(module_name, value_name);
-        self
+            .compile_batch_parallel(self.source_id, inputs)
     }
 
-    /// Add Fonts
-    pub fn add_fonts<I, F>(mut self, fonts: I) -> Self
-    where
-        I: IntoIterator<Item = F>,
-        F: Into<Font>,
-    {
-        self.collection.add_fonts_mut(fonts);
-        self
+    /// Start a `CompileSession` for the template, which keeps the memoized results of typst
+    /// between compiles until it is told to evict.
+    pub fn session(&self) -> CompileSession<'_> {
+        self.collection.session(self.source_id)
     }
 
-    /// Add file resolver, that implements the `FileResolver`` trait to a vec of file resolvers.
-    /// When a `FileId`` needs to be resolved by Typst, the vec will be iterated over until
-    /// one file resolver returns a file.
-    pub fn add_file_resolver<F>(mut self, file_resolver: F) -> Self
+    /// Compile with `inputs` and export the document with `export`, or return the artifact
+    /// of an earlier compile from `cache` (feature `sqlite`). See
+    /// `TypstTemplateCollection::compile_artifact`.
+    #[cfg(feature = "sqlite")]
+    pub fn compile_artifact<D, E>(
+        &self,
+        cache: &sqlite_cache::SqliteCache,
+        kind: &str,
+        inputs: D,
+        export: E,
+    ) -> Result<Vec<u8>, TypstAsLibError>
     where
-        F: FileResolver + Send + Sync + 'static,
+        D: Into<Dict>,
+        E: FnOnce(&Document) -> Result<Vec<u8>, TypstAsLibError>,
     {
-        self.collection.add_file_resolver_mut(file_resolver);
-        self
+        let Self {
+            source_id,
+            collection,
+            ..
+        } = self;
+        collection.compile_artifact(cache, kind, *source_id, inputs, export)
     }
 
-    /// Adds the `StaticFileResolver` to the file resolvers. It creates `HashMap`s for sources.
-    ///
-    /// `sources` The item of the IntoIterator can be of types:
-    ///   - `&str/String`, creating a detached Source (Has vpath `/main.typ`)
-    ///   - `(&str, &str/String)`, where &str is the absolute
-    ///     virtual path of the Source file.
-    ///   - `(typst::syntax::FileId, &str/String)`
-    ///   - `typst::syntax::Source`
-    ///
-    /// (`&str/String` is always the template file content)
-    pub fn with_static_source_file_resolver<IS, S>(mut self, sources: IS) -> Self
-    where
-        IS: IntoIterator<Item = S>,
-        S: Into<SourceNewType>,
-    {
-        self.collection
-            .with_static_source_file_resolver_mut(sources);
-        self
+    /// Render diagnostics CLI-style, with the annotated source snippets and hints
+    /// (feature `diag`). If `color` is set, the output contains ANSI color codes.
+    #[cfg(feature = "diag")]
+    pub fn format_diagnostics(&self, diagnostics: &[SourceDiagnostic], color: bool) -> String {
+        self.collection.format_diagnostics(diagnostics, color)
     }
 
-    /// Adds the `StaticFileResolver` to the file resolvers. It creates `HashMap`s for binaries.
-    pub fn with_static_file_resolver<IB, F, B>(mut self, binaries: IB) -> Self
-    where
-        IB: IntoIterator<Item = (F, B)>,
-        F: Into<FileIdNewType>,
-        B: Into<Bytes>,
-    {
-        self.collection.with_static_file_resolver_mut(binaries);
-        self
+    /// Query the elements of a compiled `document`, like `typst query` does.
+    /// See `TypstTemplateCollection::query`.
+    pub fn query(
+        &self,
+        document: &Document,
+        selector: &str,
+    ) -> Result<Vec<Value>, TypstAsLibError> {
+        self.collection.query(document, selector)
     }
 
-    /// Adds `FileSystemFileResolver` to the file resolvers, a resolver that can resolve
-    /// local files (when `package` is not set in `FileId`).
-    pub fn with_file_system_resolver<P>(mut self, root: P) -> Self
+    /// Same as `query`, but returns the values as JSON (feature `json`).
+    #[cfg(feature = "json")]
+    pub fn query_json(
+        &self,
+        document: &Document,
+        selector: &str,
+    ) -> Result<serde_json::Value, TypstAsLibError> {
+        self.collection.query_json(document, selector)
+    }
+
+    /// Evaluate the typst expression `expr` with the library, inputs and file resolvers of the
+    /// template. See `TypstTemplateCollection::eval_expr`.
+    pub fn eval_expr<D>(&self, expr: &str, inputs: D) -> Result<Value, TypstAsLibError>
     where
-        P: Into<PathBuf>,
+        D: Into<Dict>,
     {
-        self.collection.with_file_system_resolver_mut(root);
-        self
+        self.collection.eval_expr(expr, inputs)
     }
 
-    #[cfg(feature = "packages")]
-    /// Adds `PackageResolver` to the file resolvers.
-    /// When `package` is set in `FileId`, it will download the package from the typst package
-    /// repository. It caches the results into `cache` (which is either in memory or cache folder (default)).
-    /// Example
-    /// ```rust
-    ///     let template = TypstTemplate::new(vec![font], TEMPLATE_FILE)
-    ///         .with_package_file_resolver(None);
-    /// ```
-    pub fn with_package_file_resolver(mut self, ureq: Option<ureq::Agent>) -> Self {
-        self.collection.with_package_file_resolver_mut(ureq);
-        self
+    /// All errors and warnings of a compile as `Diagnostic`s, with file path and line/column
+    /// positions.
+    pub fn diagnostics<T>(&self, output: &Warned<Result<T, TypstAsLibError>>) -> Vec<Diagnostic> {
+        self.collection.diagnostics(output)
     }
 
-    /// Call `typst::compile()` with our template and a `Dict` as input, that will be availible
-    /// in a typst script with `#import sys: inputs`.
-    pub fn compile_with_input<D>(&self, inputs: D) -> Warned<Result<Document, TypstAsLibError>>
-    where
-        D: Into<Dict>,
-    {
+    /// Same as `compile`, but the fonts of `font_set` are available in addition to the fonts
+    /// of the template. They are not visible to any other compile.
+    pub fn compile_with_fonts(
+        &self,
+        font_set: &FontSet,
+    ) -> Warned<Result<Document, TypstAsLibError>> {
         let Self {
             source_id,
             collection,
             ..
         } = self;
-        collection.compile_with_input(*source_id, inputs)
+        collection.compile_with_fonts(*source_id, font_set)
     }
 
-    /// Call `typst::compile()` with our template and a `Dict` as input, that will be availible
-    /// in a typst script with `#import sys: inputs`. Mutates the library each call.
-    ///
-    /// Example:
-    ///
-    /// ```rust
-    /// static TEMPLATE: &str = include_str!("./templates/template.typ");
-    /// static FONT: &[u8] = include_bytes!("./fonts/texgyrecursor-regular.otf");
-    /// static TEMPLATE_ID: &str = "/template.typ";
-    /// // ...
-    /// let font = Font::new(Bytes::from(FONT), 0).expect("Could not parse font!");
-    /// let template = TypstTemplate::new(vec![font], TEMPLATE);
-    /// // Struct that implements Into<Dict>.
-    /// let inputs = todo!();
-    /// let tracer = Default::default();
-    /// let doc = template.compile_with_input_fast(&mut tracer, TEMPLATE_ID, inputs)
-    ///     .expect("Typst error!");
-    /// ```
-    #[deprecated(
-        since = "0.11.1",
-        note = "Use TypstTemplate::compile_with_input() instead!"
-    )]
-    pub fn compile_with_input_fast<D>(
-        &mut self,
-        input: D,
+    /// See `TypstTemplateCollection::compile_with_input_and_files`.
+    pub fn compile_with_input_and_files<D, IB, P, B>(
+        &self,
+        inputs: D,
+        files: IB,
     ) -> Warned<Result<Document, TypstAsLibError>>
     where
         D: Into<Dict>,
+        IB: IntoIterator<Item = (P, B)>,
+        P: Into<FileIdNewType>,
+        B: Into<Bytes>,
     {
         let Self {
             source_id,
             collection,
             ..
         } = self;
-        collection.compile_with_input_fast(*source_id, input)
+        collection.compile_with_input_and_files(*source_id, inputs, files)
     }
 
-    /// Just call `typst::compile()`
-    pub fn compile(&self) -> Warned<Result<Document, TypstAsLibError>> {
+    /// Same as `compile_with_input`, but the fonts of `font_set` are available in addition to
+    /// the fonts of the template. They are not visible to any other compile.
+    pub fn compile_with_input_and_fonts<D>(
+        &self,
+        inputs: D,
+        font_set: &FontSet,
+    ) -> Warned<Result<Document, TypstAsLibError>>
+    where
+        D: Into<Dict>,
+    {
         let Self {
             source_id,
             collection,
             ..
         } = self;
-        collection.compile(*source_id)
+        collection.compile_with_input_and_fonts(*source_id, inputs, font_set)
+    }
+
+    /// Same as `compile_detailed`, but records the timings of typst (feature `timings`).
+    /// See `TypstTemplateCollection::compile_with_profile`.
+    #[cfg(feature = "timings")]
+    pub fn compile_with_profile(&self) -> timings::CompileProfile {
+        self.collection.compile_with_profile(self.source_id)
+    }
+
+    /// Same as `compile_with_input_detailed`, but records the timings of typst
+    /// (feature `timings`). See `TypstTemplateCollection::compile_with_profile`.
+    #[cfg(feature = "timings")]
+    pub fn compile_with_input_and_profile<D>(&self, inputs: D) -> timings::CompileProfile
+    where
+        D: Into<Dict>,
+    {
+        self.collection
+            .compile_with_input_and_profile(self.source_id, inputs)
     }
 }
 
@@ -646,7 +4011,115 @@ struct TypstWorld<'a> {
     main_source_id: FileId,
     collection: &'a TypstTemplateCollection,
     library: Cow<'a, LazyHash<Library>>,
+    book: Cow<'a, LazyHash<FontBook>>,
+    /// Fonts of a `FontSet`, indexed after the fonts of the collection.
+    extra_fonts: &'a [Font],
+    /// Files of a single compile, that are resolved before the file resolvers.
+    files: Option<&'a StaticFileResolver>,
     now: DateTime<Utc>,
+    cancellation: Option<CancellationToken>,
+    /// All files, that were accessed during the compile, with their path on disk, if the
+    /// resolver, that served them, reads from disk.
+    dependencies: Mutex<HashMap<FileId, Option<PathBuf>>>,
+    /// Why a package could not be resolved, if it is not cached and the package resolver is
+    /// offline (`PackageNotCached`), it is not allowed (`PackageNotAllowed`), it failed the
+    /// integrity check (`PackageIntegrity`) or it could not be checked (`PackageUnpinned`, ...).
+    package_error: Mutex<Option<TypstAsLibError>>,
+    /// Why the main source could not be resolved.
+    main_source_errors: Mutex<Option<ResolverErrors>>,
+    /// The compile asked for the current date (`datetime.today()`).
+    used_today: AtomicBool,
+}
+
+impl TypstWorld<'_> {
+    fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+
+    /// `id` from the files of this compile (`CompileOptions::files`), if the access policy of
+    /// the collection allows it.
+    fn compile_file(&self, id: FileId) -> Option<Cow<'_, Bytes>> {
+        if !self.collection.is_allowed(id) {
+            return None;
+        }
+        self.files?.resolve_binary(id).ok()
+    }
+
+    fn track_dependency(&self, id: FileId) {
+        if let Ok(mut dependencies) = self.dependencies.lock() {
+            dependencies.entry(id).or_default();
+        }
+    }
+
+    /// Remember the path of `id` on disk, if the resolver, that served it, reads from disk.
+    /// Only needed for depfiles.
+    fn track_path(&self, id: FileId, file_resolver: &BoxedFileResolver) {
+        if self.collection.depfile.is_none() {
+            return;
+        }
+        let Some(path) = file_resolver.resolve_path(id) else {
+            return;
+        };
+        if let Ok(mut dependencies) = self.dependencies.lock() {
+            dependencies.insert(id, Some(path));
+        }
+    }
+
+    fn track_error<T>(&self, id: FileId, result: Result<T, ResolverErrors>) -> FileResult<T> {
+        let errors = match result {
+            Ok(value) => return Ok(value),
+            Err(errors) => errors,
+        };
+        if id == self.main_source_id {
+            if let Ok(mut main_source_errors) = self.main_source_errors.lock() {
+                *main_source_errors = Some(errors.clone());
+            }
+        }
+        let package = id.package().cloned();
+        let package_error = package.zip(errors.package_failure().cloned()).map(
+            |(package, failure)| match failure {
+                PackageFailure::NotCached => TypstAsLibError::PackageNotCached(package),
+                PackageFailure::NotAllowed => TypstAsLibError::PackageNotAllowed(package),
+                PackageFailure::Integrity { expected, found } => {
+                    TypstAsLibError::PackageIntegrity {
+                        package,
+                        expected,
+                        found,
+                    }
+                }
+                PackageFailure::ChecksumMissing => TypstAsLibError::PackageChecksumMissing(package),
+                PackageFailure::Unpinned => TypstAsLibError::PackageUnpinned(package),
+                PackageFailure::Unverifiable => TypstAsLibError::PackageUnverifiable(package),
+            },
+        );
+        if let (Some(package_error), Ok(mut first)) = (package_error, self.package_error.lock()) {
+            first.get_or_insert(package_error);
+        }
+        Err(FileError::from(errors))
+    }
+
+    /// All files, that were accessed during the compile.
+    fn dependencies(&self) -> Vec<FileId> {
+        let Ok(dependencies) = self.dependencies.lock() else {
+            return Vec::new();
+        };
+        let mut dependencies: Vec<_> = dependencies.keys().copied().collect();
+        dependencies.sort();
+        dependencies
+    }
+
+    /// Paths on disk of all files, that were accessed during the compile.
+    fn dependency_paths(&self) -> Vec<PathBuf> {
+        let Ok(dependencies) = self.dependencies.lock() else {
+            return Vec::new();
+        };
+        let mut paths: Vec<_> = dependencies.values().flatten().cloned().collect();
+        paths.sort();
+        paths.dedup();
+        paths
+    }
 }
 
 impl typst::World for TypstWorld<'_> {
@@ -655,31 +4128,69 @@ impl typst::World for TypstWorld<'_> {
     }
 
     fn book(&self) -> &LazyHash<FontBook> {
-        &self.collection.book
+        self.book.as_ref()
     }
 
     fn main(&self) -> FileId {
-        self.main_source_id.clone()
+        self.main_source_id
     }
 
     fn source(&self, id: FileId) -> FileResult<Source> {
-        self.collection.resolve_source(id).map(|s| s.into_owned())
+        if self.is_cancelled() {
+            return Err(cancelled());
+        }
+        self.track_dependency(id);
+        if let Some(file) = self.compile_file(id) {
+            return bytes_to_source(id, &file);
+        }
+        let source = self
+            .collection
+            .resolve_with(id, |file_resolver| file_resolver.resolve_source(id))
+            .map(|(source, file_resolver)| {
+                self.track_path(id, file_resolver);
+                source.into_owned()
+            });
+        self.track_error(id, source)
     }
 
     fn file(&self, id: FileId) -> FileResult<Bytes> {
-        self.collection.resolve_file(id).map(|b| b.into_owned())
+        if self.is_cancelled() {
+            return Err(cancelled());
+        }
+        self.track_dependency(id);
+        if let Some(file) = self.compile_file(id) {
+            return Ok(file.into_owned());
+        }
+        let file = self
+            .collection
+            .resolve_with(id, |file_resolver| file_resolver.resolve_binary(id))
+            .map(|(file, file_resolver)| {
+                self.track_path(id, file_resolver);
+                file.into_owned()
+            });
+        self.track_error(id, file)
     }
 
     fn font(&self, id: usize) -> Option<Font> {
-        self.collection.fonts.get(id).cloned()
+        if self.is_cancelled() {
+            return None;
+        }
+        let fonts = &self.collection.fonts;
+        match id.checked_sub(fonts.len()) {
+            Some(extra_id) => self.extra_fonts.get(extra_id).cloned(),
+            None => fonts.get(id)?.get(),
+        }
     }
 
     fn today(&self, offset: Option<i64>) -> Option<Datetime> {
-        let mut now = self.now;
-        if let Some(offset) = offset {
-            now += Duration::hours(offset);
+        if self.is_cancelled() {
+            return None;
         }
-        let date = now.date_naive();
+        self.used_today.store(true, Ordering::Relaxed);
+        let date = match offset {
+            Some(offset) => (self.now + chrono::Duration::hours(offset)).date_naive(),
+            None => self.collection.local_now(self.now).date(),
+        };
         let year = date.year();
         let month = (date.month0() + 1) as u8;
         let day = (date.day0() + 1) as u8;
@@ -687,7 +4198,47 @@ impl typst::World for TypstWorld<'_> {
     }
 }
 
-#[derive(Debug, Clone)]
+fn cancelled() -> FileError {
+    FileError::Other(Some(eco_format!("Compile was cancelled")))
+}
+
+fn datetime_from_chrono(value: NaiveDateTime) -> Option<Datetime> {
+    Datetime::from_ymd_hms(
+        value.year(),
+        value.month() as u8,
+        value.day() as u8,
+        value.hour() as u8,
+        value.minute() as u8,
+        value.second() as u8,
+    )
+}
+
+/// Book of all fonts and the fonts, that are not part of the collection, of a compile.
+type ExtraFonts = (Arc<LazyHash<FontBook>>, Vec<Font>);
+
+/// Settings, that only apply to a single compile.
+#[derive(Default)]
+struct CompileOverrides<'a> {
+    font_set: Option<&'a FontSet>,
+    /// Files, that are only visible to this compile.
+    files: Option<&'a StaticFileResolver>,
+    now: Option<DateTime<Utc>>,
+    cancellation: Option<CancellationToken>,
+    /// Lower bound for `comemo_evict_max_age`. Batches keep the memoized results of the
+    /// previous compile, so the parts of the template, that don't depend on the inputs, are
+    /// reused.
+    min_evict_max_age: usize,
+    /// Overrides `comemo_evict_max_age` of the collection.
+    comemo_evict_max_age: Option<usize>,
+    /// Neither read from nor write to the document cache.
+    skip_cache: bool,
+    /// Don't evict at all, the caller (e.g. a `CompileSession`) decides when to evict.
+    skip_evict: bool,
+    /// Number of pages of the preceding fragments (`compile_fragments`).
+    page_offset: Option<usize>,
+}
+
+#[derive(Debug, Clone, Hash)]
 struct InjectLocation {
     module_name: String,
     value_name: String,
@@ -695,14 +4246,71 @@ struct InjectLocation {
 
 #[derive(Debug, Clone, Error)]
 pub enum TypstAsLibError {
-    #[error("Typst source error: {}", 0.to_string())]
+    #[error("Typst source error: {}", .0.iter().map(|d| d.message.as_str()).collect::<Vec<_>>().join(", "))]
     TypstSource(EcoVec<SourceDiagnostic>),
-    #[error("Typst file error: {}", 0.to_string())]
+    #[error("Typst file error: {0}")]
     TypstFile(#[from] FileError),
     #[error("Source file does not exist in collection: {0:?}")]
     MainSourceFileDoesNotExist(FileId),
-    #[error("Typst hinted String: {}", 0.to_string())]
+    #[error("Typst hinted String: {}", .0.message())]
     HintedString(HintedString),
+    #[error("Invalid input: {}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "))]
+    InvalidInput(Vec<InputSchemaError>),
+    #[error("Compile was cancelled")]
+    Cancelled,
+    #[error("No template registered as {0:?}")]
+    TemplateNotRegistered(EcoString),
+    #[error("Package {0} is not cached and the package resolver is offline")]
+    PackageNotCached(PackageSpec),
+    /// A package doesn't match the patterns of `PackageResolverBuilder::allow`.
+    #[error("Package {0} is not allowed by the package resolver")]
+    PackageNotAllowed(PackageSpec),
+    /// A pattern of `PackageResolverBuilder::allow` is not `@namespace`, `@namespace/name` or
+    /// `@namespace/name:version`.
+    #[error("Invalid package pattern {0}")]
+    InvalidPackagePattern(EcoString),
+    /// The url of `PackageResolverBuilder::proxy` can't be parsed.
+    #[error("Invalid proxy: {0}")]
+    InvalidProxy(EcoString),
+    /// A package doesn't match the checksum, that was expected for it (see
+    /// `PackageResolverBuilder::with_archive_checksums` and `with_package_lock`), e.g.
+    /// because it was modified after it was published or in the cache.
+    #[error("Package {package} failed the integrity check (expected {expected}, found {found})")]
+    PackageIntegrity {
+        package: PackageSpec,
+        expected: EcoString,
+        found: EcoString,
+    },
+    /// A package has no archive checksum, but
+    /// `PackageResolverBuilder::require_archive_checksums` is set.
+    #[error("Package {0} has no archive checksum and is not downloaded")]
+    PackageChecksumMissing(PackageSpec),
+    /// A package is not in the lockfile, but `PackageResolverBuilder::frozen_lock` is set.
+    #[error("Package {0} is not in the lockfile")]
+    PackageUnpinned(PackageSpec),
+    /// A cached package can't be checked against its archive checksum, because the package
+    /// resolver is offline.
+    #[error("Package {0} is cached, but can't be checked against its archive checksum, because the package resolver is offline")]
+    PackageUnverifiable(PackageSpec),
+    /// The main source could not be resolved by any file resolver.
+    #[error("{0}")]
+    Unresolved(ResolverErrors),
+    /// `with_in_memory_only` was set, but a file resolver, font or depfile of the collection
+    /// touches the file system or network.
+    #[error("{0} is not in memory, but the collection is in-memory only")]
+    NotInMemory(EcoString),
+    #[cfg(feature = "serde")]
+    #[error(transparent)]
+    Serialize(#[from] serialize::SerializeError),
+    /// The standards of `PdfExportOptions` can't be combined.
+    #[cfg(feature = "pdf")]
+    #[error("Invalid pdf standards: {0}")]
+    InvalidPdfStandards(EcoString),
+    /// The pdf export failed, e.g. because the document violates a requested standard
+    /// (PDF/A forbids transparency, requires embeddable fonts, ...).
+    #[cfg(feature = "pdf")]
+    #[error("Pdf export error: {}", .0.iter().map(|d| d.message.as_str()).collect::<Vec<_>>().join(", "))]
+    PdfExport(EcoVec<SourceDiagnostic>),
 }
 
 impl From<HintedString> for TypstAsLibError {
@@ -0,0 +1,93 @@
+/// Create a `typst::foundations::Dict`, e.g. as inputs of a compile. Keys can be anything,
+/// that converts into a `Str`, values anything, that implements `IntoValue`. Values in
+/// brackets (`[...]`) become arrays (see `array!`), values in braces (`{...}`) nested
+/// dictionaries.
+///
+/// ```rust,ignore
+/// let inputs = dict! {
+///     "name" => "Alice",
+///     "age" => 42,
+///     "address" => { "city" => "Berlin", "zip" => "10115" },
+///     "items" => [
+///         { "name" => "Book", "price" => 12.5 },
+///         { "name" => "Pen", "price" => 1.2 },
+///     ],
+/// };
+/// let doc = template.compile_with_input(inputs);
+/// ```
+#[macro_export]
+macro_rules! dict {
+    () => {
+        $crate::typst::foundations::Dict::new()
+    };
+    ($($entries:tt)+) => {{
+        let mut dict = $crate::typst::foundations::Dict::new();
+        $crate::__dict_entries!(dict; $($entries)+);
+        dict
+    }};
+}
+
+/// Create a `typst::foundations::Array`. Items can be anything, that implements `IntoValue`,
+/// items in brackets (`[...]`) become nested arrays, items in braces (`{...}`) dictionaries
+/// (see `dict!`).
+///
+/// ```rust,ignore
+/// let matrix = array![[1, 2], [3, 4]];
+/// let rows = array![{ "a" => 1 }, { "a" => 2 }];
+/// ```
+#[macro_export]
+macro_rules! array {
+    () => {
+        $crate::typst::foundations::Array::new()
+    };
+    ($($items:tt)+) => {{
+        let mut array = $crate::typst::foundations::Array::new();
+        $crate::__array_items!(array; $($items)+);
+        array
+    }};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __dict_entries {
+    ($dict:ident;) => {};
+    ($dict:ident; $key:expr => [$($value:tt)*] $(, $($rest:tt)*)?) => {
+        $dict.insert(
+            $crate::typst::foundations::Str::from($key),
+            $crate::typst::foundations::Value::Array($crate::array![$($value)*]),
+        );
+        $crate::__dict_entries!($dict; $($($rest)*)?);
+    };
+    ($dict:ident; $key:expr => {$($value:tt)*} $(, $($rest:tt)*)?) => {
+        $dict.insert(
+            $crate::typst::foundations::Str::from($key),
+            $crate::typst::foundations::Value::Dict($crate::dict! { $($value)* }),
+        );
+        $crate::__dict_entries!($dict; $($($rest)*)?);
+    };
+    ($dict:ident; $key:expr => $value:expr $(, $($rest:tt)*)?) => {
+        $dict.insert(
+            $crate::typst::foundations::Str::from($key),
+            $crate::typst::foundations::IntoValue::into_value($value),
+        );
+        $crate::__dict_entries!($dict; $($($rest)*)?);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __array_items {
+    ($array:ident;) => {};
+    ($array:ident; [$($item:tt)*] $(, $($rest:tt)*)?) => {
+        $array.push($crate::typst::foundations::Value::Array($crate::array![$($item)*]));
+        $crate::__array_items!($array; $($($rest)*)?);
+    };
+    ($array:ident; {$($item:tt)*} $(, $($rest:tt)*)?) => {
+        $array.push($crate::typst::foundations::Value::Dict($crate::dict! { $($item)* }));
+        $crate::__array_items!($array; $($($rest)*)?);
+    };
+    ($array:ident; $item:expr $(, $($rest:tt)*)?) => {
+        $array.push($crate::typst::foundations::IntoValue::into_value($item));
+        $crate::__array_items!($array; $($($rest)*)?);
+    };
+}
@@ -0,0 +1,70 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use ecow::EcoString;
+
+/// Statistics of a file resolver, see `FileResolver::stats`. All counters are totals since the
+/// resolver was created.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResolverStats {
+    /// Files, that were served from the cache.
+    pub cache_hits: u64,
+    /// Files, that were not cached and had to be resolved.
+    pub cache_misses: u64,
+    pub cached_files: usize,
+    pub cached_bytes: usize,
+    pub package_downloads: u64,
+}
+
+/// Metrics of a collection, e.g. to export them to Prometheus.
+///
+/// ```rust,ignore
+/// let metrics = template.metrics();
+/// for ResolverMetrics { name, stats } in &metrics.resolvers {
+///     cache_hits.with_label_values(&[name]).set(stats.cache_hits as i64);
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metrics {
+    /// The file resolvers, in the order they are tried.
+    pub resolvers: Vec<ResolverMetrics>,
+    /// Number of compiles, that were not served from the `DocumentCache`.
+    pub compiles: u64,
+    pub last_compile_duration: Option<Duration>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolverMetrics {
+    /// Type name of the resolver, e.g. `CachedFileResolver<FileSystemResolver>`.
+    pub name: EcoString,
+    pub stats: ResolverStats,
+}
+
+/// Counts the compiles of a collection.
+#[derive(Debug, Default)]
+pub(crate) struct CompileCounter {
+    compiles: AtomicU64,
+    last_duration: Mutex<Option<Duration>>,
+}
+
+impl CompileCounter {
+    pub(crate) fn record(&self, duration: Duration) {
+        self.compiles.fetch_add(1, Ordering::Relaxed);
+        if let Ok(mut last_duration) = self.last_duration.lock() {
+            *last_duration = Some(duration);
+        }
+    }
+
+    pub(crate) fn compiles(&self) -> u64 {
+        self.compiles.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn last_duration(&self) -> Option<Duration> {
+        self.last_duration.lock().ok().and_then(|last| *last)
+    }
+}
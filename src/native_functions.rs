@@ -0,0 +1,129 @@
+//! Rust closures, that can be called from typst (see `TypstTemplateCollection::with_function`).
+//!
+//! ```rust,ignore
+//! let collection = TypstTemplateCollection::new(fonts).with_function("format-money", |args| {
+//!     let cents: i64 = args.expect("cents")?;
+//!     Ok(Value::Str(format!("{}.{:02} €", cents / 100, cents % 100).into()))
+//! })?;
+//! ```
+//!
+//! Functions can be grouped in a module with `with_module_function` (`#data.lookup("key")`),
+//! or passed as part of the inputs (`#inputs.lookup("key")`):
+//!
+//! ```rust,ignore
+//! let mut inputs = Dict::new();
+//! inputs.insert("lookup".into(), Value::Func(native_function("lookup", move |args| { .. })));
+//! ```
+//!
+//! The closure is owned by the returned `Func` and dropped with its last copy (including the
+//! copies in the comemo cache), so functions can be created per compile. Only the function data
+//! for every distinct name is kept until the process ends, so don't use generated names.
+//!
+//! Results of typst code are cached between compiles, while the comemo cache is not evicted
+//! (see `TypstTemplate::comemo_evict_max_age`), so functions should return the same value for
+//! the same arguments.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    hash::{Hash, Hasher},
+    sync::{Arc, Mutex},
+};
+
+use comemo::Tracked;
+use ecow::EcoString;
+use once_cell::sync::Lazy;
+use typst::{
+    diag::{bail, SourceResult},
+    engine::Engine,
+    foundations::{ty, Args, CastInfo, Context, Dynamic, Func, NativeFuncData, Repr, Scope, Value},
+    syntax::Span,
+};
+
+/// Rust closure, that can be called from typst. Take the arguments with `Args::expect`,
+/// `Args::named` etc. Arguments, that are not taken, result in an error.
+pub type NativeFn = dyn Fn(&mut Args) -> SourceResult<Value> + Send + Sync + 'static;
+
+/// Function data for every name, that was passed to `native_function`. Typst needs
+/// `&'static NativeFuncData`, so the data is created once per name and kept until the
+/// process ends. The closures are not kept here.
+static FUNCTION_DATA: Lazy<Mutex<HashMap<String, &'static NativeFuncData>>> =
+    Lazy::new(Default::default);
+
+/// Closure, that is bound as first argument of the function returned by `native_function`.
+/// It is dropped together with the last copy of that function.
+#[ty]
+#[derive(Clone)]
+struct NativeClosure(Arc<NativeFn>);
+
+impl fmt::Debug for NativeClosure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("NativeClosure(..)")
+    }
+}
+
+impl Repr for NativeClosure {
+    fn repr(&self) -> EcoString {
+        "native-closure(..)".into()
+    }
+}
+
+impl PartialEq for NativeClosure {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Hash for NativeClosure {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (Arc::as_ptr(&self.0) as *const () as usize).hash(state);
+    }
+}
+
+/// Create a typst function, that calls `f`. The closure is owned by the returned function
+/// and dropped with it, only the function name is kept until the process ends.
+pub fn native_function<S, F>(name: S, f: F) -> Func
+where
+    S: Into<String>,
+    F: Fn(&mut Args) -> SourceResult<Value> + Send + Sync + 'static,
+{
+    let closure = NativeClosure(Arc::new(f));
+    Func::from(function_data(name.into())).with(&mut Args::new(
+        Span::detached(),
+        [Value::Dyn(Dynamic::new(closure))],
+    ))
+}
+
+fn function_data(name: String) -> &'static NativeFuncData {
+    let mut functions = match FUNCTION_DATA.lock() {
+        Ok(functions) => functions,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    functions.entry(name).or_insert_with_key(|name| {
+        let name: &'static str = Box::leak(name.clone().into_boxed_str());
+        Box::leak(Box::new(NativeFuncData {
+            function: call,
+            name,
+            title: name,
+            docs: "",
+            keywords: &[],
+            contextual: false,
+            scope: Lazy::new(Scope::new),
+            params: Lazy::new(Vec::new),
+            returns: Lazy::new(|| CastInfo::Any),
+        }))
+    })
+}
+
+fn call(_: &mut Engine, _: Tracked<Context>, args: &mut Args) -> SourceResult<Value> {
+    let closure: Value = args.expect("closure")?;
+    let Some(NativeClosure(f)) = (match &closure {
+        Value::Dyn(dynamic) => dynamic.downcast::<NativeClosure>().cloned(),
+        _ => None,
+    }) else {
+        bail!(args.span, "native function is not available");
+    };
+    let value = f(args)?;
+    args.take().finish()?;
+    Ok(value)
+}
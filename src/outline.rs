@@ -0,0 +1,60 @@
+use std::num::NonZeroUsize;
+
+use ecow::EcoString;
+use typst::{
+    foundations::{NativeElement, StyleChain},
+    introspection::Location,
+    model::{Document, HeadingElem},
+};
+
+/// A heading of a compiled document with its subheadings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutlineEntry {
+    pub level: NonZeroUsize,
+    /// The body of the heading as plain text.
+    pub title: EcoString,
+    /// The page, starting at 1.
+    pub page: NonZeroUsize,
+    /// The label of the heading (e.g. `intro` for `= Introduction <intro>`).
+    pub label: Option<EcoString>,
+    /// `false` for headings with `outlined: false`.
+    pub outlined: bool,
+    pub location: Location,
+    pub children: Vec<OutlineEntry>,
+}
+
+/// The heading tree of `document`, e.g. to build a navigation or to check, that a generated
+/// report has the expected sections. Headings are nested under the previous heading with a
+/// lower level.
+///
+/// ```rust,ignore
+/// let outline = document_outline(&document);
+/// let titles: Vec<_> = outline.iter().map(|entry| entry.title.as_str()).collect();
+/// assert_eq!(titles, ["Summary", "Findings", "Appendix"]);
+/// ```
+pub fn document_outline(document: &Document) -> Vec<OutlineEntry> {
+    let introspector = &document.introspector;
+    let mut outline: Vec<OutlineEntry> = Vec::new();
+    for content in introspector.query(&HeadingElem::elem().select()).iter() {
+        let (Some(heading), Some(location)) =
+            (content.to_packed::<HeadingElem>(), content.location())
+        else {
+            continue;
+        };
+        let entry = OutlineEntry {
+            level: heading.resolve_level(StyleChain::default()),
+            title: heading.body().plain_text().trim().into(),
+            page: introspector.page(location),
+            label: content.label().map(|label| label.as_str().into()),
+            outlined: heading.outlined(StyleChain::default()),
+            location,
+            children: Vec::new(),
+        };
+        let mut siblings = &mut outline;
+        while siblings.last().is_some_and(|last| last.level < entry.level) {
+            siblings = &mut siblings.last_mut().unwrap().children;
+        }
+        siblings.push(entry);
+    }
+    outline
+}
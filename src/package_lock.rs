@@ -0,0 +1,180 @@
+use std::{
+    collections::BTreeMap,
+    path::Path,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use ecow::{eco_format, EcoString};
+use sha2::{Digest, Sha256};
+use typst::{
+    diag::{FileError, FileResult},
+    syntax::{package::PackageSpec, FileId},
+};
+
+/// Prefix of the checksums in a lockfile.
+static CHECKSUM_PREFIX: &str = "sha256:";
+
+/// Pins the packages, that templates import, to checksums of their contents, so renders don't
+/// change silently, when a package is republished or the cache is modified. Passed to
+/// `PackageResolverBuilder::with_package_lock`. Cloning is cheap, all clones share the same
+/// packages, so the packages, that the resolver adds, can be written with `write` after the
+/// compile.
+///
+/// The lockfile is TOML:
+///
+/// ```toml
+/// [[package]]
+/// checksum = "sha256:6f1c..."
+/// spec = "@preview/cetz:0.3.1"
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PackageLock {
+    /// Checksums by package (as `@namespace/name:version`, so the lockfile is sorted).
+    packages: Arc<Mutex<BTreeMap<EcoString, EcoString>>>,
+}
+
+impl PackageLock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read the lockfile at `path`. A missing file is an empty lockfile.
+    pub fn read<P>(path: P) -> FileResult<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let lockfile = match std::fs::read_to_string(path) {
+            Ok(lockfile) => lockfile,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Self::new()),
+            Err(error) => return Err(FileError::from_io(error, path)),
+        };
+        Self::parse(&lockfile)
+            .map_err(|error| FileError::Other(Some(eco_format!("{}: {error}", path.display()))))
+    }
+
+    pub fn parse(lockfile: &str) -> Result<Self, EcoString> {
+        let table: toml::Table = toml::from_str(lockfile).map_err(|err| eco_format!("{err}"))?;
+        let entries = match table.get("package") {
+            Some(toml::Value::Array(entries)) => entries.as_slice(),
+            Some(_) => return Err("`package` has to be an array of tables".into()),
+            None => &[],
+        };
+        let mut packages = BTreeMap::new();
+        for entry in entries {
+            let field = |name: &str| {
+                entry
+                    .get(name)
+                    .and_then(toml::Value::as_str)
+                    .ok_or_else(|| eco_format!("package without `{name}`"))
+            };
+            let spec = PackageSpec::from_str(field("spec")?)?;
+            let checksum = field("checksum")?;
+            if !checksum.starts_with(CHECKSUM_PREFIX) {
+                return Err(eco_format!("unsupported checksum `{checksum}` for {spec}"));
+            }
+            packages.insert(eco_format!("{spec}"), checksum.into());
+        }
+        Ok(Self {
+            packages: Arc::new(Mutex::new(packages)),
+        })
+    }
+
+    /// Write the lockfile to `path`.
+    pub fn write<P>(&self, path: P) -> FileResult<()>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_toml()).map_err(|error| FileError::from_io(error, path))
+    }
+
+    pub fn to_toml(&self) -> String {
+        let entries = self
+            .packages()
+            .into_iter()
+            .map(|(spec, checksum)| {
+                let mut entry = toml::Table::new();
+                entry.insert("spec".into(), spec.to_string().into());
+                entry.insert("checksum".into(), checksum.to_string().into());
+                toml::Value::Table(entry)
+            })
+            .collect::<Vec<_>>();
+        let mut table = toml::Table::new();
+        table.insert("package".into(), entries.into());
+        table.to_string()
+    }
+
+    /// Pin `package` to `checksum` (see `package_checksum`).
+    pub fn insert<S>(&self, package: &PackageSpec, checksum: S)
+    where
+        S: Into<EcoString>,
+    {
+        if let Ok(mut packages) = self.packages.lock() {
+            packages.insert(eco_format!("{package}"), checksum.into());
+        }
+    }
+
+    pub fn checksum(&self, package: &PackageSpec) -> Option<EcoString> {
+        let packages = self.packages.lock().ok()?;
+        packages.get(eco_format!("{package}").as_str()).cloned()
+    }
+
+    /// The locked packages with their checksums, sorted by package.
+    pub fn packages(&self) -> Vec<(PackageSpec, EcoString)> {
+        let Ok(packages) = self.packages.lock() else {
+            return Vec::new();
+        };
+        packages
+            .iter()
+            .filter_map(|(spec, checksum)| {
+                Some((PackageSpec::from_str(spec).ok()?, checksum.clone()))
+            })
+            .collect()
+    }
+}
+
+/// Checksum of the files of a package (`sha256:` and the hex encoded SHA-256 hash of the
+/// sorted paths and contents). It is the same for the downloaded archive and the extracted
+/// package in a cache.
+pub fn package_checksum(files: &[(FileId, Vec<u8>)]) -> EcoString {
+    let mut files: Vec<_> = files
+        .iter()
+        .map(|(id, content)| {
+            let path = id.vpath().as_rooted_path().to_string_lossy();
+            (path.replace('\\', "/"), content)
+        })
+        .collect();
+    files.sort();
+    let mut hasher = Sha256::new();
+    for (path, content) in files {
+        hasher.update(path.as_bytes());
+        hasher.update([0]);
+        hasher.update((content.len() as u64).to_le_bytes());
+        hasher.update(content);
+    }
+    finalize(hasher)
+}
+
+/// Checksum of a package archive (`sha256:` and the hex encoded SHA-256 hash of the
+/// `.tar.gz` file, as published by the registry), see
+/// `PackageResolverBuilder::with_archive_checksums`. Same as `sha256sum name-version.tar.gz`.
+pub fn archive_checksum(archive: &[u8]) -> EcoString {
+    finalize(Sha256::new_with_prefix(archive))
+}
+
+/// `checksum` with the `sha256:` prefix and in lower case, so hashes, that were copied from
+/// `sha256sum`, can be compared.
+pub(crate) fn normalize_checksum(checksum: &str) -> EcoString {
+    let hash = checksum.strip_prefix(CHECKSUM_PREFIX).unwrap_or(checksum);
+    eco_format!("{CHECKSUM_PREFIX}{}", hash.to_lowercase())
+}
+
+fn finalize(hasher: Sha256) -> EcoString {
+    let mut checksum = EcoString::from(CHECKSUM_PREFIX);
+    for byte in hasher.finalize() {
+        checksum.push_str(&eco_format!("{byte:02x}"));
+    }
+    checksum
+}
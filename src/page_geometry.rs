@@ -0,0 +1,92 @@
+use std::num::NonZeroUsize;
+
+use typst::{
+    layout::{Abs, Frame, Paper, Point, Sides, Size},
+    model::Document,
+};
+
+use crate::{
+    document_ext::FrameExt,
+    util::{item_box, transformed_corners},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Orientation {
+    Portrait,
+    Landscape,
+    Square,
+}
+
+/// Size, orientation and margins of a page.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageGeometry {
+    /// The page, starting at 1.
+    pub page: NonZeroUsize,
+    pub size: Size,
+    pub orientation: Orientation,
+    /// Distance between the page edges and the content laid out on the page (including
+    /// headers and footers). The configured margins are not part of the compiled document,
+    /// so these approximate them from the content (text is measured by its font size).
+    /// `None` for empty pages.
+    pub content_margins: Option<Sides<Abs>>,
+}
+
+impl PageGeometry {
+    /// Whether the page has the size of `paper` in the given orientation,
+    /// e.g. `is_paper(Paper::A4, Orientation::Portrait)`.
+    pub fn is_paper(&self, paper: Paper, orientation: Orientation) -> bool {
+        let (width, height) = match orientation {
+            Orientation::Landscape => (paper.height(), paper.width()),
+            _ => (paper.width(), paper.height()),
+        };
+        self.orientation == orientation
+            && self.size.x.approx_eq(width)
+            && self.size.y.approx_eq(height)
+    }
+}
+
+/// Geometry of all pages of `document`.
+pub fn page_geometry(document: &Document) -> Vec<PageGeometry> {
+    document
+        .pages
+        .iter()
+        .enumerate()
+        .map(|(i, page)| {
+            let size = page.frame.size();
+            let orientation = if size.x.approx_eq(size.y) {
+                Orientation::Square
+            } else if size.x < size.y {
+                Orientation::Portrait
+            } else {
+                Orientation::Landscape
+            };
+            let content_margins = content_bounds(&page.frame).map(|(min, max)| Sides {
+                left: min.x,
+                top: min.y,
+                right: size.x - max.x,
+                bottom: size.y - max.y,
+            });
+            PageGeometry {
+                page: NonZeroUsize::new(i + 1).unwrap(),
+                size,
+                orientation,
+                content_margins,
+            }
+        })
+        .collect()
+}
+
+fn content_bounds(frame: &Frame) -> Option<(Point, Point)> {
+    let mut bounds: Option<(Point, Point)> = None;
+    for (ts, pos, item) in frame.leaf_items() {
+        let Some((pos, size)) = item_box(pos, item) else {
+            continue;
+        };
+        for corner in transformed_corners(pos, size, ts) {
+            let (min, max) = bounds.get_or_insert((corner, corner));
+            *min = min.min(corner);
+            *max = max.max(corner);
+        }
+    }
+    bounds
+}
@@ -0,0 +1,227 @@
+use std::{borrow::Cow, io::Write};
+
+use chrono::{Datelike, Timelike, Utc};
+use ecow::{eco_format, EcoString};
+use typst::{
+    diag::HintedString,
+    foundations::{Datetime, Smart},
+    layout::PageRanges,
+    model::{Document, DocumentInfo},
+};
+use typst_pdf::{PdfOptions, PdfStandards};
+
+use crate::TypstAsLibError;
+
+pub use typst_pdf::PdfStandard;
+
+/// Options for `export_pdf`.
+#[derive(Debug, Clone, Default)]
+pub struct PdfExportOptions {
+    /// Stable identifier of the document. If `None`, a hash of title and author is used.
+    pub ident: Option<String>,
+    /// Creation date, that is used if the document does not set one
+    /// (`set document(date: ..)`). If `None`, the pdf has no creation date.
+    pub timestamp: Option<Datetime>,
+    /// Standards, that the pdf has to conform to (e.g. `PdfStandard::A_2b` for archival).
+    /// Violations fail the export with `TypstAsLibError::PdfExport`.
+    pub standards: Vec<PdfStandard>,
+    /// Overrides the title of the document (`set document(title: ..)`).
+    pub title: Option<EcoString>,
+    /// Overrides the authors of the document, if not empty.
+    pub author: Vec<EcoString>,
+    /// Overrides the keywords of the document, if not empty.
+    pub keywords: Vec<EcoString>,
+    /// Overrides the creation date of the document. Other than `timestamp`, also if the
+    /// document sets one.
+    pub date: Option<Datetime>,
+    /// Only export these pages (see `document_ext::parse_page_ranges`). If `None`, all
+    /// pages are exported.
+    pub page_ranges: Option<PageRanges>,
+}
+
+impl PdfExportOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_ident<S>(self, ident: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            ident: Some(ident.into()),
+            ..self
+        }
+    }
+
+    pub fn with_timestamp(self, timestamp: Datetime) -> Self {
+        Self {
+            timestamp: Some(timestamp),
+            ..self
+        }
+    }
+
+    /// Use the current time (UTC) as creation date.
+    pub fn with_current_timestamp(self) -> Self {
+        let now = Utc::now();
+        let timestamp = Datetime::from_ymd_hms(
+            now.year(),
+            now.month() as u8,
+            now.day() as u8,
+            now.hour() as u8,
+            now.minute() as u8,
+            now.second() as u8,
+        );
+        Self { timestamp, ..self }
+    }
+
+    pub fn with_standards<I>(self, standards: I) -> Self
+    where
+        I: IntoIterator<Item = PdfStandard>,
+    {
+        Self {
+            standards: standards.into_iter().collect(),
+            ..self
+        }
+    }
+
+    /// Add a standard, that the pdf has to conform to.
+    pub fn with_standard(mut self, standard: PdfStandard) -> Self {
+        self.standards.push(standard);
+        self
+    }
+
+    pub fn with_title<S>(self, title: S) -> Self
+    where
+        S: Into<EcoString>,
+    {
+        Self {
+            title: Some(title.into()),
+            ..self
+        }
+    }
+
+    pub fn with_author<I, S>(self, author: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<EcoString>,
+    {
+        Self {
+            author: author.into_iter().map(Into::into).collect(),
+            ..self
+        }
+    }
+
+    pub fn with_keywords<I, S>(self, keywords: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<EcoString>,
+    {
+        Self {
+            keywords: keywords.into_iter().map(Into::into).collect(),
+            ..self
+        }
+    }
+
+    pub fn with_date(self, date: Datetime) -> Self {
+        Self {
+            date: Some(date),
+            ..self
+        }
+    }
+
+    /// Only export the pages in `page_ranges`, e.g. only the first page for a preview.
+    ///
+    /// ```rust,ignore
+    /// let first_page = PageRanges::new(vec![NonZeroUsize::new(1)..=NonZeroUsize::new(1)]);
+    /// let pdf = export_pdf(&document, &PdfExportOptions::new().with_page_ranges(first_page))?;
+    /// ```
+    pub fn with_page_ranges(self, page_ranges: PageRanges) -> Self {
+        Self {
+            page_ranges: Some(page_ranges),
+            ..self
+        }
+    }
+
+    /// `true`, if the options override the metadata of the document.
+    fn overrides_info(&self) -> bool {
+        self.title.is_some()
+            || !self.author.is_empty()
+            || !self.keywords.is_empty()
+            || self.date.is_some()
+    }
+}
+
+/// Export a compiled document as pdf.
+pub fn export_pdf(
+    document: &Document,
+    options: &PdfExportOptions,
+) -> Result<Vec<u8>, TypstAsLibError> {
+    let document = if options.overrides_info() {
+        let mut document = document.clone();
+        override_info(&mut document.info, options);
+        Cow::Owned(document)
+    } else {
+        Cow::Borrowed(document)
+    };
+    let PdfExportOptions {
+        ident,
+        timestamp,
+        standards,
+        page_ranges,
+        ..
+    } = options;
+    let options = PdfOptions {
+        ident: ident.as_deref().map_or(Smart::Auto, Smart::Custom),
+        timestamp: *timestamp,
+        page_ranges: page_ranges.clone(),
+        standards: PdfStandards::new(standards).map_err(TypstAsLibError::InvalidPdfStandards)?,
+    };
+    let pdf = typst_pdf::pdf(&document, &options).map_err(TypstAsLibError::PdfExport)?;
+    Ok(pdf)
+}
+
+fn override_info(info: &mut DocumentInfo, options: &PdfExportOptions) {
+    let PdfExportOptions {
+        title,
+        author,
+        keywords,
+        date,
+        ..
+    } = options;
+    if let Some(title) = title {
+        info.title = Some(title.clone());
+    }
+    if !author.is_empty() {
+        info.author = author.clone();
+    }
+    if !keywords.is_empty() {
+        info.keywords = keywords.clone();
+    }
+    if let Some(date) = date {
+        info.date = Smart::Custom(Some(*date));
+    }
+}
+
+/// Export a compiled document as pdf into `writer`, e.g. the body of an http response or a
+/// file. Typst encodes the whole pdf before it is written.
+///
+/// ```rust,ignore
+/// let file = std::io::BufWriter::new(File::create("out.pdf")?);
+/// export_pdf_to(&document, &PdfExportOptions::new(), file)?;
+/// ```
+pub fn export_pdf_to<W>(
+    document: &Document,
+    options: &PdfExportOptions,
+    mut writer: W,
+) -> Result<(), TypstAsLibError>
+where
+    W: Write,
+{
+    let pdf = export_pdf(document, options)?;
+    writer
+        .write_all(&pdf)
+        .and_then(|()| writer.flush())
+        .map_err(|err| HintedString::from(eco_format!("Could not write pdf: {err}")))?;
+    Ok(())
+}
@@ -0,0 +1,137 @@
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+};
+
+use typst::{
+    diag::FileResult,
+    foundations::Bytes,
+    syntax::{FileId, Source, VirtualPath},
+};
+
+use crate::{
+    file_resolver::{FileResolver, PackageFailure},
+    metrics::ResolverStats,
+    util::not_found,
+};
+
+/// Resolver, that rewrites the virtual paths of the files of the project, before they are
+/// resolved by `file_resolver`, e.g. to strip `/templates` or to serve `/assets` from a
+/// different root. The first prefix, that matches, is used. Files, that don't match any
+/// prefix, and package files are not found, unless `with_passthrough` is set.
+///
+/// ```rust,ignore
+/// let assets = PrefixMapResolver::new(FileSystemResolver::new("/srv/assets".into()))
+///     .with_prefix("/assets", "/");
+/// let templates = PrefixMapResolver::new(FileSystemResolver::new("./templates".into()))
+///     .with_prefix("/templates", "/")
+///     .with_passthrough(true);
+/// let collection = TypstTemplateCollection::new(fonts)
+///     .add_file_resolver(assets)
+///     .add_file_resolver(templates);
+/// ```
+#[derive(Debug, Clone)]
+pub struct PrefixMapResolver<T> {
+    pub file_resolver: T,
+    prefixes: Vec<(PathBuf, VirtualPath)>,
+    passthrough: bool,
+}
+
+impl<T> PrefixMapResolver<T> {
+    pub fn new(file_resolver: T) -> Self {
+        Self {
+            file_resolver,
+            prefixes: Vec::new(),
+            passthrough: false,
+        }
+    }
+
+    /// Resolve files below `prefix` (e.g. `/assets/logo.png`) as files below `replacement`
+    /// (`/logo.png` for the replacement `/`). Prefixes are matched by path components, so
+    /// `/assets` does not match `/assets-old/logo.png`.
+    pub fn with_prefix<P, R>(mut self, prefix: P, replacement: R) -> Self
+    where
+        P: AsRef<Path>,
+        R: AsRef<Path>,
+    {
+        let prefix = VirtualPath::new(prefix).as_rootless_path().to_path_buf();
+        self.prefixes.push((prefix, VirtualPath::new(replacement)));
+        self
+    }
+
+    /// Resolve files, that don't match any prefix (and package files), unchanged.
+    pub fn with_passthrough(self, passthrough: bool) -> Self {
+        Self {
+            passthrough,
+            ..self
+        }
+    }
+
+    /// The id, that `file_resolver` resolves instead of `id`.
+    fn map(&self, id: FileId) -> Option<FileId> {
+        let Self {
+            prefixes,
+            passthrough,
+            ..
+        } = self;
+        let mapped = id.package().is_none().then(|| {
+            let path = id.vpath().as_rootless_path();
+            prefixes.iter().find_map(|(prefix, replacement)| {
+                let rest = path.strip_prefix(prefix).ok()?;
+                let path = VirtualPath::new(replacement.as_rooted_path().join(rest));
+                Some(FileId::new(None, path))
+            })
+        });
+        match mapped.flatten() {
+            Some(mapped) => Some(mapped),
+            None if *passthrough => Some(id),
+            None => None,
+        }
+    }
+}
+
+impl<T> FileResolver for PrefixMapResolver<T>
+where
+    T: FileResolver,
+{
+    fn resolve_binary(&self, id: FileId) -> FileResult<Cow<'_, Bytes>> {
+        let mapped = self.map(id).ok_or_else(|| not_found(id))?;
+        self.file_resolver.resolve_binary(mapped)
+    }
+
+    fn resolve_source(&self, id: FileId) -> FileResult<Cow<'_, Source>> {
+        let mapped = self.map(id).ok_or_else(|| not_found(id))?;
+        let source = self.file_resolver.resolve_source(mapped)?;
+        if mapped == id {
+            return Ok(source);
+        }
+        // Typst expects the id, that was requested.
+        Ok(Cow::Owned(Source::new(id, source.text().to_owned())))
+    }
+
+    fn resolve_path(&self, id: FileId) -> Option<PathBuf> {
+        self.file_resolver.resolve_path(self.map(id)?)
+    }
+
+    fn is_in_memory(&self) -> bool {
+        self.file_resolver.is_in_memory()
+    }
+
+    fn invalidate(&self, id: FileId) {
+        if let Some(mapped) = self.map(id) {
+            self.file_resolver.invalidate(mapped);
+        }
+    }
+
+    fn clear_cache(&self) {
+        self.file_resolver.clear_cache()
+    }
+
+    fn stats(&self) -> ResolverStats {
+        self.file_resolver.stats()
+    }
+
+    fn package_failure(&self, id: FileId) -> Option<PackageFailure> {
+        self.file_resolver.package_failure(self.map(id)?)
+    }
+}
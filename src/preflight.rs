@@ -0,0 +1,226 @@
+use std::{collections::HashSet, str::FromStr};
+
+use ecow::{eco_format, EcoString, EcoVec};
+use typst::{
+    diag::{FileError, SourceDiagnostic},
+    syntax::{
+        ast::{self, Arg, Expr},
+        package::{PackageManifest, PackageSpec},
+        FileId, LinkedNode, Source, Span, VirtualPath,
+    },
+};
+
+use crate::{file_resolver::ResolverErrors, TypstAsLibError, TypstTemplateCollection};
+
+/// Functions, that read the file of their first argument.
+static FILE_FUNCTIONS: &[&str] = &[
+    "bibliography",
+    "cbor",
+    "csv",
+    "image",
+    "json",
+    "plugin",
+    "read",
+    "toml",
+    "xml",
+    "yaml",
+];
+
+/// A file, that is referenced by a template, but can't be resolved.
+#[derive(Debug, Clone)]
+pub struct MissingFile {
+    /// The file, that contains the reference.
+    pub referenced_from: FileId,
+    /// The reference, e.g. the `#import` or the `image(..)` call.
+    pub span: Span,
+    /// The referenced path, as written in the template.
+    pub path: EcoString,
+    pub errors: ResolverErrors,
+}
+
+/// Parse `main` and all sources it imports or includes, and check, that every file and
+/// package, that is referenced with a string literal, can be resolved. Returns the
+/// referenced files or the missing ones.
+pub(crate) fn check_resolvable(
+    collection: &TypstTemplateCollection,
+    main: FileId,
+) -> Result<Vec<FileId>, Vec<MissingFile>> {
+    let main_reference = Reference {
+        path: eco_format!("{main:?}"),
+        span: Span::detached(),
+        is_source: true,
+    };
+    // Sources, that still have to be checked, with the file and the reference, that
+    // imports them.
+    let mut sources = vec![(main, main, main_reference)];
+    let mut seen = HashSet::from([main]);
+    let mut missing = Vec::new();
+    while let Some((id, referenced_from, reference)) = sources.pop() {
+        let source = match collection.resolve_source(id) {
+            Ok(source) => source.into_owned(),
+            Err(errors) => {
+                missing.push(MissingFile {
+                    referenced_from,
+                    span: reference.span,
+                    path: reference.path,
+                    errors,
+                });
+                continue;
+            }
+        };
+        for reference in references(&source) {
+            let resolved = resolve_reference(collection, id, &reference);
+            let target = match resolved {
+                Ok(target) => target,
+                Err(errors) => {
+                    missing.push(MissingFile {
+                        referenced_from: id,
+                        span: reference.span,
+                        path: reference.path,
+                        errors,
+                    });
+                    continue;
+                }
+            };
+            if !seen.insert(target) {
+                continue;
+            }
+            if reference.is_source {
+                sources.push((target, id, reference));
+            } else if let Err(errors) = collection.resolve_file(target) {
+                missing.push(MissingFile {
+                    referenced_from: id,
+                    span: reference.span,
+                    path: reference.path,
+                    errors,
+                });
+            }
+        }
+    }
+    if missing.is_empty() {
+        let mut files: Vec<FileId> = seen.into_iter().collect();
+        files.sort();
+        Ok(files)
+    } else {
+        Err(missing)
+    }
+}
+
+/// Parse `main` and all sources it imports or includes (with string literals) and return the
+/// syntax errors, without evaluating anything. Imports, that can't be resolved, are skipped,
+/// see `check_resolvable`.
+pub(crate) fn validate(
+    collection: &TypstTemplateCollection,
+    main: FileId,
+) -> Result<(), TypstAsLibError> {
+    let main_source = collection
+        .resolve_source(main)
+        .map_err(TypstAsLibError::Unresolved)?;
+    let mut sources = vec![main_source.into_owned()];
+    let mut seen = HashSet::from([main]);
+    let mut errors: EcoVec<SourceDiagnostic> = EcoVec::new();
+    while let Some(source) = sources.pop() {
+        errors.extend(source.root().errors().into_iter().map(Into::into));
+        for reference in references(&source) {
+            if !reference.is_source {
+                continue;
+            }
+            let Ok(target) = resolve_reference(collection, source.id(), &reference) else {
+                continue;
+            };
+            if !seen.insert(target) {
+                continue;
+            }
+            if let Ok(source) = collection.resolve_source(target) {
+                sources.push(source.into_owned());
+            }
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(TypstAsLibError::TypstSource(errors))
+    }
+}
+
+struct Reference {
+    path: EcoString,
+    span: Span,
+    /// Imported or included, so it has to be checked recursively.
+    is_source: bool,
+}
+
+fn references(source: &Source) -> Vec<Reference> {
+    fn walk(node: &LinkedNode, references: &mut Vec<Reference>) {
+        let reference = if let Some(import) = node.cast::<ast::ModuleImport>() {
+            string(import.source()).map(|path| (path, true))
+        } else if let Some(include) = node.cast::<ast::ModuleInclude>() {
+            string(include.source()).map(|path| (path, true))
+        } else if let Some(call) = node.cast::<ast::FuncCall>() {
+            match call.callee() {
+                Expr::Ident(ident) if FILE_FUNCTIONS.contains(&ident.as_str()) => {
+                    call.args().items().next().and_then(|arg| match arg {
+                        Arg::Pos(expr) => string(expr).map(|path| (path, false)),
+                        _ => None,
+                    })
+                }
+                _ => None,
+            }
+        } else {
+            None
+        };
+        if let Some((path, is_source)) = reference {
+            references.push(Reference {
+                path,
+                span: node.span(),
+                is_source,
+            });
+        }
+        for child in node.children() {
+            walk(&child, references);
+        }
+    }
+    let mut references = Vec::new();
+    walk(&LinkedNode::new(source.root()), &mut references);
+    references
+}
+
+fn string(expr: Expr) -> Option<EcoString> {
+    match expr {
+        Expr::Str(string) => Some(string.get()),
+        _ => None,
+    }
+}
+
+/// The `FileId`, that `path` refers to from the file `from`. Packages are resolved to their
+/// entrypoint.
+fn resolve_reference(
+    collection: &TypstTemplateCollection,
+    from: FileId,
+    reference: &Reference,
+) -> Result<FileId, ResolverErrors> {
+    let path = reference.path.as_str();
+    if !(reference.is_source && path.starts_with('@')) {
+        return Ok(from.join(path));
+    }
+    let manifest_error = |error: FileError| {
+        let mut errors = ResolverErrors::new(from);
+        errors.push("PackageManifest", error, None);
+        errors
+    };
+    let spec = PackageSpec::from_str(path)
+        .map_err(|error| manifest_error(FileError::Other(Some(error))))?;
+    let manifest_id = FileId::new(Some(spec.clone()), VirtualPath::new("typst.toml"));
+    let manifest = collection.resolve_file(manifest_id)?;
+    let manifest: PackageManifest = std::str::from_utf8(&manifest)
+        .map_err(|_| FileError::InvalidUtf8)
+        .and_then(|manifest| {
+            toml::from_str(manifest)
+                .map_err(|error| FileError::Other(Some(eco_format!("typst.toml: {error}"))))
+        })
+        .map_err(manifest_error)?;
+    Ok(FileId::new(
+        Some(spec),
+        VirtualPath::new(manifest.package.entrypoint.as_str()),
+    ))
+}
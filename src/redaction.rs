@@ -0,0 +1,101 @@
+use ecow::{EcoString, EcoVec};
+use typst::{
+    diag::SourceDiagnostic,
+    foundations::{Dict, Repr, Value},
+};
+
+/// Replacement for the values of sensitive inputs.
+pub(crate) const REDACTED: &str = "[redacted]";
+
+/// Values with fewer characters (e.g. `1` or `ab`) are not redacted, because they would
+/// also replace unrelated parts of the messages.
+const MIN_REDACTED_CHARS: usize = 3;
+
+/// Input keys, whose values must not show up in diagnostics or errors produced by a compile.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SensitiveInputs(Vec<EcoString>);
+
+impl SensitiveInputs {
+    pub(crate) fn extend<I, S>(&mut self, keys: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<EcoString>,
+    {
+        self.0.extend(keys.into_iter().map(Into::into));
+    }
+
+    /// The textual representations of all sensitive values in `inputs`. Keys are dotted
+    /// paths (`customer.iban`). If the value is a dictionary or array, all values inside
+    /// it are sensitive. Strings are included as they are and escaped like in their repr
+    /// (`"a \"b\""`), booleans, `none`, `auto` and values shorter than
+    /// `MIN_REDACTED_CHARS` are skipped.
+    pub(crate) fn values(&self, inputs: &Dict) -> Vec<EcoString> {
+        let mut values = Vec::new();
+        for key in &self.0 {
+            if let Some(value) = lookup(inputs, key) {
+                collect_values(value, &mut values);
+            }
+        }
+        // Longest first, so that a value containing another one is replaced as a whole.
+        values.sort_by_key(|v| std::cmp::Reverse(v.len()));
+        values.dedup();
+        values
+    }
+}
+
+fn lookup<'a>(inputs: &'a Dict, key: &str) -> Option<&'a Value> {
+    let mut segments = key.split('.');
+    let mut value = inputs.get(segments.next()?).ok()?;
+    for segment in segments {
+        let Value::Dict(dict) = value else {
+            return None;
+        };
+        value = dict.get(segment).ok()?;
+    }
+    Some(value)
+}
+
+fn collect_values(value: &Value, values: &mut Vec<EcoString>) {
+    let mut push = |text: EcoString| {
+        if text.chars().count() >= MIN_REDACTED_CHARS {
+            values.push(text);
+        }
+    };
+    match value {
+        Value::Dict(dict) => dict.iter().for_each(|(_, v)| collect_values(v, values)),
+        Value::Array(array) => array.iter().for_each(|v| collect_values(v, values)),
+        Value::Bool(_) | Value::None | Value::Auto => (),
+        Value::Str(s) => {
+            // Diagnostics show strings as repr, with escaped quotes and backslashes.
+            let repr = s.repr();
+            let escaped = &repr[1..repr.len() - 1];
+            if escaped != s.as_str() {
+                push(escaped.into());
+            }
+            push(s.as_str().into());
+        }
+        value => push(value.repr()),
+    }
+}
+
+pub(crate) fn redact(text: &str, values: &[EcoString]) -> EcoString {
+    let mut text = EcoString::from(text);
+    for value in values {
+        if text.contains(value.as_str()) {
+            text = text.replace(value.as_str(), REDACTED);
+        }
+    }
+    text
+}
+
+pub(crate) fn redact_diagnostics(diagnostics: &mut EcoVec<SourceDiagnostic>, values: &[EcoString]) {
+    if values.is_empty() {
+        return;
+    }
+    for diagnostic in diagnostics.make_mut() {
+        diagnostic.message = redact(&diagnostic.message, values);
+        for hint in diagnostic.hints.make_mut() {
+            *hint = redact(hint, values);
+        }
+    }
+}
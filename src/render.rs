@@ -0,0 +1,72 @@
+use std::io::Write;
+
+use ecow::eco_format;
+use typst::{
+    diag::HintedString,
+    layout::{Page, PageRanges},
+    model::Document,
+};
+
+use crate::{document_ext::DocumentExt, TypstAsLibError};
+
+/// Render a page as png with `ppi` pixels per inch.
+pub fn render_page(page: &Page, ppi: f32) -> Result<Vec<u8>, TypstAsLibError> {
+    let pixmap = typst_render::render(page, ppi / 72.0);
+    let png = pixmap
+        .encode_png()
+        .map_err(|err| HintedString::from(eco_format!("Could not encode png: {err}")))?;
+    Ok(png)
+}
+
+/// Render a page as png with `ppi` pixels per inch into `writer`.
+pub fn render_page_to<W>(page: &Page, ppi: f32, mut writer: W) -> Result<(), TypstAsLibError>
+where
+    W: Write,
+{
+    let png = render_page(page, ppi)?;
+    writer
+        .write_all(&png)
+        .map_err(|err| HintedString::from(eco_format!("Could not write png: {err}")))?;
+    Ok(())
+}
+
+/// Render the pages one after another, each when it is requested, e.g. to stream the
+/// pages of a large document, while the next ones are still rendered.
+///
+/// ```rust,ignore
+/// for (i, png) in render_pages_iter(&document, 144.0).enumerate() {
+///     send_page(i, png?)?;
+/// }
+/// ```
+pub fn render_pages_iter(
+    document: &Document,
+    ppi: f32,
+) -> impl Iterator<Item = Result<Vec<u8>, TypstAsLibError>> + '_ {
+    document
+        .pages
+        .iter()
+        .map(move |page| render_page(page, ppi))
+}
+
+/// Render only the pages in `ranges` (see `document_ext::parse_page_ranges`), e.g. the first
+/// page of a long document as preview.
+pub fn render_page_ranges(
+    document: &Document,
+    ppi: f32,
+    ranges: &PageRanges,
+) -> Result<Vec<Vec<u8>>, TypstAsLibError> {
+    document
+        .pages_in(ranges)
+        .map(|(_, page)| render_page(page, ppi))
+        .collect()
+}
+
+/// Render all pages as png with `ppi` pixels per inch (e.g. `72.0` for thumbnails,
+/// `300.0` for print quality).
+pub fn render_pages(document: &Document, ppi: f32) -> Result<Vec<Vec<u8>>, TypstAsLibError> {
+    document
+        .pages
+        .iter()
+        .map(|page| render_page(page, ppi))
+        .collect()
+}
@@ -0,0 +1,120 @@
+use crate::file_resolver::FileResolver;
+
+pub(crate) type BoxedFileResolver = Box<dyn FileResolver + Send + Sync + 'static>;
+
+/// Handle of a file resolver, that was added with
+/// `TypstTemplateCollection::register_file_resolver`. Used to remove or replace it later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ResolverHandle(u64);
+
+/// Priority of file resolvers, that are added with `add_file_resolver`.
+pub const DEFAULT_RESOLVER_PRIORITY: i32 = 0;
+
+/// Built-in resolvers (virtual packages, registered templates, edited sources) are resolved
+/// before all other resolvers.
+const BUILT_IN_PRIORITY: i32 = i32::MAX;
+
+struct Entry {
+    handle: ResolverHandle,
+    priority: i32,
+    file_resolver: BoxedFileResolver,
+}
+
+/// The file resolvers of a collection, ordered by priority (highest first). Resolvers with
+/// the same priority are resolved in the order they were added.
+#[derive(Default)]
+pub(crate) struct ResolverChain {
+    entries: Vec<Entry>,
+    next_handle: u64,
+}
+
+impl ResolverChain {
+    pub(crate) fn push(
+        &mut self,
+        file_resolver: BoxedFileResolver,
+        priority: i32,
+    ) -> ResolverHandle {
+        let handle = self.next_handle();
+        self.insert(Entry {
+            handle,
+            priority,
+            file_resolver,
+        });
+        handle
+    }
+
+    /// Add a built-in resolver before all other resolvers (also the built-in ones, that were
+    /// added before).
+    pub(crate) fn push_built_in(&mut self, file_resolver: BoxedFileResolver) {
+        let handle = self.next_handle();
+        self.entries.insert(
+            0,
+            Entry {
+                handle,
+                priority: BUILT_IN_PRIORITY,
+                file_resolver,
+            },
+        );
+    }
+
+    pub(crate) fn remove(&mut self, handle: ResolverHandle) -> Option<BoxedFileResolver> {
+        let index = self.position(handle)?;
+        Some(self.entries.remove(index).file_resolver)
+    }
+
+    pub(crate) fn replace(
+        &mut self,
+        handle: ResolverHandle,
+        file_resolver: BoxedFileResolver,
+    ) -> Option<BoxedFileResolver> {
+        let index = self.position(handle)?;
+        Some(std::mem::replace(
+            &mut self.entries[index].file_resolver,
+            file_resolver,
+        ))
+    }
+
+    /// Move the resolver behind the other resolvers with `priority`.
+    pub(crate) fn set_priority(&mut self, handle: ResolverHandle, priority: i32) -> bool {
+        let Some(index) = self.position(handle) else {
+            return false;
+        };
+        let entry = self.entries.remove(index);
+        self.insert(Entry { priority, ..entry });
+        true
+    }
+
+    pub(crate) fn priority(&self, handle: ResolverHandle) -> Option<i32> {
+        self.position(handle)
+            .map(|index| self.entries[index].priority)
+    }
+
+    /// A handle, that belongs to no resolver.
+    pub(crate) fn unused_handle(&mut self) -> ResolverHandle {
+        self.next_handle()
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &BoxedFileResolver> {
+        self.entries.iter().map(|entry| &entry.file_resolver)
+    }
+
+    fn next_handle(&mut self) -> ResolverHandle {
+        let handle = ResolverHandle(self.next_handle);
+        self.next_handle += 1;
+        handle
+    }
+
+    /// Insert `entry` behind the other entries with its priority.
+    fn insert(&mut self, entry: Entry) {
+        let index = self
+            .entries
+            .iter()
+            .position(|other| other.priority < entry.priority)
+            .unwrap_or(self.entries.len());
+        self.entries.insert(index, entry);
+    }
+
+    fn position(&self, handle: ResolverHandle) -> Option<usize> {
+        self.entries.iter().position(|entry| entry.handle == handle)
+    }
+}
@@ -0,0 +1,452 @@
+//! Conversion of any `serde::Serialize` type into typst values (feature `serde`).
+//!
+//! ```rust,ignore
+//! #[derive(Serialize)]
+//! struct Invoice {
+//!     number: u32,
+//!     customer: Option<String>,
+//!     items: Vec<Item>,
+//! }
+//!
+//! let input = typst_as_lib::serialize::to_dict(&invoice)?;
+//! let doc = template.compile_with_input(input).output?;
+//! ```
+//!
+//! Mapping:
+//! - `bool`, integers, floats, strings and `char` become `bool`, `int`, `float` and `str`.
+//!   Integers, that do not fit into an `i64`, become floats.
+//! - Byte slices (e.g. with `serde_bytes`) become `bytes`.
+//! - `None` and `()` become `none`, `Some(value)` becomes `value`.
+//! - Sequences and tuples become arrays, structs and maps become dictionaries.
+//! - Enums are externally tagged like in `serde_json`: unit variants become the name of the
+//!   variant, all other variants become a dictionary with the name of the variant as its only key.
+
+use ecow::{eco_format, EcoString};
+use serde::{ser, Serialize};
+use thiserror::Error;
+use typst::foundations::{Array, Bytes, Dict, IntoValue, Str, Value};
+
+#[derive(Debug, Clone, Error)]
+#[error("Could not convert to typst value: {0}")]
+pub struct SerializeError(EcoString);
+
+impl ser::Error for SerializeError {
+    fn custom<T>(msg: T) -> Self
+    where
+        T: std::fmt::Display,
+    {
+        SerializeError(eco_format!("{msg}"))
+    }
+}
+
+/// Convert `value` into a typst value.
+pub fn to_value<T>(value: &T) -> Result<Value, SerializeError>
+where
+    T: Serialize + ?Sized,
+{
+    value.serialize(ValueSerializer)
+}
+
+/// Convert `value` into a typst dictionary, e.g. to use it as input of a template.
+/// Fails, if `value` does not serialize to a struct or a map.
+pub fn to_dict<T>(value: &T) -> Result<Dict, SerializeError>
+where
+    T: Serialize + ?Sized,
+{
+    match to_value(value)? {
+        Value::Dict(dict) => Ok(dict),
+        other => Err(SerializeError(eco_format!(
+            "expected a struct or a map, found {}",
+            other.ty()
+        ))),
+    }
+}
+
+struct ValueSerializer;
+
+/// Integers, that do not fit into an `i64`, become floats.
+macro_rules! int {
+    ($v:expr) => {
+        i64::try_from($v).map_or(Value::Float($v as f64), Value::Int)
+    };
+}
+
+fn tagged(variant: &'static str, value: Value) -> Value {
+    let mut dict = Dict::new();
+    dict.insert(variant.into(), value);
+    Value::Dict(dict)
+}
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = SerializeError;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = SeqSerializer;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = MapSerializer;
+    type SerializeStructVariant = MapSerializer;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, SerializeError> {
+        Ok(Value::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value, SerializeError> {
+        Ok(Value::Int(v.into()))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value, SerializeError> {
+        Ok(Value::Int(v.into()))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value, SerializeError> {
+        Ok(Value::Int(v.into()))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value, SerializeError> {
+        Ok(Value::Int(v))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Value, SerializeError> {
+        Ok(int!(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value, SerializeError> {
+        Ok(Value::Int(v.into()))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value, SerializeError> {
+        Ok(Value::Int(v.into()))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value, SerializeError> {
+        Ok(Value::Int(v.into()))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value, SerializeError> {
+        Ok(int!(v))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Value, SerializeError> {
+        Ok(int!(v))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value, SerializeError> {
+        Ok(Value::Float(v.into()))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value, SerializeError> {
+        Ok(Value::Float(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value, SerializeError> {
+        Ok(Value::Str(v.into()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, SerializeError> {
+        Ok(Value::Str(v.into()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value, SerializeError> {
+        Ok(Value::Bytes(Bytes::from(v.to_vec())))
+    }
+
+    fn serialize_none(self) -> Result<Value, SerializeError> {
+        Ok(Value::None)
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Value, SerializeError>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, SerializeError> {
+        Ok(Value::None)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, SerializeError> {
+        Ok(Value::None)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Value, SerializeError> {
+        Ok(Value::Str(variant.into()))
+    }
+
+    fn serialize_newtype_struct<T>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Value, SerializeError>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value, SerializeError>
+    where
+        T: Serialize + ?Sized,
+    {
+        Ok(tagged(variant, to_value(value)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer, SerializeError> {
+        Ok(SeqSerializer {
+            variant: None,
+            array: Array::with_capacity(len.unwrap_or_default()),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer, SerializeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer, SerializeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer, SerializeError> {
+        Ok(SeqSerializer {
+            variant: Some(variant),
+            array: Array::with_capacity(len),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<MapSerializer, SerializeError> {
+        Ok(MapSerializer {
+            variant: None,
+            dict: Dict::new(),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<MapSerializer, SerializeError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<MapSerializer, SerializeError> {
+        Ok(MapSerializer {
+            variant: Some(variant),
+            dict: Dict::new(),
+            key: None,
+        })
+    }
+}
+
+struct SeqSerializer {
+    /// Set for tuple variants.
+    variant: Option<&'static str>,
+    array: Array,
+}
+
+impl SeqSerializer {
+    fn push<T>(&mut self, value: &T) -> Result<(), SerializeError>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.array.push(to_value(value)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value, SerializeError> {
+        let Self { variant, array } = self;
+        let value = array.into_value();
+        Ok(match variant {
+            Some(variant) => tagged(variant, value),
+            None => value,
+        })
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Value;
+    type Error = SerializeError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), SerializeError>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, SerializeError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Value;
+    type Error = SerializeError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), SerializeError>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, SerializeError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Value;
+    type Error = SerializeError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), SerializeError>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, SerializeError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SeqSerializer {
+    type Ok = Value;
+    type Error = SerializeError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), SerializeError>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, SerializeError> {
+        self.finish()
+    }
+}
+
+struct MapSerializer {
+    /// Set for struct variants.
+    variant: Option<&'static str>,
+    dict: Dict,
+    /// Key of the next value of a map.
+    key: Option<Str>,
+}
+
+impl MapSerializer {
+    fn finish(self) -> Result<Value, SerializeError> {
+        let Self { variant, dict, .. } = self;
+        let value = Value::Dict(dict);
+        Ok(match variant {
+            Some(variant) => tagged(variant, value),
+            None => value,
+        })
+    }
+}
+
+/// Dictionary keys have to be strings. Numbers, booleans and chars are converted.
+fn key(value: Value) -> Result<Str, SerializeError> {
+    match value {
+        Value::Str(key) => Ok(key),
+        Value::Int(key) => Ok(eco_format!("{key}").into()),
+        Value::Bool(key) => Ok(eco_format!("{key}").into()),
+        other => Err(SerializeError(eco_format!(
+            "map keys have to be strings, found {}",
+            other.ty()
+        ))),
+    }
+}
+
+impl ser::SerializeMap for MapSerializer {
+    type Ok = Value;
+    type Error = SerializeError;
+
+    fn serialize_key<T>(&mut self, key_value: &T) -> Result<(), SerializeError>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.key = Some(key(to_value(key_value)?)?);
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), SerializeError>
+    where
+        T: Serialize + ?Sized,
+    {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| SerializeError("value without a key".into()))?;
+        self.dict.insert(key, to_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, SerializeError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for MapSerializer {
+    type Ok = Value;
+    type Error = SerializeError;
+
+    fn serialize_field<T>(&mut self, name: &'static str, value: &T) -> Result<(), SerializeError>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.dict.insert(name.into(), to_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, SerializeError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for MapSerializer {
+    type Ok = Value;
+    type Error = SerializeError;
+
+    fn serialize_field<T>(&mut self, name: &'static str, value: &T) -> Result<(), SerializeError>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.dict.insert(name.into(), to_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, SerializeError> {
+        self.finish()
+    }
+}
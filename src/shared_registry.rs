@@ -0,0 +1,130 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, OnceLock, RwLock,
+    },
+};
+
+use ecow::eco_format;
+use typst::{
+    diag::{FileError, FileResult},
+    foundations::Bytes,
+    syntax::{FileId, Source},
+    text::Font,
+};
+
+use crate::{file_resolver::FileResolver, util::not_found, FileIdNewType, SourceNewType};
+
+static GLOBAL_REGISTRY: OnceLock<SharedRegistry> = OnceLock::new();
+
+/// Registry for fonts and static files, that can be shared between collections.
+/// Cloning is cheap, all clones point to the same registry.
+///
+/// Adding fonts or files invalidates the documents of all collections with this registry in
+/// the `DocumentCache` (see `generation`).
+#[derive(Debug, Clone, Default)]
+pub struct SharedRegistry {
+    fonts: Arc<RwLock<Vec<Font>>>,
+    sources: Arc<RwLock<HashMap<FileId, Source>>>,
+    binaries: Arc<RwLock<HashMap<FileId, Bytes>>>,
+    generation: Arc<AtomicU64>,
+}
+
+impl SharedRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The process-wide registry.
+    pub fn global() -> Self {
+        GLOBAL_REGISTRY.get_or_init(Default::default).clone()
+    }
+
+    /// Add Fonts
+    pub fn add_fonts<I, F>(&self, fonts: I)
+    where
+        I: IntoIterator<Item = F>,
+        F: Into<Font>,
+    {
+        if let Ok(mut registered) = self.fonts.write() {
+            registered.extend(fonts.into_iter().map(Into::into));
+        }
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Add sources. See `TypstTemplateCollection::with_static_source_file_resolver` for
+    /// the possible item types.
+    pub fn add_static_sources<IS, S>(&self, sources: IS)
+    where
+        IS: IntoIterator<Item = S>,
+        S: Into<SourceNewType>,
+    {
+        if let Ok(mut registered) = self.sources.write() {
+            registered.extend(sources.into_iter().map(|s| {
+                let SourceNewType(s) = s.into();
+                (s.id(), s)
+            }));
+        }
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Add binaries as key value pairs.
+    pub fn add_static_files<IB, F, B>(&self, binaries: IB)
+    where
+        IB: IntoIterator<Item = (F, B)>,
+        F: Into<FileIdNewType>,
+        B: Into<Bytes>,
+    {
+        if let Ok(mut registered) = self.binaries.write() {
+            registered.extend(binaries.into_iter().map(|(id, b)| {
+                let FileIdNewType(id) = id.into();
+                (id, b.into())
+            }));
+        }
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counter, that is incremented on every change of the registry. Part of the key of the
+    /// `DocumentCache`, so documents are compiled again after a shared file was replaced.
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Relaxed)
+    }
+
+    /// All registered fonts. `Font` is reference counted, so the font data is not copied.
+    pub fn fonts(&self) -> FileResult<Vec<Font>> {
+        self.fonts
+            .read()
+            .map(|fonts| fonts.clone())
+            .map_err(|_| FileError::Other(Some(eco_format!("Could not lock registry"))))
+    }
+}
+
+impl FileResolver for SharedRegistry {
+    fn resolve_binary(&self, id: FileId) -> FileResult<Cow<'_, Bytes>> {
+        let binaries = self
+            .binaries
+            .read()
+            .map_err(|_| FileError::Other(Some(eco_format!("Could not lock registry"))))?;
+        binaries
+            .get(&id)
+            .map(|b| Cow::Owned(b.clone()))
+            .ok_or_else(|| not_found(id))
+    }
+
+    fn resolve_source(&self, id: FileId) -> FileResult<Cow<'_, Source>> {
+        let sources = self
+            .sources
+            .read()
+            .map_err(|_| FileError::Other(Some(eco_format!("Could not lock registry"))))?;
+        sources
+            .get(&id)
+            .map(|s| Cow::Owned(s.clone()))
+            .ok_or_else(|| not_found(id))
+    }
+
+    fn is_in_memory(&self) -> bool {
+        true
+    }
+}
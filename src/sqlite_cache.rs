@@ -0,0 +1,463 @@
+use std::{
+    borrow::Cow,
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::UNIX_EPOCH,
+};
+
+use ecow::eco_format;
+use rusqlite::{params, Connection, OptionalExtension};
+use typst::{
+    diag::{FileError, FileResult},
+    foundations::{Bytes, Dict},
+    model::Document,
+    syntax::{package::PackageSpec, FileId, Source, VirtualPath},
+    utils::hash128,
+};
+
+use crate::{
+    file_resolver::{FileResolver, PackageFailure},
+    util::{bytes_to_source, file_id_key},
+    TypstAsLibError, TypstTemplateCollection,
+};
+
+/// Cache, that stores resolved files, packages and compiled artifacts in a single SQLite file.
+/// Cloning is cheap, all clones share the same connection.
+#[derive(Debug, Clone)]
+pub struct SqliteCache(Arc<Mutex<Connection>>);
+
+impl SqliteCache {
+    /// Open (or create) the cache database at `path`.
+    pub fn open<P>(path: P) -> FileResult<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let connection = Connection::open(path).map_err(sqlite_error)?;
+        Self::from_connection(connection)
+    }
+
+    /// Cache, that only lives in memory.
+    pub fn open_in_memory() -> FileResult<Self> {
+        let connection = Connection::open_in_memory().map_err(sqlite_error)?;
+        Self::from_connection(connection)
+    }
+
+    fn from_connection(connection: Connection) -> FileResult<Self> {
+        connection
+            .execute_batch(
+                "CREATE TABLE IF NOT EXISTS files (id TEXT PRIMARY KEY, data BLOB NOT NULL);
+                CREATE TABLE IF NOT EXISTS file_times (id TEXT PRIMARY KEY, modified INTEGER NOT NULL);
+                CREATE TABLE IF NOT EXISTS artifacts (key TEXT PRIMARY KEY, data BLOB NOT NULL);
+                CREATE TABLE IF NOT EXISTS compiled_artifacts
+                    (key TEXT PRIMARY KEY, dependencies TEXT NOT NULL, data BLOB NOT NULL);",
+            )
+            .map_err(sqlite_error)?;
+        Ok(Self(Arc::new(Mutex::new(connection))))
+    }
+
+    /// Wrap `file_resolver`, so that resolved files are stored in this cache. Files, that the
+    /// resolver reads from disk (`FileResolver::resolve_path`), are read again, when their
+    /// modification time changed. Resolvers, that are in memory (`FileResolver::is_in_memory`),
+    /// are not cached, so their files can change. `FileResolver::clear_cache` removes the files
+    /// of the resolver from the cache.
+    pub fn cached<T>(&self, file_resolver: T) -> SqliteCachedFileResolver<T> {
+        SqliteCachedFileResolver {
+            file_resolver,
+            cache: self.clone(),
+            files: Default::default(),
+        }
+    }
+
+    pub fn get_file(&self, id: FileId) -> FileResult<Option<Vec<u8>>> {
+        self.get("SELECT data FROM files WHERE id = ?1", &file_id_key(id))
+    }
+
+    pub fn put_file(&self, id: FileId, data: &[u8]) -> FileResult<()> {
+        self.put_file_modified(id, data, None)
+    }
+
+    /// Remove the file `id`, so it is resolved again.
+    pub fn remove_file(&self, id: FileId) -> FileResult<()> {
+        let connection = self.lock()?;
+        let key = file_id_key(id);
+        connection
+            .execute("DELETE FROM files WHERE id = ?1", params![key])
+            .and_then(|_| connection.execute("DELETE FROM file_times WHERE id = ?1", params![key]))
+            .map_err(sqlite_error)?;
+        Ok(())
+    }
+
+    /// The file `id`, if it was stored with the modification time `modified`.
+    fn get_file_modified(&self, id: FileId, modified: Option<i64>) -> FileResult<Option<Vec<u8>>> {
+        let connection = self.lock()?;
+        connection
+            .query_row(
+                "SELECT data FROM files WHERE id = ?1
+                    AND (SELECT modified FROM file_times WHERE file_times.id = files.id) IS ?2",
+                params![file_id_key(id), modified],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(sqlite_error)
+    }
+
+    fn put_file_modified(&self, id: FileId, data: &[u8], modified: Option<i64>) -> FileResult<()> {
+        let connection = self.lock()?;
+        let key = file_id_key(id);
+        connection
+            .execute(
+                "INSERT OR REPLACE INTO files (id, data) VALUES (?1, ?2)",
+                params![key, data],
+            )
+            .map_err(sqlite_error)?;
+        match modified {
+            Some(modified) => connection.execute(
+                "INSERT OR REPLACE INTO file_times (id, modified) VALUES (?1, ?2)",
+                params![key, modified],
+            ),
+            None => connection.execute("DELETE FROM file_times WHERE id = ?1", params![key]),
+        }
+        .map_err(sqlite_error)?;
+        Ok(())
+    }
+
+    /// Get an artifact, that was stored with `put_artifact`. The artifact is not checked
+    /// against the files it was built from, see `TypstTemplateCollection::compile_artifact`
+    /// for artifacts of compiles.
+    pub fn get_artifact(&self, key: &str) -> FileResult<Option<Vec<u8>>> {
+        self.get("SELECT data FROM artifacts WHERE key = ?1", key)
+    }
+
+    /// Store a compiled artifact (e.g. an exported pdf), see `get_artifact`.
+    pub fn put_artifact(&self, key: &str, data: &[u8]) -> FileResult<()> {
+        self.put(
+            "INSERT OR REPLACE INTO artifacts (key, data) VALUES (?1, ?2)",
+            key,
+            data,
+        )
+    }
+
+    /// Remove all cached files and artifacts.
+    pub fn clear(&self) -> FileResult<()> {
+        let connection = self.lock()?;
+        connection
+            .execute_batch(
+                "DELETE FROM files; DELETE FROM file_times; DELETE FROM artifacts;
+                DELETE FROM compiled_artifacts;",
+            )
+            .map_err(sqlite_error)
+    }
+
+    /// A compiled artifact with the fingerprints of the files it was built from.
+    fn get_compiled(&self, key: &str) -> FileResult<Option<(Fingerprints, Vec<u8>)>> {
+        let connection = self.lock()?;
+        let compiled: Option<(String, Vec<u8>)> = connection
+            .query_row(
+                "SELECT dependencies, data FROM compiled_artifacts WHERE key = ?1",
+                params![key],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(sqlite_error)?;
+        let Some((dependencies, data)) = compiled else {
+            return Ok(None);
+        };
+        // Lines of `{fingerprint} {file_id_key}`.
+        let dependencies = dependencies
+            .lines()
+            .map(|line| {
+                let (fingerprint, key) = line.split_once(' ')?;
+                Some((
+                    file_id_from_key(key)?,
+                    u128::from_str_radix(fingerprint, 16).ok()?,
+                ))
+            })
+            .collect::<Option<_>>();
+        Ok(dependencies.map(|dependencies| (dependencies, data)))
+    }
+
+    fn put_compiled(
+        &self,
+        key: &str,
+        dependencies: &[(FileId, u128)],
+        data: &[u8],
+    ) -> FileResult<()> {
+        let dependencies: Vec<_> = dependencies
+            .iter()
+            .map(|(id, fingerprint)| format!("{fingerprint:032x} {}", file_id_key(*id)))
+            .collect();
+        let connection = self.lock()?;
+        connection
+            .execute(
+                "INSERT OR REPLACE INTO compiled_artifacts (key, dependencies, data)
+                    VALUES (?1, ?2, ?3)",
+                params![key, dependencies.join("\n"), data],
+            )
+            .map_err(sqlite_error)?;
+        Ok(())
+    }
+
+    fn get(&self, sql: &str, key: &str) -> FileResult<Option<Vec<u8>>> {
+        let connection = self.lock()?;
+        connection
+            .query_row(sql, params![key], |row| row.get(0))
+            .optional()
+            .map_err(sqlite_error)
+    }
+
+    fn put(&self, sql: &str, key: &str, data: &[u8]) -> FileResult<()> {
+        let connection = self.lock()?;
+        connection
+            .execute(sql, params![key, data])
+            .map_err(sqlite_error)?;
+        Ok(())
+    }
+
+    fn lock(&self) -> FileResult<std::sync::MutexGuard<'_, Connection>> {
+        let SqliteCache(connection) = self;
+        connection
+            .lock()
+            .map_err(|_| FileError::Other(Some(eco_format!("Could not lock cache"))))
+    }
+}
+
+fn sqlite_error(error: rusqlite::Error) -> FileError {
+    FileError::Other(Some(eco_format!("SQLite cache error: {error}")))
+}
+
+/// The files of a compile with the hash of their content.
+type Fingerprints = Vec<(FileId, u128)>;
+
+/// Inverse of `file_id_key`.
+fn file_id_from_key(key: &str) -> Option<FileId> {
+    if !key.starts_with('@') {
+        return Some(FileId::new(None, VirtualPath::new(key)));
+    }
+    // `@namespace/name:version/path`, the path starts at the second slash.
+    let (_, rest) = key.split_once('/')?;
+    let split = key.len() - rest.len() + rest.find('/')?;
+    let package: PackageSpec = key[..split].parse().ok()?;
+    Some(FileId::new(Some(package), VirtualPath::new(&key[split..])))
+}
+
+/// Modification time of the file on disk, that `file_resolver` reads `id` from.
+fn modified_time<T>(file_resolver: &T, id: FileId) -> Option<i64>
+where
+    T: FileResolver,
+{
+    let path: PathBuf = file_resolver.resolve_path(id)?;
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    let nanos = modified.duration_since(UNIX_EPOCH).ok()?.as_nanos();
+    i64::try_from(nanos).ok()
+}
+
+/// Fingerprint of the current content of `id`, as the collection resolves it.
+fn fingerprint(collection: &TypstTemplateCollection, id: FileId) -> Option<u128> {
+    if let Ok(file) = collection.resolve_file(id) {
+        return Some(hash128(file.as_slice()));
+    }
+    let source = collection.resolve_source(id).ok()?;
+    Some(hash128(source.text().as_bytes()))
+}
+
+/// See `TypstTemplateCollection::compile_artifact`.
+pub(crate) fn compile_artifact<E>(
+    collection: &TypstTemplateCollection,
+    cache: &SqliteCache,
+    kind: &str,
+    main_source_id: FileId,
+    inputs: Dict,
+    export: E,
+) -> Result<Vec<u8>, TypstAsLibError>
+where
+    E: FnOnce(&Document) -> Result<Vec<u8>, TypstAsLibError>,
+{
+    let key = format!(
+        "{kind}:{:032x}",
+        collection.artifact_key(main_source_id, inputs.clone())
+    );
+    if let Ok(Some((dependencies, data))) = cache.get_compiled(&key) {
+        let unchanged = dependencies
+            .iter()
+            .all(|(id, expected)| fingerprint(collection, *id) == Some(*expected));
+        if unchanged {
+            return Ok(data);
+        }
+    }
+    let output = collection.compile_with_input_detailed(main_source_id, inputs);
+    let data = export(&output.output?)?;
+    let dependencies: Vec<_> = output
+        .dependencies
+        .iter()
+        .filter_map(|id| Some((*id, fingerprint(collection, *id)?)))
+        .collect();
+    let _ = cache.put_compiled(&key, &dependencies, &data);
+    Ok(data)
+}
+
+/// File resolver, that stores the resolved files in a `SqliteCache`. See `SqliteCache::cached`.
+pub struct SqliteCachedFileResolver<T> {
+    pub file_resolver: T,
+    pub cache: SqliteCache,
+    /// Files, that were read from or written to the cache, for `clear_cache`.
+    files: Mutex<HashSet<FileId>>,
+}
+
+impl<T> SqliteCachedFileResolver<T>
+where
+    T: FileResolver,
+{
+    /// The cached file `id`, if it is still up to date.
+    fn lookup(&self, id: FileId, modified: Option<i64>) -> Option<Vec<u8>> {
+        if self.file_resolver.is_in_memory() {
+            return None;
+        }
+        let cached = self.cache.get_file_modified(id, modified).ok()??;
+        self.remember(id);
+        Some(cached)
+    }
+
+    fn store(&self, id: FileId, data: &[u8], modified: Option<i64>) {
+        if self.file_resolver.is_in_memory() {
+            return;
+        }
+        if self.cache.put_file_modified(id, data, modified).is_ok() {
+            self.remember(id);
+        }
+    }
+
+    fn remember(&self, id: FileId) {
+        if let Ok(mut files) = self.files.lock() {
+            files.insert(id);
+        }
+    }
+}
+
+impl<T> FileResolver for SqliteCachedFileResolver<T>
+where
+    T: FileResolver,
+{
+    fn resolve_binary(&self, id: FileId) -> FileResult<Cow<'_, Bytes>> {
+        let Self { file_resolver, .. } = self;
+        let modified = modified_time(file_resolver, id);
+        if let Some(cached) = self.lookup(id, modified) {
+            return Ok(Cow::Owned(Bytes::from(cached)));
+        }
+        let resolved = file_resolver.resolve_binary(id)?;
+        self.store(id, resolved.as_slice(), modified);
+        Ok(resolved)
+    }
+
+    fn resolve_source(&self, id: FileId) -> FileResult<Cow<'_, Source>> {
+        let Self { file_resolver, .. } = self;
+        let modified = modified_time(file_resolver, id);
+        if let Some(cached) = self.lookup(id, modified) {
+            return Ok(Cow::Owned(bytes_to_source(id, &cached)?));
+        }
+        let resolved = file_resolver.resolve_source(id)?;
+        self.store(id, resolved.text().as_bytes(), modified);
+        Ok(resolved)
+    }
+
+    fn resolve_path(&self, id: FileId) -> Option<PathBuf> {
+        self.file_resolver.resolve_path(id)
+    }
+
+    fn invalidate(&self, id: FileId) {
+        let _ = self.cache.remove_file(id);
+        self.file_resolver.invalidate(id)
+    }
+
+    fn clear_cache(&self) {
+        if let Ok(mut files) = self.files.lock() {
+            for id in files.drain() {
+                let _ = self.cache.remove_file(id);
+            }
+        }
+        self.file_resolver.clear_cache()
+    }
+
+    fn package_failure(&self, id: FileId) -> Option<PackageFailure> {
+        self.file_resolver.package_failure(id)
+    }
+}
+
+#[cfg(feature = "packages")]
+mod packages {
+    use binstall_tar::Archive;
+    use rusqlite::params;
+    use typst::{
+        diag::FileResult,
+        syntax::{package::PackageSpec, FileId, VirtualPath},
+    };
+
+    use crate::{
+        cached_file_resolver::{CachedFileResolver, IntoCachedFileResolver},
+        package_resolver::{
+            read_archive_files, CreateBytesOrSource, PackageResolver, PackageResolverCache,
+            SourceOrBytesCreator,
+        },
+    };
+
+    use super::{sqlite_error, SqliteCache};
+
+    impl PackageResolverCache for SqliteCache {
+        fn lookup_cached<T>(&self, _package: &PackageSpec, id: FileId) -> FileResult<Option<T>>
+        where
+            SourceOrBytesCreator: CreateBytesOrSource<T>,
+        {
+            let Some(data) = self.get_file(id)? else {
+                return Ok(None);
+            };
+            let cached = SourceOrBytesCreator.try_create(id, &data)?;
+            Ok(Some(cached))
+        }
+
+        fn package_files(&self, package: &PackageSpec) -> FileResult<Vec<(FileId, Vec<u8>)>> {
+            // Keys of package files are `{package}{rooted path}`, see `file_id_key`.
+            let prefix = format!("{package}");
+            let connection = self.lock()?;
+            let mut statement = connection
+                .prepare("SELECT id, data FROM files WHERE substr(id, 1, length(?1)) = ?1")
+                .map_err(sqlite_error)?;
+            let rows = statement
+                .query_map(params![prefix], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, Vec<u8>>(1)?))
+                })
+                .map_err(sqlite_error)?;
+            let mut files = Vec::new();
+            for row in rows {
+                let (key, data) = row.map_err(sqlite_error)?;
+                let Some(path) = key
+                    .strip_prefix(&prefix)
+                    .filter(|path| path.starts_with('/'))
+                else {
+                    continue;
+                };
+                files.push((
+                    FileId::new(Some(package.clone()), VirtualPath::new(path)),
+                    data,
+                ));
+            }
+            Ok(files)
+        }
+
+        fn cache_archive(
+            &self,
+            archive: Archive<&[u8]>,
+            package: &PackageSpec,
+        ) -> FileResult<bool> {
+            for (id, data) in read_archive_files(archive, package)? {
+                self.put_file(id, &data)?;
+            }
+            Ok(true)
+        }
+    }
+
+    impl IntoCachedFileResolver for PackageResolver<SqliteCache> {
+        fn into_cached(self) -> CachedFileResolver<Self> {
+            CachedFileResolver::new(self).with_in_memory_source_cache()
+        }
+    }
+}
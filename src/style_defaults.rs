@@ -0,0 +1,82 @@
+use ecow::EcoString;
+use typst::{
+    foundations::{Smart, Styles},
+    layout::{Abs, Length, Margin, PageElem, Paper, Rel, Sides},
+    text::{FontFamily, FontList, Lang, Region, TextElem, TextSize},
+};
+
+/// Default styles for all documents of a collection, so operators can change page size,
+/// margins and fonts without editing every template. Set and show rules of the templates
+/// still win. Fields, that are `None` (or empty), keep the defaults of typst.
+///
+/// ```rust,ignore
+/// let collection = collection.with_style_defaults(StyleDefaults {
+///     paper: Some(Paper::A4),
+///     margin: Some(Sides::splat(Abs::cm(2.0))),
+///     font: vec!["Brand Sans".into()],
+///     font_size: Some(Abs::pt(10.5)),
+///     lang: Some(Lang::GERMAN),
+///     ..Default::default()
+/// });
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct StyleDefaults {
+    /// Page width and height of a paper size. `page_width` and `page_height` take precedence.
+    pub paper: Option<Paper>,
+    pub page_width: Option<Abs>,
+    pub page_height: Option<Abs>,
+    pub margin: Option<Sides<Abs>>,
+    /// Font families, the first family has the highest priority.
+    pub font: Vec<EcoString>,
+    pub font_size: Option<Abs>,
+    pub lang: Option<Lang>,
+    pub region: Option<Region>,
+}
+
+impl StyleDefaults {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the styles to `styles`, e.g. the styles of a `typst::Library`.
+    pub fn apply(&self, styles: &mut Styles) {
+        let Self {
+            paper,
+            page_width,
+            page_height,
+            margin,
+            font,
+            font_size,
+            lang,
+            region,
+        } = self;
+        let width = page_width.or(paper.map(|paper| paper.width()));
+        let height = page_height.or(paper.map(|paper| paper.height()));
+        if let Some(width) = width {
+            styles.set(PageElem::set_width(Smart::Custom(width.into())));
+        }
+        if let Some(height) = height {
+            styles.set(PageElem::set_height(Smart::Custom(height.into())));
+        }
+        if let Some(margin) = margin {
+            let sides = margin.map(|side| Some(Smart::Custom(Rel::from(Length::from(side)))));
+            styles.set(PageElem::set_margin(Margin {
+                sides,
+                two_sided: None,
+            }));
+        }
+        if !font.is_empty() {
+            let families = font.iter().map(|family| FontFamily::new(family)).collect();
+            styles.set(TextElem::set_font(FontList(families)));
+        }
+        if let Some(font_size) = font_size {
+            styles.set(TextElem::set_size(TextSize((*font_size).into())));
+        }
+        if let Some(lang) = lang {
+            styles.set(TextElem::set_lang(*lang));
+        }
+        if let Some(region) = region {
+            styles.set(TextElem::set_region(Some(*region)));
+        }
+    }
+}
@@ -0,0 +1,31 @@
+//! Discovery of system-installed fonts (feature `system-fonts`), similar to the font search
+//! of the typst cli. See `TypstTemplateCollection::with_system_fonts`.
+
+use fontdb::{Database, Source};
+use typst::text::FontInfo;
+
+use crate::font_slot::FontSlot;
+
+/// All fonts, that are installed on the system. The fonts are only parsed, when they are used.
+pub fn system_fonts() -> Vec<FontSlot> {
+    let mut db = Database::new();
+    db.load_system_fonts();
+    font_slots(&db)
+}
+
+fn font_slots(db: &Database) -> Vec<FontSlot> {
+    db.faces()
+        .filter_map(|face| {
+            let path = match &face.source {
+                Source::File(path) | Source::SharedFile(path, _) => path,
+                Source::Binary(_) => return None,
+            };
+            let info = db.with_face_data(face.id, FontInfo::new).flatten()?;
+            Some(FontSlot::from_path_with_info(
+                path.clone(),
+                face.index,
+                info,
+            ))
+        })
+        .collect()
+}
@@ -0,0 +1,102 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use ecow::{eco_format, EcoString};
+use typst::{
+    diag::{FileError, FileResult},
+    foundations::Bytes,
+    syntax::{FileId, Source, VirtualPath},
+};
+
+use crate::{file_resolver::FileResolver, util::not_found};
+
+/// Templates, that are registered by name (e.g. `"invoice"`), so they can be compiled with
+/// `TypstTemplateCollection::compile_by_name` instead of mapping names to `FileId`s by hand.
+/// Imports inside the templates (`#import "header.typ"`) are resolved by the other file
+/// resolvers of the collection. Cloning is cheap, all clones share the same templates.
+#[derive(Debug, Clone, Default)]
+pub struct TemplateRegistry {
+    templates: Arc<RwLock<HashMap<EcoString, Source>>>,
+}
+
+/// `FileId` of the registered template `name`.
+pub fn registered_template_file_id(name: &str) -> FileId {
+    FileId::new(None, VirtualPath::new(name))
+}
+
+impl TemplateRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `source` as `name`. Replaces the template, if it is already registered.
+    pub fn register<N, S>(&self, name: N, source: S) -> FileId
+    where
+        N: Into<EcoString>,
+        S: Into<String>,
+    {
+        let name = name.into();
+        let id = registered_template_file_id(&name);
+        if let Ok(mut templates) = self.templates.write() {
+            templates.insert(name, Source::new(id, source.into()));
+        }
+        id
+    }
+
+    /// Returns `true`, if the template was registered.
+    pub fn remove(&self, name: &str) -> bool {
+        self.templates
+            .write()
+            .is_ok_and(|mut templates| templates.remove(name).is_some())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.templates
+            .read()
+            .is_ok_and(|templates| templates.contains_key(name))
+    }
+
+    /// Names of all registered templates, sorted.
+    pub fn names(&self) -> Vec<EcoString> {
+        let mut names: Vec<EcoString> = self
+            .templates
+            .read()
+            .map(|templates| templates.keys().cloned().collect())
+            .unwrap_or_default();
+        names.sort();
+        names
+    }
+
+    fn get(&self, id: FileId) -> FileResult<Source> {
+        if id.package().is_some() {
+            return Err(not_found(id));
+        }
+        let templates = self
+            .templates
+            .read()
+            .map_err(|_| FileError::Other(Some(eco_format!("Could not lock template registry"))))?;
+        let name = id.vpath().as_rootless_path().to_string_lossy();
+        templates
+            .get(name.as_ref())
+            .cloned()
+            .ok_or_else(|| not_found(id))
+    }
+}
+
+impl FileResolver for TemplateRegistry {
+    fn resolve_binary(&self, id: FileId) -> FileResult<Cow<'_, Bytes>> {
+        let source = self.get(id)?;
+        Ok(Cow::Owned(Bytes::from(source.text().as_bytes().to_vec())))
+    }
+
+    fn resolve_source(&self, id: FileId) -> FileResult<Cow<'_, Source>> {
+        self.get(id).map(Cow::Owned)
+    }
+
+    fn is_in_memory(&self) -> bool {
+        true
+    }
+}
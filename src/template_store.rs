@@ -0,0 +1,192 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use ecow::{eco_format, EcoString};
+use typst::{
+    diag::{FileError, FileResult},
+    foundations::Bytes,
+    syntax::{FileId, Source, VirtualPath},
+};
+
+use crate::{file_resolver::FileResolver, util::not_found};
+
+/// Callback, that is called with the template, that was added or changed.
+pub type OnTemplateChange = Box<dyn Fn(&TemplateInfo) + Send + Sync + 'static>;
+
+/// Storage for versioned templates (e.g. in a database or a CMS).
+///
+/// Contract for implementations:
+/// - `get` with `version: None` returns the latest version of the template.
+/// - `get` returns `FileError::NotFound` if the template or the version does not exist.
+/// - `list` returns the latest version of each template.
+/// - `watch` calls `on_change` each time a template is added or changed. Stores, that cannot
+///   notify about changes, keep the default implementation, that returns an error.
+///
+/// Add it to a collection with `TypstTemplateCollection::with_template_store` and compile
+/// a template with `TypstTemplateCollection::compile(template_file_id(name, version))`.
+pub trait TemplateStore: Send + Sync {
+    fn get(&self, name: &str, version: Option<&str>) -> FileResult<StoredTemplate>;
+
+    fn list(&self) -> FileResult<Vec<TemplateInfo>>;
+
+    fn watch(&self, _on_change: OnTemplateChange) -> FileResult<()> {
+        Err(FileError::Other(Some(eco_format!(
+            "Template store does not support watching for changes"
+        ))))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TemplateInfo {
+    pub name: EcoString,
+    pub version: EcoString,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct StoredTemplate {
+    pub info: TemplateInfo,
+    pub source: String,
+}
+
+/// `FileId` of the template `name` in `version` (latest version, if `None`). Imports
+/// inside the templates (`#import "header.typ"`) resolve to the latest version.
+/// `@` and `%` in `name` are escaped (`%40`, `%25`), so the version can be separated from
+/// names, that contain `@`.
+pub fn template_file_id(name: &str, version: Option<&str>) -> FileId {
+    let name = name.replace('%', "%25").replace('@', "%40");
+    let path = match version {
+        Some(version) => eco_format!("{name}@{version}"),
+        None => name.into(),
+    };
+    FileId::new(None, VirtualPath::new(path.as_str()))
+}
+
+/// File resolver, that resolves the files of a `TemplateStore`. See `template_file_id`.
+#[derive(Clone)]
+pub struct TemplateStoreResolver(pub Arc<dyn TemplateStore>);
+
+impl TemplateStoreResolver {
+    fn get(&self, id: FileId) -> FileResult<StoredTemplate> {
+        if id.package().is_some() {
+            return Err(not_found(id));
+        }
+        let TemplateStoreResolver(store) = self;
+        let path = id.vpath().as_rootless_path().to_string_lossy();
+        // `@` in names is escaped, see `template_file_id`.
+        let (name, version) = match path.split_once('@') {
+            Some((name, version)) => (name, Some(version)),
+            None => (path.as_ref(), None),
+        };
+        let name = name.replace("%40", "@").replace("%25", "%");
+        store.get(&name, version)
+    }
+}
+
+impl FileResolver for TemplateStoreResolver {
+    fn resolve_binary(&self, id: FileId) -> FileResult<Cow<'_, Bytes>> {
+        let template = self.get(id)?;
+        Ok(Cow::Owned(Bytes::from(template.source.into_bytes())))
+    }
+
+    fn resolve_source(&self, id: FileId) -> FileResult<Cow<'_, Source>> {
+        let template = self.get(id)?;
+        Ok(Cow::Owned(Source::new(id, template.source)))
+    }
+}
+
+/// Versions of a template with their sources, the latest last.
+type TemplateVersions = Vec<(EcoString, String)>;
+
+/// `TemplateStore`, that keeps all versions in memory. The latest version of a template
+/// is the one, that was inserted last. Cloning is cheap, all clones share the same templates.
+#[derive(Clone, Default)]
+pub struct InMemoryTemplateStore {
+    templates: Arc<RwLock<HashMap<EcoString, TemplateVersions>>>,
+    watchers: Arc<RwLock<Vec<Arc<OnTemplateChange>>>>,
+}
+
+impl InMemoryTemplateStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a version of a template. Replaces the source, if the version already exists.
+    pub fn insert<N, V, S>(&self, name: N, version: V, source: S)
+    where
+        N: Into<EcoString>,
+        V: Into<EcoString>,
+        S: Into<String>,
+    {
+        let info = TemplateInfo {
+            name: name.into(),
+            version: version.into(),
+        };
+        if let Ok(mut templates) = self.templates.write() {
+            let versions = templates.entry(info.name.clone()).or_default();
+            versions.retain(|(version, _)| *version != info.version);
+            versions.push((info.version.clone(), source.into()));
+        }
+        // Not called under the lock, so callbacks can call `watch`.
+        let watchers = match self.watchers.read() {
+            Ok(watchers) => watchers.clone(),
+            Err(_) => return,
+        };
+        watchers.iter().for_each(|on_change| on_change(&info));
+    }
+}
+
+impl TemplateStore for InMemoryTemplateStore {
+    fn get(&self, name: &str, version: Option<&str>) -> FileResult<StoredTemplate> {
+        let not_found = || {
+            FileError::NotFound(
+                template_file_id(name, version)
+                    .vpath()
+                    .as_rootless_path()
+                    .into(),
+            )
+        };
+        let templates = self.templates.read().map_err(|_| lock_error())?;
+        let versions = templates.get(name).ok_or_else(not_found)?;
+        let (version, source) = match version {
+            Some(version) => versions.iter().find(|(v, _)| v == version),
+            None => versions.last(),
+        }
+        .ok_or_else(not_found)?;
+        Ok(StoredTemplate {
+            info: TemplateInfo {
+                name: name.into(),
+                version: version.clone(),
+            },
+            source: source.clone(),
+        })
+    }
+
+    fn list(&self) -> FileResult<Vec<TemplateInfo>> {
+        let templates = self.templates.read().map_err(|_| lock_error())?;
+        let mut list: Vec<TemplateInfo> = templates
+            .iter()
+            .filter_map(|(name, versions)| {
+                let (version, _) = versions.last()?;
+                Some(TemplateInfo {
+                    name: name.clone(),
+                    version: version.clone(),
+                })
+            })
+            .collect();
+        list.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(list)
+    }
+
+    fn watch(&self, on_change: OnTemplateChange) -> FileResult<()> {
+        let mut watchers = self.watchers.write().map_err(|_| lock_error())?;
+        watchers.push(Arc::new(on_change));
+        Ok(())
+    }
+}
+
+fn lock_error() -> FileError {
+    FileError::Other(Some(eco_format!("Could not lock template store")))
+}
@@ -0,0 +1,87 @@
+use ecow::EcoString;
+use typst::{
+    layout::{Abs, Frame, FrameItem, Point},
+    model::Document,
+};
+
+use crate::document_ext::FrameExt;
+
+/// A run of text on a page, as typst laid it out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PositionedText {
+    pub text: EcoString,
+    /// Start of the baseline, relative to the top left corner of the page.
+    pub position: Point,
+    /// Width of the run (without rotations or scales of the surrounding groups).
+    pub width: Abs,
+    pub font_size: Abs,
+}
+
+/// Plain text of each page of `document`, e.g. for search indexing or to check generated
+/// content in tests without parsing pdfs. Runs on a new baseline start a new line, runs
+/// with a gap between them are separated by a space. The text is in paint order, which
+/// is the reading order for most documents, but not e.g. for text placed with `place`.
+///
+/// ```rust,ignore
+/// let pages = extract_text(&document);
+/// assert!(pages[0].contains("Invoice 2024-001"));
+/// ```
+pub fn extract_text(document: &Document) -> Vec<String> {
+    document
+        .pages
+        .iter()
+        .map(|page| frame_text(&page.frame))
+        .collect()
+}
+
+/// Plain text of `frame`, see `extract_text`.
+pub fn frame_text(frame: &Frame) -> String {
+    join_runs(&frame_runs(frame))
+}
+
+/// The text runs of each page of `document` with their positions.
+pub fn extract_text_with_positions(document: &Document) -> Vec<Vec<PositionedText>> {
+    document
+        .pages
+        .iter()
+        .map(|page| frame_runs(&page.frame))
+        .collect()
+}
+
+fn frame_runs(frame: &Frame) -> Vec<PositionedText> {
+    frame
+        .leaf_items()
+        .into_iter()
+        .filter_map(|(ts, pos, item)| match item {
+            FrameItem::Text(text) if !text.text.is_empty() => Some(PositionedText {
+                text: text.text.clone(),
+                position: pos.transform(ts),
+                width: text.width(),
+                font_size: text.size,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+fn join_runs(runs: &[PositionedText]) -> String {
+    let mut text = String::new();
+    let mut previous: Option<&PositionedText> = None;
+    for run in runs {
+        if let Some(previous) = previous {
+            let line_height = previous.font_size.max(run.font_size);
+            let gap = run.position.x - (previous.position.x + previous.width);
+            if (run.position.y - previous.position.y).abs() > line_height / 2.0 {
+                text.push('\n');
+            } else if gap > run.font_size * 0.1
+                && !text.ends_with(char::is_whitespace)
+                && !run.text.starts_with(char::is_whitespace)
+            {
+                text.push(' ');
+            }
+        }
+        text.push_str(&run.text);
+        previous = Some(run);
+    }
+    text
+}
@@ -0,0 +1,60 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use ecow::{eco_format, EcoString};
+use typst::syntax::Span;
+
+use crate::{compile_output::CompileOutput, TypstTemplateCollection};
+
+/// Number of profiled compiles, that are running.
+static PROFILING: AtomicUsize = AtomicUsize::new(0);
+
+/// Result of `TypstTemplateCollection::compile_with_profile`.
+#[derive(Debug, Clone)]
+pub struct CompileProfile {
+    pub output: CompileOutput,
+    /// The timings of typst (layout, show rules, ...) as JSON in the Chrome trace event format,
+    /// like `typst compile --timings` writes it. Open it with `chrome://tracing`, Perfetto or
+    /// speedscope.
+    pub timings: Result<String, EcoString>,
+}
+
+/// The timer of typst is global: compiles, that run at the same time as a profiled compile,
+/// are part of the profile.
+pub(crate) fn profile<C>(collection: &TypstTemplateCollection, compile: C) -> CompileProfile
+where
+    C: FnOnce() -> CompileOutput,
+{
+    if PROFILING.fetch_add(1, Ordering::SeqCst) == 0 {
+        typst_timing::clear();
+    }
+    typst_timing::enable();
+    let output = compile();
+    let mut json = Vec::new();
+    let timings = typst_timing::export_json(&mut json, |span| source_location(collection, span))
+        .map_err(EcoString::from)
+        .and_then(|()| String::from_utf8(json).map_err(|error| eco_format!("{error}")));
+    if PROFILING.fetch_sub(1, Ordering::SeqCst) == 1 {
+        typst_timing::clear();
+    }
+    CompileProfile { output, timings }
+}
+
+/// Typst can't disable the timer again. Drop the events of compiles, that are not profiled,
+/// so they don't pile up.
+pub(crate) fn clear_unless_profiling() {
+    if typst_timing::is_enabled() && PROFILING.load(Ordering::SeqCst) == 0 {
+        typst_timing::clear();
+    }
+}
+
+fn source_location(collection: &TypstTemplateCollection, span: Span) -> (String, u32) {
+    let location = span.id().and_then(|id| {
+        let source = collection.resolve_source(id).ok()?;
+        let line = source
+            .range(span)
+            .and_then(|range| source.byte_to_line(range.start))
+            .map_or(0, |line| line as u32 + 1);
+        Some((format!("{id:?}"), line))
+    });
+    location.unwrap_or_else(|| ("unknown".to_string(), 0))
+}
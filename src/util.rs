@@ -1,5 +1,8 @@
+use ecow::EcoString;
 use typst::{
     diag::{FileError, FileResult},
+    foundations::{Dict, Value},
+    layout::{FrameItem, Point, Size, Transform},
     syntax::{FileId, Source},
 };
 
@@ -13,3 +16,123 @@ pub(crate) fn bytes_to_source(id: FileId, bytes: &[u8]) -> FileResult<Source> {
     let contents = contents.trim_start_matches('\u{feff}');
     Ok(Source::new(id, contents.to_owned()))
 }
+
+/// `rows` as CSV (RFC 4180). Fields with commas, quotes or line breaks are quoted.
+pub(crate) fn csv_bytes<I, R, S>(rows: I) -> Vec<u8>
+where
+    I: IntoIterator<Item = R>,
+    R: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut csv = String::new();
+    for row in rows {
+        for (i, field) in row.into_iter().enumerate() {
+            if i > 0 {
+                csv.push(',');
+            }
+            let field = field.as_ref();
+            if field.contains([',', '"', '\n', '\r']) {
+                csv.push('"');
+                csv.push_str(&field.replace('"', "\"\""));
+                csv.push('"');
+            } else {
+                csv.push_str(field);
+            }
+        }
+        csv.push_str("\r\n");
+    }
+    csv.into_bytes()
+}
+
+/// Stable string representation of a `FileId`, e.g. for keys in persistent caches.
+#[cfg(feature = "sqlite")]
+pub(crate) fn file_id_key(id: FileId) -> String {
+    let path = id.vpath().as_rooted_path().to_string_lossy();
+    match id.package() {
+        Some(package) => format!("{package}{path}"),
+        None => path.into_owned(),
+    }
+}
+
+/// Path of a file as shown to users, prefixed with the package, if it is part of one.
+pub(crate) fn display_path(id: FileId) -> String {
+    let vpath = id.vpath();
+    match id.package() {
+        Some(package) => format!("{package}{}", vpath.as_rooted_path().display()),
+        None => vpath.as_rootless_path().display().to_string(),
+    }
+}
+
+/// Strips the module paths from a type name (`a::B<c::D>` -> `B<D>`).
+pub(crate) fn short_type_name(name: &str) -> EcoString {
+    let mut short = EcoString::new();
+    let mut segment = String::new();
+    for c in name.chars() {
+        if c.is_alphanumeric() || c == '_' || c == ':' {
+            segment.push(c);
+            continue;
+        }
+        short.push_str(segment.rsplit("::").next().unwrap_or_default());
+        segment.clear();
+        short.push(c);
+    }
+    short.push_str(segment.rsplit("::").next().unwrap_or_default());
+    short
+}
+
+/// Deep merges `overrides` into `base`. Nested dictionaries are merged, all other values
+/// of `overrides` replace the ones in `base`.
+pub(crate) fn merge_dicts(mut base: Dict, overrides: Dict) -> Dict {
+    for (key, value) in overrides {
+        let merged = match (base.get(&key).ok(), value) {
+            (Some(Value::Dict(base_dict)), Value::Dict(override_dict)) => {
+                Value::Dict(merge_dicts(base_dict.clone(), override_dict))
+            }
+            (_, value) => value,
+        };
+        base.insert(key, merged);
+    }
+    base
+}
+
+/// Top left corner and size of a leaf item of a frame. Text is approximated by its font size.
+pub(crate) fn item_box(pos: Point, item: &FrameItem) -> Option<(Point, Size)> {
+    match item {
+        // `pos` is on the baseline.
+        FrameItem::Text(text) => Some((
+            Point::new(pos.x, pos.y - text.size),
+            Size::new(text.width(), text.size),
+        )),
+        FrameItem::Shape(shape, _) => Some((pos, shape.geometry.bbox_size())),
+        FrameItem::Image(_, size, _) => Some((pos, *size)),
+        FrameItem::Link(_, size) => Some((pos, *size)),
+        FrameItem::Group(_) | FrameItem::Tag(_) => None,
+    }
+}
+
+/// The corners of the box at `pos` with `size` after applying `ts`.
+pub(crate) fn transformed_corners(pos: Point, size: Size, ts: Transform) -> [Point; 4] {
+    [
+        pos,
+        pos + Point::with_x(size.x),
+        pos + Point::with_y(size.y),
+        pos + size.to_point(),
+    ]
+    .map(|corner| corner.transform(ts))
+}
+
+/// Run `f` on the blocking thread pool of tokio. Panics of `f` are passed on.
+#[cfg(feature = "async")]
+pub(crate) async fn spawn_blocking<F, T>(f: F) -> T
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    match tokio::task::spawn_blocking(f).await {
+        Ok(output) => output,
+        Err(err) => match err.try_into_panic() {
+            Ok(panic) => std::panic::resume_unwind(panic),
+            Err(err) => panic!("Compile task did not finish: {err}"),
+        },
+    }
+}
@@ -0,0 +1,87 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, RwLock},
+};
+
+use ecow::eco_format;
+use typst::{
+    diag::{FileError, FileResult},
+    foundations::Bytes,
+    syntax::{package::PackageSpec, FileId, Source, VirtualPath},
+};
+
+use crate::{
+    file_resolver::FileResolver,
+    util::{bytes_to_source, not_found},
+};
+
+/// Packages, that only exist in memory, e.g. a company branding package, that is imported with
+/// `#import "@mycompany/branding:1.0.0"`. Add them with
+/// `TypstTemplateCollection::with_virtual_package`, so they are resolved before any other
+/// package resolver. Cloning is cheap, all clones share the same packages.
+#[derive(Debug, Clone, Default)]
+pub struct VirtualPackages {
+    files: Arc<RwLock<HashMap<FileId, Bytes>>>,
+}
+
+impl VirtualPackages {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the package `spec` with `files` (path inside the package and content), e.g.
+    /// `[("lib.typ", LIB), ("logo.svg", LOGO)]`. If there is no `typst.toml`, a manifest
+    /// with `lib.typ` as entrypoint is added. Replaces the package, if it already exists.
+    pub fn add_package<I, P, B>(&self, spec: PackageSpec, files: I)
+    where
+        I: IntoIterator<Item = (P, B)>,
+        P: AsRef<Path>,
+        B: AsRef<[u8]>,
+    {
+        let mut package: HashMap<FileId, Bytes> = files
+            .into_iter()
+            .map(|(path, content)| {
+                let id = FileId::new(Some(spec.clone()), VirtualPath::new(path));
+                (id, Bytes::from(content.as_ref().to_vec()))
+            })
+            .collect();
+        let manifest_id = FileId::new(Some(spec.clone()), VirtualPath::new("typst.toml"));
+        package.entry(manifest_id).or_insert_with(|| {
+            let manifest = eco_format!(
+                "[package]\nname = \"{}\"\nversion = \"{}\"\nentrypoint = \"lib.typ\"\n",
+                spec.name,
+                spec.version
+            );
+            Bytes::from(manifest.as_bytes().to_vec())
+        });
+        if let Ok(mut files) = self.files.write() {
+            files.retain(|id, _| id.package() != Some(&spec));
+            files.extend(package);
+        }
+    }
+
+    fn get(&self, id: FileId) -> FileResult<Bytes> {
+        let files = self
+            .files
+            .read()
+            .map_err(|_| FileError::Other(Some(eco_format!("Could not lock virtual packages"))))?;
+        files.get(&id).cloned().ok_or_else(|| not_found(id))
+    }
+}
+
+impl FileResolver for VirtualPackages {
+    fn resolve_binary(&self, id: FileId) -> FileResult<Cow<'_, Bytes>> {
+        self.get(id).map(Cow::Owned)
+    }
+
+    fn resolve_source(&self, id: FileId) -> FileResult<Cow<'_, Source>> {
+        let bytes = self.get(id)?;
+        bytes_to_source(id, &bytes).map(Cow::Owned)
+    }
+
+    fn is_in_memory(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use ecow::{EcoString, EcoVec};
+use typst::diag::{Severity, SourceDiagnostic};
+
+use crate::{FileIdNewType, TypstAsLibError};
+
+/// Collects the warnings of many compiles (e.g. a batch compiled from the same template)
+/// and deduplicates identical warnings, counting how often each occurred.
+///
+/// ```rust,ignore
+/// let mut aggregator = WarningAggregator::new();
+/// for input in inputs {
+///     let output = template.compile_with_input_detailed(input);
+///     aggregator.add(&output.warnings);
+/// }
+/// for AggregatedWarning { warning, count } in aggregator.warnings() {
+///     log::warn!("{} ({count}x)", warning.message);
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct WarningAggregator {
+    warnings: Vec<AggregatedWarning>,
+    indices: HashMap<SourceDiagnostic, usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AggregatedWarning {
+    pub warning: SourceDiagnostic,
+    /// Number of times the warning occurred.
+    pub count: usize,
+}
+
+impl WarningAggregator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the warnings of one compile.
+    pub fn add<'a, I>(&mut self, warnings: I)
+    where
+        I: IntoIterator<Item = &'a SourceDiagnostic>,
+    {
+        let Self {
+            warnings: aggregated,
+            indices,
+        } = self;
+        for warning in warnings {
+            match indices.get(warning) {
+                Some(&i) => aggregated[i].count += 1,
+                None => {
+                    indices.insert(warning.clone(), aggregated.len());
+                    aggregated.push(AggregatedWarning {
+                        warning: warning.clone(),
+                        count: 1,
+                    });
+                }
+            }
+        }
+    }
+
+    /// The deduplicated warnings, in the order they first occurred.
+    pub fn warnings(&self) -> &[AggregatedWarning] {
+        &self.warnings
+    }
+
+    pub fn into_warnings(self) -> Vec<AggregatedWarning> {
+        self.warnings
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.warnings.is_empty()
+    }
+}
+
+type WarningFilter = Arc<dyn Fn(&SourceDiagnostic) -> bool + Send + Sync + 'static>;
+
+/// Which warnings of a compile are reported and whether warnings fail the compile, see
+/// `TypstTemplateCollection::with_warning_policy`. Warnings, that match any of the
+/// suppressions, are removed. With `deny_warnings`, the remaining warnings are promoted to
+/// errors, e.g. for CI pipelines, that should fail on layout warnings.
+///
+/// ```rust,ignore
+/// let policy = WarningPolicy::new()
+///     .suppress_message("unknown font family")
+///     .suppress_file("/vendor/legacy.typ")
+///     .deny_warnings(true);
+/// let template = template.with_warning_policy(policy);
+/// ```
+#[derive(Clone, Default)]
+pub struct WarningPolicy {
+    filters: Vec<WarningFilter>,
+    deny_warnings: bool,
+}
+
+impl WarningPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Suppress warnings, whose message contains `pattern`.
+    pub fn suppress_message<S>(self, pattern: S) -> Self
+    where
+        S: Into<EcoString>,
+    {
+        let pattern = pattern.into();
+        self.suppress_if(move |warning| warning.message.contains(pattern.as_str()))
+    }
+
+    /// Suppress warnings, that originate in the file `file_id`.
+    pub fn suppress_file<F>(self, file_id: F) -> Self
+    where
+        F: Into<FileIdNewType>,
+    {
+        let FileIdNewType(file_id) = file_id.into();
+        self.suppress_if(move |warning| warning.span.id() == Some(file_id))
+    }
+
+    /// Suppress warnings, for which `filter` returns `true`.
+    pub fn suppress_if<P>(mut self, filter: P) -> Self
+    where
+        P: Fn(&SourceDiagnostic) -> bool + Send + Sync + 'static,
+    {
+        self.filters.push(Arc::new(filter));
+        self
+    }
+
+    /// Fail compiles with `TypstAsLibError::TypstSource`, if warnings remain after the
+    /// suppressions. The warnings become the errors.
+    pub fn deny_warnings(mut self, deny_warnings: bool) -> Self {
+        self.deny_warnings = deny_warnings;
+        self
+    }
+
+    /// `true`, if `warning` is suppressed.
+    pub fn is_suppressed(&self, warning: &SourceDiagnostic) -> bool {
+        self.filters.iter().any(|filter| filter(warning))
+    }
+
+    /// Remove the suppressed warnings and, with `deny_warnings`, turn a successful `output`
+    /// into an error, if warnings remain.
+    pub fn apply<T>(
+        &self,
+        output: &mut Result<T, TypstAsLibError>,
+        warnings: &mut EcoVec<SourceDiagnostic>,
+    ) {
+        if !self.filters.is_empty() {
+            warnings.retain(|warning| !self.is_suppressed(warning));
+        }
+        if self.deny_warnings && output.is_ok() && !warnings.is_empty() {
+            let errors = std::mem::take(warnings)
+                .into_iter()
+                .map(|mut warning| {
+                    warning.severity = Severity::Error;
+                    warning
+                })
+                .collect();
+            *output = Err(TypstAsLibError::TypstSource(errors));
+        }
+    }
+}